@@ -1,39 +1,43 @@
-use wgpu_gp::helpers as ws;
+use wgpu_gp::info::AdapterReport;
 
-use winit::{
-    event::{Event, WindowEvent,},
-    event_loop::{ControlFlow, EventLoop},
-    window::Window,
-};
+const USAGE: &str = "usage: wgpu_info [--json] [--backend LIST]
 
+Reports every adapter on every backend, or on LIST (e.g. vulkan,gl): features, limits against
+the default and downlevel defaults, downlevel capabilities and texture format support. --json
+prints an array with one object per adapter.";
 
-async fn run() {
-    let event_loop = EventLoop::new().unwrap();
-    let window  = Window::new(&event_loop).unwrap();
-    ws::get_wgpu_info(&window).await;
-    window.set_title("WGPU Test");
+fn main() {
     env_logger::init();
-
-    event_loop.set_control_flow(ControlFlow::Poll);
-
-    let _ = event_loop.run(
-        move |event, elwt| {
-            match event {
-                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
-                    log::info!("Window closed");
-                    elwt.exit();
-                },
-                // Event::AboutToWait => {
-                //     log::info!("About to wait");
-                //     window.request_redraw();
-                // },
-                _ => (),
+    let (mut json, mut backends) = (false, wgpu::Backends::all());
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--backend" => {
+                backends = args.next().map_or(wgpu::Backends::empty(), |list| wgpu::Backends::from_comma_list(&list));
+                if backends.is_empty() {
+                    eprintln!("--backend needs a list of known backends\n{}", USAGE);
+                    std::process::exit(2);
+                }
+            }
+            _ => {
+                eprintln!("unknown argument {}\n{}", arg, USAGE);
+                std::process::exit(2);
             }
         }
-    );
-    
-}
+    }
 
-fn main() {
-    pollster::block_on(run());
-}
\ No newline at end of file
+    let reports = AdapterReport::enumerate(backends);
+    if json {
+        let reports: Vec<_> = reports.iter().map(AdapterReport::to_json).collect();
+        println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+    } else {
+        for (i, report) in reports.iter().enumerate() {
+            print!("{}Adapter {}: {}", if i > 0 { "\n" } else { "" }, i, report);
+        }
+    }
+    if reports.is_empty() {
+        eprintln!("no adapters found");
+        std::process::exit(1);
+    }
+}
@@ -1,12 +1,6 @@
 use wgpu::{IndexFormat, PrimitiveTopology, ShaderSource};
 
-use winit::{
-    event::{Event, WindowEvent,},
-    event_loop::EventLoop,
-    window::Window,
-};
-
-use wgpu_gp::helpers as ws;
+use wgpu_gp::{app::Example, helpers as ws};
 
 pub struct Inputs<'a> {
     pub source: ShaderSource<'a>,
@@ -15,66 +9,96 @@ pub struct Inputs<'a> {
 }
 
 impl Inputs<'_> {
-    pub async fn new(&mut self, event_loop: EventLoop<()>, window: Window, 
-    num_vertices: u32) {
-        let init = ws::IWgpuInit::new(&window, 1, None).await;
+    /// A pipeline without vertex buffers that draws `num_vertices` vertices each frame.
+    pub fn into_example(self, init: ws::IWgpuInit<'_>, num_vertices: u32) -> Box<dyn Example + '_> {
         let shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("simple shader"),
-            source: self.source.clone(),
-        });
-        let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("simple pipeline layout"),
-            bind_group_layouts: &[],
-            push_constant_ranges: &[],
+            source: self.source,
         });
-
-        let mut ppl = ws::IRenderPipeline {
-            shader: Some(&shader),
-            pipeline_layout: Some(&pipeline_layout),
-            is_depth_stencil: false,
+        let render_pipeline = create_pipeline(&init, &shader, self.topology, self.strip_index_format);
+        let msaa_view = ws::create_msaa_view(&init);
+        Box::new(State {
+            init,
+            msaa_view,
+            shader,
             topology: self.topology,
             strip_index_format: self.strip_index_format,
-            ..Default::default()
-        };
+            render_pipeline,
+            num_vertices,
+        })
+    }
+}
 
-    let render_pipeline = ppl.new(&init);
+fn create_pipeline(init: &ws::IWgpuInit, shader: &wgpu::ShaderModule, topology: PrimitiveTopology,
+strip_index_format: Option<IndexFormat>) -> wgpu::RenderPipeline {
+    let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("simple pipeline layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
 
-    let _ = event_loop.run(
-        move |event, elwt| {
-            match event {
-                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
-                    log::info!("Window closed");
-                    elwt.exit();
-                },
-                Event::WindowEvent { event: WindowEvent::RedrawRequested, .. } => {
-                    let frame = init.surface.get_current_texture().unwrap();
-                    let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
-                    let mut encoder = init.device.create_command_encoder(
-                        &wgpu::CommandEncoderDescriptor {
-                            label: Some("Render Encoder")
-                        }
-                    );
-                    {
-                        let color_attachment = ws::create_color_attachment(&view);
-                        let mut rpass = encoder.begin_render_pass(
-                            &wgpu::RenderPassDescriptor {
-                                label: None,
-                                color_attachments: &[Some(color_attachment)],
-                                depth_stencil_attachment: None,
-                                occlusion_query_set: None,
-                                timestamp_writes: None,
-                            }
-                        );
-                        rpass.set_pipeline(&render_pipeline);
-                        rpass.draw(0..num_vertices, 0..1);
-                    }
-                    init.queue.submit(std::iter::once(encoder.finish()));
-                    frame.present();
-                }
-                _ => (),
+    let mut ppl = ws::IRenderPipeline {
+        shader: Some(shader),
+        pipeline_layout: Some(&pipeline_layout),
+        is_depth_stencil: false,
+        topology,
+        strip_index_format,
+        ..Default::default()
+    };
+    ppl.new(init)
+}
+
+struct State<'a> {
+    init: ws::IWgpuInit<'a>,
+    msaa_view: Option<wgpu::TextureView>,
+    shader: wgpu::ShaderModule,
+    topology: PrimitiveTopology,
+    strip_index_format: Option<IndexFormat>,
+    render_pipeline: wgpu::RenderPipeline,
+    num_vertices: u32,
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+    }
+
+    fn set_sample_count(&mut self, requested: u32) -> Option<u32> {
+        let count = self.init.set_sample_count(requested);
+        self.render_pipeline = create_pipeline(&self.init, &self.shader, self.topology, self.strip_index_format);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        Some(count)
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let frame = self.init.get_current_frame()?;
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
             }
+        );
+        {
+            let color_attachment = ws::create_frame_color_attachment(&view, self.msaa_view.as_ref());
+            let mut rpass = encoder.begin_render_pass(
+                &wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(color_attachment)],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                }
+            );
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.draw(0..self.num_vertices, 0..1);
         }
-    );
-        
+        self.init.queue.submit(std::iter::once(encoder.finish()));
+        frame.present();
+        Ok(())
     }
-}
\ No newline at end of file
+}
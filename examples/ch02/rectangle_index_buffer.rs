@@ -0,0 +1,141 @@
+use bytemuck::{Pod, Zeroable};
+use std::{iter, mem};
+use wgpu::VertexBufferLayout;
+use wgpu_gp::{app::{self, Example}, helpers as ws, mesh::Mesh};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+
+const VERTICES: &[Vertex] = &[
+    Vertex {
+        position: [-0.5, -0.5],
+        color: [1.0, 0.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, -0.5],
+        color: [0.0, 1.0, 0.0],
+    },
+    Vertex {
+        position: [0.5, 0.5],
+        color: [0.0, 0.0, 1.0],
+    },
+    Vertex {
+        position: [-0.5, 0.5],
+        color: [1.0, 1.0, 0.0],
+    },
+];
+
+const INDICES: &[u32] = &[0, 1, 2, 2, 3, 0];
+
+fn create_pipeline(init: &ws::IWgpuInit, mesh: &Mesh) -> wgpu::RenderPipeline {
+    let shader = init.device.create_shader_module(
+        wgpu::include_wgsl!("triangle_gpu_buffer.wgsl")
+    );
+    let pipeline_layout = init.device.create_pipeline_layout(
+        &wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        }
+    );
+
+    let vertex_buffer_layout = VertexBufferLayout {
+        array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![
+            0 => Float32x2,
+            1 => Float32x3,
+        ],
+    };
+
+    let mut ppl = ws::IRenderPipeline {
+        shader: Some(&shader),
+        pipeline_layout: Some(&pipeline_layout),
+        is_depth_stencil: false,
+        vertex_buffer_layout: &[vertex_buffer_layout],
+        topology: mesh.topology,
+        strip_index_format: mesh.strip_index_format(),
+        ..Default::default()
+    };
+    mesh.validate(&ppl).unwrap();
+    ppl.new(init)
+}
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    msaa_view: Option<wgpu::TextureView>,
+    pipeline: wgpu::RenderPipeline,
+    mesh: Mesh,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>) -> Self {
+        let mesh = Mesh::new(&init, VERTICES, INDICES, wgpu::PrimitiveTopology::TriangleList);
+        let pipeline = create_pipeline(&init, &mesh);
+
+        let msaa_view = ws::create_msaa_view(&init);
+        Self {
+            init,
+            msaa_view,
+            pipeline,
+            mesh,
+        }
+
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+    }
+
+    fn set_sample_count(&mut self, requested: u32) -> Option<u32> {
+        let count = self.init.set_sample_count(requested);
+        self.pipeline = create_pipeline(&self.init, &self.mesh);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        Some(count)
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        {
+            let color_attachment = ws::create_frame_color_attachment(&view, self.msaa_view.as_ref());
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            self.mesh.draw(&mut render_pass);
+        }
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+pub fn example<'a>(init: ws::IWgpuInit<'a>, _args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    init.set_title("Chapter 02: Index buffer");
+    Ok(Box::new(State::new(init)))
+}
+
+fn main() {
+    app::run(example);
+}
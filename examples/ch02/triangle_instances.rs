@@ -0,0 +1,244 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Matrix4, Vector3};
+use std::{iter, mem, time::Duration};
+use wgpu::{util::DeviceExt, VertexBufferLayout};
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+use wgpu_gp::{
+    app::{self, Example},
+    helpers as ws,
+    instance::{InstanceBuffer, InstanceData, InstanceRaw},
+    mesh::Mesh,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    position: [f32; 2],
+}
+
+const VERTICES: &[Vertex] = &[
+    Vertex { position: [0.0, 0.5] },
+    Vertex { position: [-0.433, -0.25] },
+    Vertex { position: [0.433, -0.25] },
+];
+
+const INDICES: &[u32] = &[0, 1, 2];
+
+const COLUMNS: u32 = 256;
+
+fn hue(t: f32) -> [f32; 4] {
+    let channel = |offset: f32| 0.5 + 0.5 * (std::f32::consts::TAU * (t + offset)).cos();
+    [channel(0.0), channel(1.0 / 3.0), channel(2.0 / 3.0), 1.0]
+}
+
+/// A `COLUMNS` x `rows` grid of triangles covering the whole clip space.
+fn grid(rows: u32) -> Vec<InstanceRaw> {
+    let (dx, dy) = (2.0 / COLUMNS as f32, 2.0 / rows as f32);
+    let mut instances = Vec::with_capacity((COLUMNS * rows) as usize);
+    for row in 0..rows {
+        for col in 0..COLUMNS {
+            let x = -1.0 + dx * (col as f32 + 0.5);
+            let y = -1.0 + dy * (row as f32 + 0.5);
+            let model = Matrix4::from_translation(Vector3::new(x, y, 0.0))
+                * Matrix4::from_nonuniform_scale(dx * 1.6, dy * 1.6, 1.0);
+            let mut instance = InstanceRaw::new(model, hue(col as f32 / COLUMNS as f32));
+            instance.data = [(row * COLUMNS + col) as f32 * 0.1, 1.0 + (col % 7) as f32 * 0.25, 0.0, 0.0];
+            instances.push(instance);
+        }
+    }
+    instances
+}
+
+fn create_pipeline(init: &ws::IWgpuInit, time_bind_group_layout: &wgpu::BindGroupLayout, mesh: &Mesh) -> wgpu::RenderPipeline {
+    let shader = init.device.create_shader_module(
+        wgpu::include_wgsl!("triangle_instances.wgsl")
+    );
+
+    let pipeline_layout = init.device.create_pipeline_layout(
+        &wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[time_bind_group_layout],
+            push_constant_ranges: &[],
+        }
+    );
+
+    let vertex_buffer_layout = VertexBufferLayout {
+        array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+    };
+
+    let mut ppl = ws::IRenderPipeline {
+        shader: Some(&shader),
+        pipeline_layout: Some(&pipeline_layout),
+        is_depth_stencil: false,
+        vertex_buffer_layout: &[vertex_buffer_layout, InstanceRaw::vertex_buffer_layout()],
+        topology: mesh.topology,
+        strip_index_format: mesh.strip_index_format(),
+        ..Default::default()
+    };
+    mesh.validate(&ppl).unwrap();
+    ppl.new(init)
+}
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    msaa_view: Option<wgpu::TextureView>,
+    pipeline: wgpu::RenderPipeline,
+    mesh: Mesh,
+    rows: u32,
+    instances: Vec<InstanceRaw>,
+    instance_buffer: InstanceBuffer<InstanceRaw>,
+    time_buffer: wgpu::Buffer,
+    time_bind_group_layout: wgpu::BindGroupLayout,
+    time_bind_group: wgpu::BindGroup,
+    /// Seconds of animation so far.
+    time: f32,
+    frame: u32,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>) -> Self {
+        let time_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Time Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[0.0f32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let time_bind_group_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Time Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let time_bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Time Bind Group"),
+            layout: &time_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: time_buffer.as_entire_binding() }],
+        });
+
+        let mesh = Mesh::new(&init, VERTICES, INDICES, wgpu::PrimitiveTopology::TriangleList);
+        let rows = 160;
+        let instances = grid(rows);
+        let instance_buffer = InstanceBuffer::new(&init, &instances);
+
+        let pipeline = create_pipeline(&init, &time_bind_group_layout, &mesh);
+
+        let msaa_view = ws::create_msaa_view(&init);
+        Self {
+            init,
+            msaa_view,
+            pipeline,
+            mesh,
+            rows,
+            instances,
+            instance_buffer,
+            time_buffer,
+            time_bind_group_layout,
+            time_bind_group,
+            time: 0.0,
+            frame: 0,
+        }
+
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+    }
+
+    fn set_sample_count(&mut self, requested: u32) -> Option<u32> {
+        let count = self.init.set_sample_count(requested);
+        self.pipeline = create_pipeline(&self.init, &self.time_bind_group_layout, &self.mesh);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        Some(count)
+    }
+
+    /// Arrow up/down doubles or halves the number of rows.
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput {
+            event: KeyEvent { physical_key: PhysicalKey::Code(code), state: ElementState::Pressed, .. },
+            ..
+        } = event else {
+            return false;
+        };
+        let rows = match code {
+            KeyCode::ArrowUp => (self.rows * 2).min(4096),
+            KeyCode::ArrowDown => (self.rows / 2).max(1),
+            _ => return false,
+        };
+        self.rows = rows;
+        self.instances = grid(rows);
+        if self.instance_buffer.set(&self.init, &self.instances) {
+            log::info!("instance buffer grown to {} instances", self.instance_buffer.capacity());
+        }
+        self.init.set_title(&format!("Chapter 02: {} instanced triangles", self.instances.len()));
+        true
+    }
+
+    /// Recolors a single row per frame through a partial buffer write.
+    fn update(&mut self, dt: Duration) {
+        self.time += dt.as_secs_f32();
+        let time = self.time;
+        self.init.queue.write_buffer(&self.time_buffer, 0, bytemuck::cast_slice(&[time, 0.0, 0.0, 0.0]));
+
+        let row = self.frame % self.rows;
+        let first = (row * COLUMNS) as usize;
+        let row_instances = &mut self.instances[first..first + COLUMNS as usize];
+        for (col, instance) in row_instances.iter_mut().enumerate() {
+            instance.color = hue(col as f32 / COLUMNS as f32 + time * 0.1);
+        }
+        self.instance_buffer.write(&self.init, first as u32, row_instances).unwrap();
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        {
+            let color_attachment = ws::create_frame_color_attachment(&view, self.msaa_view.as_ref());
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.time_bind_group, &[]);
+            self.instance_buffer.draw(&mut render_pass, &self.mesh);
+        }
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+pub fn example<'a>(init: ws::IWgpuInit<'a>, _args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    init.set_title("Chapter 02: Instanced triangles");
+    Ok(Box::new(State::new(init)))
+}
+
+fn main() {
+    app::run(example);
+}
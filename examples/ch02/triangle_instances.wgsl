@@ -0,0 +1,38 @@
+struct VertexInput {
+    @location(0) pos: vec2f,
+};
+
+struct InstanceInput {
+    @location(4) model_0: vec4f,
+    @location(5) model_1: vec4f,
+    @location(6) model_2: vec4f,
+    @location(7) model_3: vec4f,
+    @location(8) color: vec4f,
+    // x initial angle, y angular speed
+    @location(9) data: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
+};
+
+@group(0) @binding(0) var<uniform> time: vec4f;
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4f(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let angle = instance.data.x + instance.data.y * time.x;
+    let c = cos(angle);
+    let s = sin(angle);
+    let pos = vec2f(c * in.pos.x - s * in.pos.y, s * in.pos.x + c * in.pos.y);
+    var output: VertexOutput;
+    output.position = model * vec4f(pos, 0.0, 1.0);
+    output.color = instance.color;
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return in.color;
+}
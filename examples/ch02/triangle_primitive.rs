@@ -1,20 +1,13 @@
 pub mod common;
 use common::Inputs;
-use winit::{
-    event_loop::{ControlFlow, EventLoop},
-    window::Window,
-};
 use std::borrow::Cow;
+use wgpu_gp::{app::{self, Example}, helpers as ws};
 
-fn main() {
-    let mut primitive_type="traingle-list";
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        primitive_type = &args[1];
-    }
+pub fn example<'a>(init: ws::IWgpuInit<'a>, args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    let primitive_type = args.first().map_or("traingle-list", String::as_str);
     let mut topology = wgpu::PrimitiveTopology::TriangleList;
     let mut index_format = None;
-    match &primitive_type[..] {
+    match primitive_type {
         "triangle-strip" => {
             topology = wgpu::PrimitiveTopology::TriangleStrip;
             index_format = Some(wgpu::IndexFormat::Uint32);
@@ -27,20 +20,18 @@ fn main() {
         },
         _ => {},
     }
-    let event_loop = EventLoop::new().unwrap();
-    let window  = Window::new(&event_loop).unwrap();
-    window.set_title(&*format!("{}{}", "Chapter 02: primitive type: ", primitive_type));
-    env_logger::init();
-    
-    event_loop.set_control_flow(ControlFlow::Poll);
-    
-    let mut inputs = Inputs {
+    init.set_title(&format!("{}{}", "Chapter 02: primitive type: ", primitive_type));
+
+    let inputs = Inputs {
         source: wgpu::ShaderSource::Wgsl(
             Cow::Borrowed(include_str!("triangle_primitive.wgsl"))
         ),
         topology,
         strip_index_format: index_format,
     };
+    Ok(inputs.into_example(init, 9))
+}
 
-    pollster::block_on(inputs.new(event_loop, window, 9));
-}
\ No newline at end of file
+fn main() {
+    app::run(example);
+}
@@ -0,0 +1,201 @@
+use std::{iter, time::Duration};
+use cgmath::{Deg, Point3, Vector3};
+use winit::{
+    event::{DeviceEvent, WindowEvent},
+};
+use wgpu_gp::{
+    app::{self, Example},
+    camera::{Camera, CameraBinding},
+    controller::{ArcballController, CameraController, FlyController, OrbitController},
+    depth::{self, DepthConfig},
+    geometry::{Geometry, Vertex}, helpers as ws, mesh::Mesh,
+};
+
+/// With `outline`, the shape also sets the stencil to the pass's reference value wherever it is
+/// drawn.
+fn create_pipeline(init: &ws::IWgpuInit, camera_binding: &CameraBinding, outline: bool) -> wgpu::RenderPipeline {
+    let shader = init.device.create_shader_module(
+        wgpu::include_wgsl!("shaded.wgsl")
+    );
+
+    let pipeline_layout = init.device.create_pipeline_layout(
+        &wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&camera_binding.bind_group_layout],
+            push_constant_ranges: &[],
+        }
+    );
+
+    let mut ppl = ws::IRenderPipeline {
+        shader: Some(&shader),
+        pipeline_layout: Some(&pipeline_layout),
+        vertex_buffer_layout: &[Vertex::vertex_buffer_layout()],
+        ..Default::default()
+    };
+    if outline {
+        ppl.stencil = depth::stencil_state(wgpu::CompareFunction::Always, wgpu::StencilOperation::Replace);
+    }
+    ppl.new(init)
+}
+
+/// Draws the shape scaled up where the stencil was not marked, on top of everything, leaving a
+/// rim around the silhouette.
+fn create_outline_pipeline(init: &ws::IWgpuInit, camera_binding: &CameraBinding) -> wgpu::RenderPipeline {
+    let shader = init.device.create_shader_module(wgpu::include_wgsl!("outline.wgsl"));
+    let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Outline Pipeline Layout"),
+        bind_group_layouts: &[&camera_binding.bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let mut ppl = ws::IRenderPipeline {
+        shader: Some(&shader),
+        pipeline_layout: Some(&pipeline_layout),
+        vertex_buffer_layout: &[Vertex::vertex_buffer_layout()],
+        depth_compare: Some(wgpu::CompareFunction::Always),
+        depth_write_enabled: false,
+        stencil: depth::stencil_state(wgpu::CompareFunction::NotEqual, wgpu::StencilOperation::Keep),
+        ..Default::default()
+    };
+    ppl.new(init)
+}
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    msaa_view: Option<wgpu::TextureView>,
+    pipeline: wgpu::RenderPipeline,
+    outline_pipeline: Option<wgpu::RenderPipeline>,
+    mesh: Mesh,
+    camera: Camera,
+    camera_binding: CameraBinding,
+    depth_view: wgpu::TextureView,
+    controller: Box<dyn CameraController>,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>, geometry: Geometry, controller: &str, outline: bool) -> Self {
+        let mut camera = Camera::perspective(Deg(45.0), init.aspect_ratio().unwrap_or(1.0), 0.1, 100.0);
+        camera.position = Point3::new(3.0, 2.0, 3.0);
+        camera.look_at(Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        let camera_binding = CameraBinding::new(&init, &camera);
+        let target = Point3::new(0.0, 0.0, 0.0);
+        let controller: Box<dyn CameraController> = match controller {
+            "arcball" => Box::new(ArcballController::from_camera(&camera, target, init.size)),
+            "fly" => Box::new(FlyController::from_camera(&camera, 2.0)),
+            _ => Box::new(OrbitController::from_camera(&camera, target)),
+        };
+
+        let pipeline = create_pipeline(&init, &camera_binding, outline);
+        let outline_pipeline = outline.then(|| create_outline_pipeline(&init, &camera_binding));
+
+        let mesh = geometry.to_mesh(&init);
+        let depth_view = ws::create_depth_view(&init);
+
+        let msaa_view = ws::create_msaa_view(&init);
+        Self {
+            init,
+            msaa_view,
+            pipeline,
+            outline_pipeline,
+            mesh,
+            camera,
+            camera_binding,
+            depth_view,
+            controller,
+        }
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        self.camera.resize(&self.init);
+        self.depth_view = ws::create_depth_view(&self.init);
+    }
+
+    fn set_sample_count(&mut self, requested: u32) -> Option<u32> {
+        let count = self.init.set_sample_count(requested);
+        self.pipeline = create_pipeline(&self.init, &self.camera_binding, self.outline_pipeline.is_some());
+        if self.outline_pipeline.is_some() {
+            self.outline_pipeline = Some(create_outline_pipeline(&self.init, &self.camera_binding));
+        }
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        self.depth_view = ws::create_depth_view(&self.init);
+        Some(count)
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.controller.process_window_event(event)
+    }
+
+    fn device_event(&mut self, event: &DeviceEvent) {
+        self.controller.process_device_event(event);
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.controller.update(&mut self.camera, dt);
+        self.camera_binding.update(&self.init, &self.camera);
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        {
+            let color_attachment = ws::create_frame_color_attachment(&view, self.msaa_view.as_ref());
+            let depth_attachment = ws::create_depth_stencil_attachment(&self.init, &self.depth_view);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: Some(depth_attachment),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            render_pass.set_stencil_reference(1);
+            self.mesh.draw(&mut render_pass);
+            if let Some(outline_pipeline) = &self.outline_pipeline {
+                render_pass.set_pipeline(outline_pipeline);
+                self.mesh.draw(&mut render_pass);
+            }
+        }
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+/// Arguments: shape, controller, and `outline` to draw a stencil outline around the shape.
+pub fn example<'a>(mut init: ws::IWgpuInit<'a>, args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    let shape = args.first().map_or("cube", String::as_str);
+    let controller = args.get(1).map_or("orbit", String::as_str);
+    let outline = args.get(2).is_some_and(|arg| arg == "outline");
+    if outline && !init.depth.has_stencil() {
+        init.set_depth(DepthConfig { format: wgpu::TextureFormat::Depth24PlusStencil8, ..init.depth })?;
+    }
+    let geometry = match shape {
+        "uv-sphere" => Geometry::uv_sphere(1.0, 32, 16),
+        "icosphere" => Geometry::icosphere(1.0, 3),
+        "cylinder" => Geometry::cylinder(0.7, 1.5, 32, 1, true),
+        "cone" => Geometry::cone(0.8, 1.5, 32, 1, true),
+        "torus" => Geometry::torus(0.8, 0.3, 24, 48),
+        "plane" => Geometry::plane(2.0, 2.0, 4, 4),
+        "capsule" => Geometry::capsule(0.5, 1.0, 32, 8),
+        _ => Geometry::cube(1.5, 1),
+    };
+    init.set_title(&format!("{}{}", "Chapter 03: camera: ", shape));
+    Ok(Box::new(State::new(init, geometry, controller, outline)))
+}
+
+fn main() {
+    app::run(example);
+}
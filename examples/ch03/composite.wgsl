@@ -0,0 +1,9 @@
+// Appended to fullscreen::FULLSCREEN_WGSL and drawn as instanced quads.
+
+@group(0) @binding(0) var uImage: texture_2d<f32>;
+@group(0) @binding(1) var uSampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return textureSample(uImage, uSampler, in.uv);
+}
@@ -0,0 +1,23 @@
+// Appended to fullscreen::FULLSCREEN_WGSL and drawn as an instanced quad.
+
+// Bound as an unfilterable float texture rather than `texture_depth_2d`, which the GL backend
+// can only read through comparison samplers.
+@group(0) @binding(0) var uDepth: texture_2d<f32>;
+
+fn load_depth(uv: vec2f) -> f32 {
+    let size = textureDimensions(uDepth);
+    let texel = min(vec2u(uv * vec2f(size)), size - 1u);
+    return textureLoad(uDepth, texel, 0).r;
+}
+
+// Near surfaces bright, the cleared far plane black.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(vec3f(1.0 - load_depth(in.uv)), 1.0);
+}
+
+// Reversed-Z already stores near as 1.
+@fragment
+fn fs_reversed(in: VertexOutput) -> @location(0) vec4f {
+    return vec4f(vec3f(load_depth(in.uv)), 1.0);
+}
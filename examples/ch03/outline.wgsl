@@ -0,0 +1,23 @@
+struct Camera {
+    view: mat4x4f,
+    projection: mat4x4f,
+    view_projection: mat4x4f,
+    inverse_view_projection: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+// The shapes are centered on the origin, so scaling about it grows them evenly; only the rim
+// outside the stencil mark of the unscaled shape is drawn.
+const SCALE: f32 = 1.08;
+
+@vertex
+fn vs_main(@location(0) position: vec3f) -> @builtin(position) vec4f {
+    return camera.view_projection * vec4f(position * SCALE, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4f {
+    return vec4f(1.0, 0.6, 0.1, 1.0);
+}
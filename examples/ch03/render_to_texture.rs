@@ -0,0 +1,255 @@
+use std::{iter, time::Duration};
+use cgmath::{Deg, Point3, Vector3};
+use winit::{
+    event::{DeviceEvent, WindowEvent},
+};
+use wgpu_gp::{
+    app::{self, Example},
+    camera::{Camera, CameraBinding},
+    controller::{CameraController, OrbitController},
+    fullscreen::{FullscreenPassDescriptor, Quad, QuadPass},
+    geometry::{Geometry, Vertex}, helpers as ws, mesh::Mesh,
+    render_target::{self as rt, RenderTarget, RenderTargetDescriptor, TargetSize},
+};
+
+const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    scene_pipeline: wgpu::RenderPipeline,
+    inset_pipeline: wgpu::RenderPipeline,
+    composite: QuadPass,
+    depth_panel: QuadPass,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    meshes: Vec<Mesh>,
+    camera: Camera,
+    camera_binding: CameraBinding,
+    top_camera_binding: CameraBinding,
+    controller: OrbitController,
+    main_target: RenderTarget,
+    inset_target: RenderTarget,
+    main_texture: wgpu::BindGroup,
+    inset_texture: wgpu::BindGroup,
+    inset_depth: wgpu::BindGroup,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>) -> Self {
+        let mut camera = Camera::perspective(Deg(45.0), init.aspect_ratio().unwrap_or(1.0), 0.1, 100.0);
+        camera.position = Point3::new(4.0, 3.0, 4.0);
+        camera.look_at(Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        let camera_binding = CameraBinding::new(&init, &camera);
+        let controller = OrbitController::from_camera(&camera, Point3::new(0.0, 0.0, 0.0));
+
+        // Near and far hug the scene, so the depth panel spans its full range.
+        let mut top_camera = Camera::orthographic(6.0, 1.0, 9.0, 11.0);
+        top_camera.position = Point3::new(0.0, 10.0, 0.0);
+        top_camera.look_at(Point3::new(0.0, 0.0, 0.0), -Vector3::unit_z());
+        let top_camera_binding = CameraBinding::new(&init, &top_camera);
+
+        // The main view is rendered with 4x MSAA and resolved; the inset at a fixed resolution
+        // and single-sampled, so its depth can be shown as well.
+        let main_target = RenderTarget::new(&init, &RenderTargetDescriptor {
+            label: "Main View",
+            color_formats: &[SCENE_FORMAT],
+            sample_count: 4,
+            ..Default::default()
+        });
+        let inset_target = RenderTarget::new(&init, &RenderTargetDescriptor {
+            label: "Top View",
+            size: TargetSize::Fixed { width: 256, height: 256 },
+            color_formats: &[SCENE_FORMAT],
+            ..Default::default()
+        });
+
+        let shader = init.device.create_shader_module(wgpu::include_wgsl!("shaded.wgsl"));
+        let scene_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scene Pipeline Layout"),
+            bind_group_layouts: &[&camera_binding.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        // Both targets share a color format but not the sample count, so each gets its own pipeline.
+        let mut ppl = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&scene_layout),
+            vertex_buffer_layout: &[Vertex::vertex_buffer_layout()],
+            color_formats: main_target.color_formats(),
+            sample_count: Some(main_target.sample_count),
+            ..Default::default()
+        };
+        let scene_pipeline = ppl.new(&init);
+        ppl.sample_count = Some(inset_target.sample_count);
+        let inset_pipeline = ppl.new(&init);
+
+        let texture_layout = rt::create_texture_bind_group_layout(&init);
+        let mut composite = QuadPass::new(&init, include_str!("composite.wgsl"), &FullscreenPassDescriptor {
+            label: "Composite",
+            bind_group_layouts: &[&texture_layout],
+            ..Default::default()
+        });
+        let depth_layout = create_depth_bind_group_layout(&init);
+        let mut depth_panel = QuadPass::new(&init, include_str!("depth_view.wgsl"), &FullscreenPassDescriptor {
+            label: "Depth Panel",
+            fs_entry: if init.depth.reversed_z { "fs_reversed" } else { "fs_main" },
+            bind_group_layouts: &[&depth_layout],
+            ..Default::default()
+        });
+        let quads = panels(&init);
+        composite.set_quads(&init, &quads[..2]);
+        depth_panel.set_quads(&init, &quads[2..]);
+
+        let sampler = ws::create_default_sampler(&init);
+        let main_texture = main_target.create_bind_group(&init, &texture_layout, 0, &sampler);
+        let inset_texture = inset_target.create_bind_group(&init, &texture_layout, 0, &sampler);
+        let inset_depth = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Top View Depth"),
+            layout: &depth_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    inset_target.depth_sample_view.as_ref().expect("the top view is single-sampled"),
+                ),
+            }],
+        });
+
+        let meshes = vec![
+            Geometry::torus(1.0, 0.35, 24, 48).to_mesh(&init),
+            Geometry::cube(0.6, 1).to_mesh(&init),
+        ];
+
+        Self {
+            init,
+            scene_pipeline,
+            inset_pipeline,
+            composite,
+            depth_panel,
+            texture_layout,
+            sampler,
+            meshes,
+            camera,
+            camera_binding,
+            top_camera_binding,
+            controller,
+            main_target,
+            inset_target,
+            main_texture,
+            inset_texture,
+            inset_depth,
+        }
+    }
+
+    fn draw_scene(&self, render_pass: &mut wgpu::RenderPass, pipeline: &wgpu::RenderPipeline, camera: &CameraBinding) {
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        for mesh in &self.meshes {
+            mesh.draw(render_pass);
+        }
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.camera.resize(&self.init);
+        // Only the surface-sized target follows the window; its bind group must be rebuilt.
+        if self.main_target.resize(&self.init) {
+            self.main_texture = self.main_target.create_bind_group(&self.init, &self.texture_layout, 0, &self.sampler);
+        }
+        self.inset_target.resize(&self.init);
+        let quads = panels(&self.init);
+        self.composite.set_quads(&self.init, &quads[..2]);
+        self.depth_panel.set_quads(&self.init, &quads[2..]);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.controller.process_window_event(event)
+    }
+
+    fn device_event(&mut self, event: &DeviceEvent) {
+        self.controller.process_device_event(event);
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.controller.update(&mut self.camera, dt);
+        self.camera_binding.update(&self.init, &self.camera);
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        {
+            let mut render_pass = self.main_target.begin_render_pass(&mut encoder, Some(wgpu::Color::BLACK));
+            self.draw_scene(&mut render_pass, &self.scene_pipeline, &self.camera_binding);
+        }
+        {
+            let clear = wgpu::Color { r: 0.1, g: 0.1, b: 0.15, a: 1.0 };
+            let mut render_pass = self.inset_target.begin_render_pass(&mut encoder, Some(clear));
+            self.draw_scene(&mut render_pass, &self.inset_pipeline, &self.top_camera_binding);
+        }
+        {
+            let color_attachment = ws::create_color_attachment(&view);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Composite Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.composite.draw_range(&mut render_pass, &[&self.main_texture], 0..1);
+            self.composite.draw_range(&mut render_pass, &[&self.inset_texture], 1..2);
+            self.depth_panel.draw(&mut render_pass, &[&self.inset_depth]);
+        }
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+/// The main view over the whole window, then the top view and its depth stacked in the top-right
+/// corner, up to 256x256 pixels each.
+fn panels(init: &ws::IWgpuInit) -> [Quad; 3] {
+    let size = (init.config.width, init.config.height);
+    let side = (size.1 as f32 - 48.0).clamp(0.0, 512.0) / 2.0;
+    let x = size.0 as f32 - side - 16.0;
+    [
+        Quad::new([0.0, 0.0, 1.0, 1.0]),
+        Quad::from_pixels(x, 16.0, side, side, size),
+        Quad::from_pixels(x, 32.0 + side, side, side, size),
+    ]
+}
+
+/// The depth texture `depth_view.wgsl` loads from, as unfilterable float.
+fn create_depth_bind_group_layout(init: &ws::IWgpuInit) -> wgpu::BindGroupLayout {
+    init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Depth Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        }],
+    })
+}
+
+pub fn example<'a>(init: ws::IWgpuInit<'a>, _args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    init.set_title("Chapter 03: Render to texture");
+    Ok(Box::new(State::new(init)))
+}
+
+fn main() {
+    app::run(example);
+}
@@ -0,0 +1,38 @@
+struct Camera {
+    view: mat4x4f,
+    projection: mat4x4f,
+    view_projection: mat4x4f,
+    inverse_view_projection: mat4x4f,
+    position: vec4f,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+    @location(3) tangent: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = camera.view_projection * vec4f(in.position, 1.0);
+    output.normal = in.normal;
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let n = normalize(in.normal);
+    let light = normalize(vec3f(0.5, 1.0, 0.3));
+    let base = 0.5 * n + 0.5;
+    let diffuse = max(dot(n, light), 0.0);
+    return vec4f(base * (0.2 + 0.8 * diffuse), 1.0);
+}
@@ -0,0 +1,253 @@
+use std::{iter, time::Duration};
+use cgmath::{Deg, Matrix4, Point3, Vector3};
+use winit::{
+    event::{DeviceEvent, WindowEvent},
+};
+use wgpu_gp::{
+    app::{self, Example},
+    camera::{Camera, CameraBinding},
+    controller::{CameraController, OrbitController},
+    geometry::Geometry,
+    helpers as ws,
+    light::{Light, Lighting, Lights, MaterialBinding, PbrMaps, PbrMaterial, PhongMaterial},
+    mesh::Mesh,
+    model::Model,
+    shadow::ShadowSettings,
+    transform::TransformBinding,
+};
+
+struct Object {
+    mesh: usize,
+    transform: TransformBinding,
+    material: MaterialBinding,
+    double_sided: bool,
+}
+
+fn create_pipeline(init: &ws::IWgpuInit, lighting: &Lighting, camera_binding: &CameraBinding, phong: bool) -> wgpu::RenderPipeline {
+    if phong {
+        lighting.create_phong_pipeline(init, &camera_binding.bind_group_layout)
+    } else {
+        lighting.create_pbr_pipeline(init, &camera_binding.bind_group_layout)
+    }
+}
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    msaa_view: Option<wgpu::TextureView>,
+    pipeline: wgpu::RenderPipeline,
+    double_sided_pipeline: wgpu::RenderPipeline,
+    phong: bool,
+    lighting: Lighting,
+    meshes: Vec<Mesh>,
+    objects: Vec<Object>,
+    lights: Lights,
+    camera: Camera,
+    camera_binding: CameraBinding,
+    controller: OrbitController,
+    depth_view: wgpu::TextureView,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>, phong: bool, shadows: bool, model_path: Option<&str>) -> Self {
+        let mut camera = Camera::perspective(Deg(45.0), init.aspect_ratio().unwrap_or(1.0), 0.1, 100.0);
+        camera.position = Point3::new(0.0, 3.0, 8.0);
+        camera.look_at(Point3::new(0.0, 0.5, 0.0), Vector3::unit_y());
+        let camera_binding = CameraBinding::new(&init, &camera);
+        let controller = OrbitController::from_camera(&camera, Point3::new(0.0, 0.5, 0.0));
+
+        let lighting = Lighting::new(&init);
+        let pipeline = create_pipeline(&init, &lighting, &camera_binding, phong);
+        let double_sided_pipeline = lighting.create_double_sided_pbr_pipeline(&init, &camera_binding.bind_group_layout);
+        let mut lights = Lights::new(&init, &lighting, vec![
+            Light::Directional {
+                direction: Vector3::new(-0.3, -1.0, -0.5),
+                color: [1.0, 0.95, 0.9],
+                intensity: 2.0,
+            },
+            Light::Point {
+                position: Point3::new(2.0, 1.5, 2.0),
+                color: [1.0, 0.4, 0.2],
+                intensity: 8.0,
+                range: 10.0,
+            },
+            Light::Spot {
+                position: Point3::new(-3.0, 4.0, 1.0),
+                direction: Vector3::new(0.6, -1.0, -0.2),
+                color: [0.3, 0.5, 1.0],
+                intensity: 40.0,
+                range: 20.0,
+                inner_angle: Deg(15.0).into(),
+                outer_angle: Deg(25.0).into(),
+            },
+        ], [0.03, 0.03, 0.04]);
+        if shadows {
+            lights.set_shadow_settings(&init, &lighting, ShadowSettings { max_distance: 30.0, ..Default::default() });
+        }
+
+        let material = |color: [f32; 4], metallic: f32, roughness: f32| -> MaterialBinding {
+            if phong {
+                let phong_material = PhongMaterial {
+                    diffuse: color,
+                    shininess: 2.0 / (roughness * roughness).max(1e-3),
+                    ..Default::default()
+                };
+                lighting.create_phong_material(&init, &phong_material, None)
+            } else {
+                let pbr_material = PbrMaterial { base_color: color, metallic, roughness, ..Default::default() };
+                lighting.create_pbr_material(&init, &pbr_material, &PbrMaps::default())
+            }
+        };
+
+        let mut meshes = vec![
+            Geometry::plane(12.0, 12.0, 1, 1).to_mesh(&init),
+            Geometry::uv_sphere(0.45, 48, 24).to_mesh(&init),
+        ];
+        let mut objects = vec![Object {
+            mesh: 0,
+            transform: TransformBinding::new(&init, &lighting.transform_layout, Matrix4::from_scale(1.0)),
+            material: material([0.6, 0.6, 0.6, 1.0], 0.0, 0.8),
+            double_sided: false,
+        }];
+
+        match model_path.map(|path| (path, Model::load(&init, path))) {
+            Some((_, Ok(model))) => {
+                let world = model.world_transforms();
+                let first_mesh = meshes.len();
+                for (node, transform) in model.nodes.iter().zip(world) {
+                    for &mesh in &node.meshes {
+                        let material = model.meshes[mesh].material;
+                        objects.push(Object {
+                            mesh: first_mesh + mesh,
+                            transform: TransformBinding::new(&init, &lighting.transform_layout, transform),
+                            material: lighting.create_model_material(&init, &model, material),
+                            double_sided: material.and_then(|i| model.materials.get(i)).is_some_and(|m| m.double_sided),
+                        });
+                    }
+                }
+                meshes.extend(model.meshes.into_iter().map(|m| m.mesh));
+            }
+            Some((path, Err(e))) => log::error!("{}: {:#}", path, e),
+            None => {
+                // A grid of spheres: metallic increases along z, roughness along x.
+                for row in 0..2 {
+                    for col in 0..5 {
+                        let position = Vector3::new(col as f32 - 2.0, 0.45, row as f32 * 1.2 - 0.6);
+                        objects.push(Object {
+                            mesh: 1,
+                            transform: TransformBinding::new(&init, &lighting.transform_layout, Matrix4::from_translation(position)),
+                            material: material([0.9, 0.6, 0.2, 1.0], row as f32, 0.1 + 0.2 * col as f32),
+                            double_sided: false,
+                        });
+                    }
+                }
+            }
+        }
+
+        let depth_view = ws::create_depth_view(&init);
+
+        let msaa_view = ws::create_msaa_view(&init);
+        Self {
+            init,
+            msaa_view,
+            pipeline,
+            double_sided_pipeline,
+            phong,
+            lighting,
+            meshes,
+            objects,
+            lights,
+            camera,
+            camera_binding,
+            controller,
+            depth_view,
+        }
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        self.camera.resize(&self.init);
+        self.depth_view = ws::create_depth_view(&self.init);
+    }
+
+    fn set_sample_count(&mut self, requested: u32) -> Option<u32> {
+        let count = self.init.set_sample_count(requested);
+        self.pipeline = create_pipeline(&self.init, &self.lighting, &self.camera_binding, self.phong);
+        self.double_sided_pipeline = self.lighting.create_double_sided_pbr_pipeline(&self.init, &self.camera_binding.bind_group_layout);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        self.depth_view = ws::create_depth_view(&self.init);
+        Some(count)
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.controller.process_window_event(event)
+    }
+
+    fn device_event(&mut self, event: &DeviceEvent) {
+        self.controller.process_device_event(event);
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.controller.update(&mut self.camera, dt);
+        self.camera_binding.update(&self.init, &self.camera);
+        self.lights.update(&self.init, &self.lighting, &self.camera);
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        self.lights.render_shadows(&mut encoder, &mut |render_pass| {
+            for object in &self.objects {
+                render_pass.set_bind_group(1, &object.transform.bind_group, &[]);
+                self.meshes[object.mesh].draw(render_pass);
+            }
+        });
+        {
+            let color_attachment = ws::create_frame_color_attachment(&view, self.msaa_view.as_ref());
+            let depth_attachment = ws::create_depth_stencil_attachment(&self.init, &self.depth_view);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: Some(depth_attachment),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.lights.bind_group, &[]);
+            for object in &self.objects {
+                render_pass.set_pipeline(if object.double_sided { &self.double_sided_pipeline } else { &self.pipeline });
+                render_pass.set_bind_group(2, &object.material.bind_group, &[]);
+                render_pass.set_bind_group(3, &object.transform.bind_group, &[]);
+                self.meshes[object.mesh].draw(&mut render_pass);
+            }
+        }
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+pub fn example<'a>(init: ws::IWgpuInit<'a>, args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    let phong = args.iter().any(|a| a == "phong");
+    let shadows = !args.iter().any(|a| a == "noshadow");
+    let model_path = args.iter()
+        .find(|a| !["phong", "pbr", "noshadow"].contains(&a.as_str()))
+        .map(|s| s.as_str());
+    init.set_title(if phong { "Chapter 04: Blinn-Phong lighting" } else { "Chapter 04: PBR lighting" });
+    Ok(Box::new(State::new(init, phong, shadows, model_path)))
+}
+
+fn main() {
+    app::run(example);
+}
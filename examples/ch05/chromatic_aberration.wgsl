@@ -0,0 +1,12 @@
+// Custom post effect, appended to postprocess::POST_COMMON_WGSL.
+// values: x strength in pixels at the corners
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let texel = 1.0 / vec2f(textureDimensions(input_texture));
+    let offset = (in.uv - 0.5) * 2.0 * params.values.x * texel;
+    let r = input_at(in.uv + offset).r;
+    let center = input_at(in.uv);
+    let b = input_at(in.uv - offset).b;
+    return vec4f(r, center.g, b, center.a);
+}
@@ -0,0 +1,213 @@
+use std::{iter, time::Duration};
+use cgmath::{Deg, Matrix4, Point3, Vector3};
+use winit::{
+    event::{DeviceEvent, ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+use wgpu_gp::{
+    app::{self, Example},
+    camera::{Camera, CameraBinding},
+    controller::{CameraController, OrbitController},
+    geometry::Geometry,
+    helpers as ws,
+    light::{Light, Lighting, Lights, MaterialBinding, PbrMaps, PbrMaterial},
+    mesh::Mesh,
+    postprocess::{Effect, Lut, PostProcessor, ToneMapOperator},
+    transform::TransformBinding,
+};
+
+struct Object {
+    mesh: usize,
+    transform: TransformBinding,
+    material: MaterialBinding,
+}
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    pipeline: wgpu::RenderPipeline,
+    meshes: Vec<Mesh>,
+    objects: Vec<Object>,
+    lights: Lights,
+    camera: Camera,
+    camera_binding: CameraBinding,
+    controller: OrbitController,
+    post: PostProcessor,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>, lut_path: Option<&str>) -> Self {
+        let mut camera = Camera::perspective(Deg(45.0), init.aspect_ratio().unwrap_or(1.0), 0.1, 100.0);
+        camera.position = Point3::new(0.0, 3.0, 8.0);
+        camera.look_at(Point3::new(0.0, 0.5, 0.0), Vector3::unit_y());
+        let camera_binding = CameraBinding::new(&init, &camera);
+        let controller = OrbitController::from_camera(&camera, Point3::new(0.0, 0.5, 0.0));
+
+        let mut post = PostProcessor::with_default_chain(&init, 4);
+        post.push(&init, "chromatic aberration", Effect::Custom {
+            source: include_str!("chromatic_aberration.wgsl").to_string(),
+            values: [3.0, 0.0, 0.0, 0.0],
+            extra: [0.0; 4],
+        }).unwrap();
+        post.set_enabled("chromatic aberration", false);
+        let lut = match lut_path.map(|path| (path, Lut::from_cube(&init, path))) {
+            Some((_, Ok(lut))) => lut,
+            Some((path, Err(e))) => {
+                log::error!("{}: {:#}", path, e);
+                Lut::identity(&init, 2)
+            }
+            // Without a file, grade towards warm highlights and cool shadows.
+            None => Lut::from_fn(&init, 16, |[r, g, b]| {
+                let l = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                [r + 0.08 * l, g + 0.02 * (l - 0.5), b + 0.08 * (0.5 - l)]
+            }),
+        };
+        post.set_lut(&init, "color grade", &lut);
+
+        let lighting = Lighting::new(&init);
+        let pipeline = lighting.create_pbr_pipeline_for_target(&init, &camera_binding.bind_group_layout, &post.scene);
+        let lights = Lights::new(&init, &lighting, vec![
+            Light::Directional {
+                direction: Vector3::new(-0.3, -1.0, -0.5),
+                color: [1.0, 0.95, 0.9],
+                intensity: 3.0,
+            },
+            Light::Point {
+                position: Point3::new(0.0, 1.2, 1.5),
+                color: [1.0, 0.5, 0.2],
+                intensity: 30.0,
+                range: 10.0,
+            },
+        ], [0.03, 0.03, 0.04]);
+
+        let material = |base_color: [f32; 4], metallic: f32, roughness: f32, emissive: [f32; 3]| {
+            let pbr_material = PbrMaterial { base_color, metallic, roughness, emissive, ..Default::default() };
+            lighting.create_pbr_material(&init, &pbr_material, &PbrMaps::default())
+        };
+
+        let meshes = vec![
+            Geometry::plane(12.0, 12.0, 1, 1).to_mesh(&init),
+            Geometry::uv_sphere(0.45, 48, 24).to_mesh(&init),
+        ];
+        let mut objects = vec![Object {
+            mesh: 0,
+            transform: TransformBinding::new(&init, &lighting.transform_layout, Matrix4::from_scale(1.0)),
+            material: material([0.6, 0.6, 0.6, 1.0], 0.0, 0.8, [0.0; 3]),
+        }];
+        // Alternate shiny spheres with emissive ones that are bright enough to bloom.
+        for i in 0..5 {
+            let position = Vector3::new(i as f32 * 1.2 - 2.4, 0.45, 0.0);
+            let emissive = if i % 2 == 1 { [4.0, 1.5, 0.5] } else { [0.0; 3] };
+            objects.push(Object {
+                mesh: 1,
+                transform: TransformBinding::new(&init, &lighting.transform_layout, Matrix4::from_translation(position)),
+                material: material([0.9, 0.9, 0.9, 1.0], 1.0, 0.15 + 0.15 * i as f32, emissive),
+            });
+        }
+
+        Self {
+            init,
+            pipeline,
+            meshes,
+            objects,
+            lights,
+            camera,
+            camera_binding,
+            controller,
+            post,
+        }
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.camera.resize(&self.init);
+        self.post.resize(&self.init);
+    }
+
+    /// 1-6 toggle effects, +/- change exposure, T cycles the tone mapping operator and R moves
+    /// the vignette between the end and the start of the chain.
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::KeyboardInput {
+            event: KeyEvent { physical_key: PhysicalKey::Code(code), state: ElementState::Pressed, .. },
+            ..
+        } = event {
+            let names = ["bloom", "tone map", "color grade", "vignette", "fxaa", "chromatic aberration"];
+            let digits = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6];
+            if let Some(i) = digits.iter().position(|d| d == code) {
+                let enabled = self.post.toggle(names[i]);
+                log::info!("{}: {:?}", names[i], enabled);
+            }
+            match (code, self.post.effect_mut("tone map")) {
+                (KeyCode::Equal, Some(Effect::ToneMap { exposure, .. })) => *exposure *= 1.25,
+                (KeyCode::Minus, Some(Effect::ToneMap { exposure, .. })) => *exposure /= 1.25,
+                (KeyCode::KeyT, Some(Effect::ToneMap { operator, .. })) => {
+                    *operator = match operator {
+                        ToneMapOperator::Clamp => ToneMapOperator::Reinhard,
+                        ToneMapOperator::Reinhard => ToneMapOperator::Aces,
+                        ToneMapOperator::Aces => ToneMapOperator::Clamp,
+                    };
+                }
+                (KeyCode::KeyR, _) => {
+                    let last = self.post.effects().count();
+                    let at_end = self.post.effects().last().is_some_and(|(name, _)| name == "vignette");
+                    self.post.move_to("vignette", if at_end { 0 } else { last });
+                }
+                _ => {}
+            }
+            let chain: Vec<String> = self.post.effects()
+                .map(|(name, enabled)| if enabled { name.to_string() } else { format!("({})", name) })
+                .collect();
+            self.init.set_title(&format!("Chapter 05: {}", chain.join(" > ")));
+        }
+        self.controller.process_window_event(event)
+    }
+
+    fn device_event(&mut self, event: &DeviceEvent) {
+        self.controller.process_device_event(event);
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.controller.update(&mut self.camera, dt);
+        self.camera_binding.update(&self.init, &self.camera);
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        {
+            let mut render_pass = self.post.scene.begin_render_pass(&mut encoder, Some(wgpu::Color::BLACK));
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.lights.bind_group, &[]);
+            for object in &self.objects {
+                render_pass.set_bind_group(2, &object.material.bind_group, &[]);
+                render_pass.set_bind_group(3, &object.transform.bind_group, &[]);
+                self.meshes[object.mesh].draw(&mut render_pass);
+            }
+        }
+        self.post.run(&self.init, &mut encoder, &view);
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+pub fn example<'a>(init: ws::IWgpuInit<'a>, args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    let lut_path = args.first().map(|s| s.as_str());
+    init.set_title("Chapter 05: Post-processing");
+    Ok(Box::new(State::new(init, lut_path)))
+}
+
+fn main() {
+    app::run(example);
+}
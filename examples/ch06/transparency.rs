@@ -0,0 +1,366 @@
+use std::{borrow::Cow, f32::consts::FRAC_PI_2, iter, time::Duration};
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Deg, Matrix4, Point3, Rad, Vector3};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+use wgpu_gp::{
+    app::{self, Example},
+    blend::BlendMode,
+    camera::{Camera, CameraBinding},
+    controller::{CameraController, OrbitController},
+    geometry::{Geometry, Vertex},
+    helpers as ws,
+    mesh::Mesh,
+    oit::{WeightedBlendedOit, OIT_BLEND, OIT_WGSL},
+    transform::{self, TransformUniform},
+    transparency::TransparentQueue,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ObjectUniform {
+    transform: TransformUniform,
+    color: [f32; 4],
+}
+
+struct Object {
+    mesh: usize,
+    model: Matrix4<f32>,
+    center: Point3<f32>,
+    /// Straight (not premultiplied) color; translucent when alpha is below 1.
+    color: [f32; 4],
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Object {
+    fn is_translucent(&self) -> bool {
+        self.color[3] < 1.0
+    }
+
+    fn write(&self, init: &ws::IWgpuInit, blend: BlendMode) {
+        let [r, g, b, a] = self.color;
+        let color = if blend.is_premultiplied() { [r * a, g * a, b * a, a] } else { self.color };
+        let uniform = ObjectUniform { transform: TransformUniform::from(self.model), color };
+        init.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+}
+
+struct Pipelines {
+    /// Replaces and writes depth.
+    opaque: wgpu::RenderPipeline,
+    /// Blends translucent objects with the selected mode and only tests depth.
+    transparent: wgpu::RenderPipeline,
+    /// Accumulates translucent objects into the OIT targets, when there are any.
+    oit: Option<wgpu::RenderPipeline>,
+}
+
+fn create_pipelines(init: &ws::IWgpuInit, camera_binding: &CameraBinding, object_layout: &wgpu::BindGroupLayout,
+blend: BlendMode, oit: Option<&WeightedBlendedOit>) -> Pipelines {
+    let shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Transparency Shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!("{}\n{}", OIT_WGSL, include_str!("transparency.wgsl")))),
+    });
+    let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Transparency Pipeline Layout"),
+        bind_group_layouts: &[&camera_binding.bind_group_layout, object_layout],
+        push_constant_ranges: &[],
+    });
+    let mut ppl = ws::IRenderPipeline {
+        shader: Some(&shader),
+        pipeline_layout: Some(&pipeline_layout),
+        vertex_buffer_layout: &[Vertex::vertex_buffer_layout()],
+        ..Default::default()
+    };
+    let opaque = ppl.new(init);
+    let blend = [blend];
+    ppl.blend = &blend;
+    ppl.depth_write_enabled = false;
+    let transparent = ppl.new(init);
+    let oit = oit.map(|oit| {
+        ppl.color_formats = oit.color_formats();
+        ppl.sample_count = Some(oit.sample_count());
+        ppl.blend = &OIT_BLEND;
+        ppl.fs_entry = String::from("fs_oit");
+        ppl.new(init)
+    });
+    Pipelines { opaque, transparent, oit }
+}
+
+/// The OIT targets, or none when they do not support the frame's sample count.
+fn create_oit(init: &ws::IWgpuInit) -> Option<WeightedBlendedOit> {
+    WeightedBlendedOit::new(init)
+        .map_err(|e| log::warn!("weighted blended OIT is unavailable, blending instead: {:#}", e))
+        .ok()
+}
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    msaa_view: Option<wgpu::TextureView>,
+    depth_view: wgpu::TextureView,
+    pipelines: Pipelines,
+    oit: Option<WeightedBlendedOit>,
+    /// Weighted blended OIT instead of the blend mode; only with `oit`.
+    use_oit: bool,
+    object_layout: wgpu::BindGroupLayout,
+    meshes: Vec<Mesh>,
+    objects: Vec<Object>,
+    queue: TransparentQueue<usize>,
+    blend: BlendMode,
+    sorted: bool,
+    camera: Camera,
+    camera_binding: CameraBinding,
+    controller: OrbitController,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>, blend: BlendMode, sorted: bool, use_oit: bool) -> Self {
+        let mut camera = Camera::perspective(Deg(45.0), init.aspect_ratio().unwrap_or(1.0), 0.1, 100.0);
+        camera.position = Point3::new(3.0, 3.0, 5.0);
+        camera.look_at(Point3::new(0.0, 0.5, 0.0), Vector3::unit_y());
+        let camera_binding = CameraBinding::new(&init, &camera);
+        let controller = OrbitController::from_camera(&camera, Point3::new(0.0, 0.5, 0.0));
+
+        let object_layout = transform::create_transform_bind_group_layout(&init);
+        let oit = create_oit(&init);
+        let use_oit = use_oit && oit.is_some();
+        let pipelines = create_pipelines(&init, &camera_binding, &object_layout, blend, oit.as_ref());
+
+        let meshes = vec![
+            Geometry::plane(8.0, 8.0, 1, 1).to_mesh(&init),
+            Geometry::cube(1.0, 1).to_mesh(&init),
+            Geometry::uv_sphere(0.6, 48, 24).to_mesh(&init),
+            Geometry::plane(3.0, 1.5, 1, 1).to_mesh(&init),
+        ];
+        // Overlapping spheres and an upright pane in front of everything else.
+        let pane = Matrix4::from_translation(Vector3::new(0.0, 0.75, 1.6)) * Matrix4::from_angle_x(Rad(FRAC_PI_2));
+        let placements = [
+            (0, Matrix4::from_scale(1.0), [0.5, 0.5, 0.5, 1.0]),
+            (1, Matrix4::from_translation(Vector3::new(1.8, 0.5, -0.6)), [0.9, 0.9, 0.85, 1.0]),
+            (1, Matrix4::from_translation(Vector3::new(-0.3, 0.5, -1.4)) * Matrix4::from_scale(0.9), [0.2, 0.3, 1.0, 0.5]),
+            (2, Matrix4::from_translation(Vector3::new(-0.7, 0.6, 0.0)), [1.0, 0.15, 0.1, 0.5]),
+            (2, Matrix4::from_translation(Vector3::new(0.0, 0.6, 0.5)), [0.1, 1.0, 0.2, 0.5]),
+            (2, Matrix4::from_translation(Vector3::new(0.7, 0.6, 0.0)), [0.2, 0.5, 1.0, 0.5]),
+            (3, pane, [1.0, 0.85, 0.1, 0.35]),
+        ];
+        let objects: Vec<Object> = placements
+            .into_iter()
+            .map(|(mesh, model, color)| {
+                let buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Object Uniform Buffer"),
+                    contents: bytemuck::bytes_of(&ObjectUniform { transform: TransformUniform::from(model), color }),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                });
+                let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Object Bind Group"),
+                    layout: &object_layout,
+                    entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+                });
+                let center = Point3::new(model.w.x, model.w.y, model.w.z);
+                Object { mesh, model, center, color, buffer, bind_group }
+            })
+            .collect();
+        for object in &objects {
+            object.write(&init, if use_oit { BlendMode::Alpha } else { blend });
+        }
+
+        let depth_view = ws::create_depth_view(&init);
+        let msaa_view = ws::create_msaa_view(&init);
+        Self {
+            init,
+            msaa_view,
+            depth_view,
+            pipelines,
+            oit,
+            use_oit,
+            object_layout,
+            meshes,
+            objects,
+            queue: TransparentQueue::new(),
+            blend,
+            sorted,
+            camera,
+            camera_binding,
+            controller,
+        }
+    }
+
+    fn rebuild_pipelines(&mut self) {
+        self.pipelines = create_pipelines(&self.init, &self.camera_binding, &self.object_layout, self.blend, self.oit.as_ref());
+    }
+
+    /// OIT blends straight colors whatever the blend mode.
+    fn write_objects(&self) {
+        let blend = if self.use_oit { BlendMode::Alpha } else { self.blend };
+        for object in &self.objects {
+            object.write(&self.init, blend);
+        }
+    }
+
+    fn set_title(&self) {
+        if self.use_oit {
+            self.init.set_title("Chapter 06: transparency, weighted blended OIT");
+        } else {
+            let order = if self.sorted { "sorted" } else { "unsorted" };
+            self.init.set_title(&format!("Chapter 06: transparency, {} blending, {}", self.blend.name(), order));
+        }
+    }
+
+    fn draw_translucent(&self, render_pass: &mut wgpu::RenderPass, order: &[usize]) {
+        for &i in order {
+            let object = &self.objects[i];
+            render_pass.set_bind_group(1, &object.bind_group, &[]);
+            self.meshes[object.mesh].draw(render_pass);
+        }
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        self.camera.resize(&self.init);
+        self.depth_view = ws::create_depth_view(&self.init);
+        if let Some(oit) = &mut self.oit {
+            oit.resize(&self.init);
+        }
+    }
+
+    fn set_sample_count(&mut self, requested: u32) -> Option<u32> {
+        let count = self.init.set_sample_count(requested);
+        self.oit = create_oit(&self.init);
+        if self.use_oit && self.oit.is_none() {
+            self.use_oit = false;
+            self.write_objects();
+            self.set_title();
+        }
+        self.rebuild_pipelines();
+        self.msaa_view = ws::create_msaa_view(&self.init);
+        self.depth_view = ws::create_depth_view(&self.init);
+        Some(count)
+    }
+
+    /// B cycles through the blend modes of translucent objects, S toggles sorting them and O
+    /// switches between blending and weighted blended OIT.
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::KeyboardInput {
+            event: KeyEvent { physical_key: PhysicalKey::Code(code), state: ElementState::Pressed, .. },
+            ..
+        } = event
+        {
+            match code {
+                KeyCode::KeyB => {
+                    let presets = &BlendMode::PRESETS[1..];
+                    let next = presets.iter().position(|&mode| mode == self.blend).map_or(0, |i| (i + 1) % presets.len());
+                    self.blend = presets[next];
+                    self.use_oit = false;
+                    self.rebuild_pipelines();
+                    self.write_objects();
+                    self.set_title();
+                    return true;
+                }
+                KeyCode::KeyO => {
+                    self.use_oit = !self.use_oit && self.oit.is_some();
+                    self.write_objects();
+                    self.set_title();
+                    return true;
+                }
+                KeyCode::KeyS => {
+                    self.sorted = !self.sorted;
+                    self.set_title();
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        self.controller.process_window_event(event)
+    }
+
+    fn device_event(&mut self, event: &DeviceEvent) {
+        self.controller.process_device_event(event);
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.controller.update(&mut self.camera, dt);
+        self.camera_binding.update(&self.init, &self.camera);
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        for (i, object) in self.objects.iter().enumerate().filter(|(_, object)| object.is_translucent()) {
+            self.queue.push(&self.camera, object.center, i);
+        }
+        let order: Vec<usize> = if self.sorted && !self.use_oit {
+            self.queue.drain_back_to_front().collect()
+        } else {
+            self.queue.drain_unsorted().collect()
+        };
+        {
+            let color_attachment = ws::create_frame_color_attachment(&view, self.msaa_view.as_ref());
+            let depth_attachment = ws::create_depth_stencil_attachment(&self.init, &self.depth_view);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: Some(depth_attachment),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            render_pass.set_pipeline(&self.pipelines.opaque);
+            for object in self.objects.iter().filter(|object| !object.is_translucent()) {
+                render_pass.set_bind_group(1, &object.bind_group, &[]);
+                self.meshes[object.mesh].draw(&mut render_pass);
+            }
+            if !self.use_oit {
+                render_pass.set_pipeline(&self.pipelines.transparent);
+                self.draw_translucent(&mut render_pass, &order);
+            }
+        }
+        if let (true, Some(oit), Some(pipeline)) = (self.use_oit, &self.oit, &self.pipelines.oit) {
+            {
+                let mut render_pass = oit.begin_accumulate_pass(&mut encoder, &self.depth_view, &self.init.depth);
+                render_pass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+                render_pass.set_pipeline(pipeline);
+                self.draw_translucent(&mut render_pass, &order);
+            }
+            oit.composite(&mut encoder, &view, self.msaa_view.as_ref());
+        }
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+/// Arguments: a blend mode of `BlendMode::PRESETS` (default alpha), `unsorted` and `oit`.
+pub fn example<'a>(init: ws::IWgpuInit<'a>, args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    let mut blend = BlendMode::Alpha;
+    let mut sorted = true;
+    let mut use_oit = false;
+    for arg in args {
+        match arg.as_str() {
+            "unsorted" => sorted = false,
+            "oit" => use_oit = true,
+            name => blend = BlendMode::from_name(name)?,
+        }
+    }
+    let state = State::new(init, blend, sorted, use_oit);
+    state.set_title();
+    Ok(Box::new(state))
+}
+
+fn main() {
+    app::run(example);
+}
@@ -0,0 +1,58 @@
+struct Camera {
+    view: mat4x4f,
+    projection: mat4x4f,
+    view_projection: mat4x4f,
+    inverse_view_projection: mat4x4f,
+    position: vec4f,
+};
+
+// Colors arrive premultiplied by alpha for premultiplied blending.
+struct Object {
+    model: mat4x4f,
+    normal: mat4x4f,
+    color: vec4f,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(0) var<uniform> object: Object;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+    @location(3) tangent: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) world: vec3f,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    let world = object.model * vec4f(in.position, 1.0);
+    output.position = camera.view_projection * world;
+    output.world = world.xyz;
+    output.normal = (object.normal * vec4f(in.normal, 0.0)).xyz;
+    return output;
+}
+
+fn shade(in: VertexOutput) -> vec4f {
+    let n = normalize(in.normal);
+    let light = normalize(vec3f(0.5, 1.0, 0.3));
+    let diffuse = max(dot(n, light), 0.0);
+    return vec4f(object.color.rgb * (0.3 + 0.7 * diffuse), object.color.a);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    return shade(in);
+}
+
+// Weighted blended OIT; `OIT_WGSL` is prepended to this file.
+@fragment
+fn fs_oit(in: VertexOutput) -> OitOutput {
+    return oit_output(shade(in), distance(camera.position.xyz, in.world));
+}
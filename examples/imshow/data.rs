@@ -0,0 +1,221 @@
+use image::{DynamicImage, GenericImageView};
+use wgpu_gp::{array::Array, helpers as ws};
+
+/// What the viewer maps to the screen: all color channels, or one scalar per pixel shown through
+/// the colormap.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Color,
+    Index(u32),
+    Luminance,
+}
+
+#[allow(dead_code)]
+impl Channel {
+    /// The value of `Display::channel` in `imshow.wgsl`.
+    pub fn shader_value(self) -> i32 {
+        match self {
+            Channel::Color => -1,
+            Channel::Index(i) => i as i32,
+            Channel::Luminance => 4,
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Channel::Color => "rgb".to_string(),
+            Channel::Index(i) => ["r", "g", "b", "a"][i as usize].to_string(),
+            Channel::Luminance => "luminance".to_string(),
+        }
+    }
+}
+
+/// Pixel values as uploaded to the GPU: `channels` interleaved f32 per pixel, rows from top to
+/// bottom, then slices. 8 and 16 bit images are normalized to 0..1.
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    /// Number of slices; 1 except for volumes, which are shown one slice at a time.
+    pub depth: u32,
+    /// 1, 2 or 4, matching an R32Float, Rg32Float or Rgba32Float texture.
+    pub channels: u32,
+    pub values: Vec<f32>,
+    /// Decoded from an image file, so colors are meant to be shown over a 0..1 range.
+    pub normalized: bool,
+    /// The integer value that 1.0 stands for in normalized images, 255 for 8-bit files.
+    #[allow(dead_code)]
+    pub max_value: Option<f32>,
+}
+
+impl ImageData {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let (channels, values) = match image {
+            DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) => (1, image.to_luma16().pixels().map(|p| p[0] as f32 / 65535.0).collect()),
+            DynamicImage::ImageLumaA8(_) | DynamicImage::ImageLumaA16(_) => {
+                (2, image.to_luma_alpha16().pixels().flat_map(|p| p.0).map(|v| v as f32 / 65535.0).collect())
+            }
+            _ => (4, image.to_rgba16().pixels().flat_map(|p| p.0).map(|v| v as f32 / 65535.0).collect()),
+        };
+        let max_value = match image {
+            DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_) | DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => 65535.0,
+            _ => 255.0,
+        };
+        Self { width, height, depth: 1, channels, values, normalized: true, max_value: Some(max_value) }
+    }
+
+    /// Arrays keep their values; 3-channel data is padded to 4.
+    pub fn from_array(array: &Array) -> anyhow::Result<Self> {
+        let layout = array.layout()?;
+        Ok(Self {
+            width: layout.width,
+            height: layout.height,
+            depth: layout.depth,
+            channels: layout.texel_channels(),
+            values: array.texels()?,
+            normalized: false,
+            max_value: None,
+        })
+    }
+
+    /// Filter output: 4 channels of unnormalized values.
+    pub fn from_rgba(width: u32, height: u32, pixels: &[[f32; 4]], normalized: bool) -> Self {
+        Self { width, height, depth: 1, channels: 4, values: pixels.concat(), normalized, max_value: None }
+    }
+
+    /// The first slice as RGBA; one channel is repeated as gray, two are gray and alpha.
+    pub fn to_rgba(&self) -> Vec<[f32; 4]> {
+        let n = self.channels as usize;
+        self.values
+            .chunks_exact(n)
+            .take((self.width * self.height) as usize)
+            .map(|p| match n {
+                1 => [p[0], p[0], p[0], 1.0],
+                2 => [p[0], p[0], p[0], p[1]],
+                _ => [p[0], p[1], p[2], p[3]],
+            })
+            .collect()
+    }
+
+    /// The stored channels of pixel (x, y) in `slice`.
+    pub fn value_at(&self, x: u32, y: u32, slice: u32) -> Option<&[f32]> {
+        if x >= self.width || y >= self.height || slice >= self.depth {
+            return None;
+        }
+        let n = self.channels as usize;
+        let start = (((slice * self.height + y) * self.width + x) as usize) * n;
+        Some(&self.values[start..start + n])
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match self.channels {
+            1 => wgpu::TextureFormat::R32Float,
+            2 => wgpu::TextureFormat::Rg32Float,
+            _ => wgpu::TextureFormat::Rgba32Float,
+        }
+    }
+
+    /// Fails when a slice is larger than the device's 2D textures can be, so viewers can reject
+    /// the data before `create_texture_view`.
+    pub fn check_size(&self, init: &ws::IWgpuInit) -> anyhow::Result<()> {
+        let max = init.device.limits().max_texture_dimension_2d;
+        anyhow::ensure!(
+            self.width <= max && self.height <= max,
+            "{}x{} pixels exceed the texture size limit {}", self.width, self.height, max
+        );
+        Ok(())
+    }
+
+    /// Uploads one slice, which must pass `check_size`. Float textures are not filterable without
+    /// an extra feature; `imshow.wgsl` reads them with `textureLoad` and interpolates itself.
+    pub fn create_texture_view(&self, init: &ws::IWgpuInit, slice: u32) -> wgpu::TextureView {
+        let slice_len = (self.width * self.height * self.channels) as usize;
+        let start = slice.min(self.depth - 1) as usize * slice_len;
+        let size = wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 };
+        let texture = init.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image Data"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        init.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&self.values[start..start + slice_len]),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * self.channels * self.width),
+                rows_per_image: Some(self.height),
+            },
+            size,
+        );
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+}
+
+/// Channels and value ranges of the display, which imdiff does without.
+#[allow(dead_code)]
+impl ImageData {
+    /// The channel shown by default: the colormap for single-channel data, colors otherwise.
+    pub fn default_channel(&self) -> Channel {
+        if self.channels <= 2 { Channel::Index(0) } else { Channel::Color }
+    }
+
+    /// The channel after `current` when cycling with a key, skipping ones this data lacks.
+    pub fn next_channel(&self, current: Channel) -> Channel {
+        let mut all = Vec::new();
+        if self.channels == 4 {
+            all.push(Channel::Color);
+        }
+        all.extend((0..self.channels).map(Channel::Index));
+        if self.channels == 4 {
+            all.push(Channel::Luminance);
+        }
+        let index = all.iter().position(|&c| c == current).map_or(0, |i| i + 1);
+        all[index % all.len()]
+    }
+
+    /// The finite values `channel` displays over all slices, so browsing a volume keeps one value
+    /// range; all color channels but alpha for `Channel::Color`.
+    fn samples(&self, channel: Channel) -> Vec<f32> {
+        let n = self.channels as usize;
+        let pixels = self.values.chunks_exact(n);
+        let samples: Vec<f32> = match channel {
+            Channel::Color => pixels.flat_map(|p| p[..n.min(3)].to_vec()).collect(),
+            Channel::Index(i) => pixels.map(|p| p[i as usize]).collect(),
+            Channel::Luminance => pixels.map(|p| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2]).collect(),
+        };
+        samples.into_iter().filter(|v| v.is_finite()).collect()
+    }
+
+    /// The `percentile`th and `100 - percentile`th percentile of the displayed values, so a few
+    /// outliers do not wash out the rest; 0 gives the full min..max range.
+    pub fn percentile_range(&self, channel: Channel, percentile: f32) -> Option<(f32, f32)> {
+        let mut samples = self.samples(channel);
+        if samples.is_empty() {
+            return None;
+        }
+        let last = samples.len() - 1;
+        let p = percentile.clamp(0.0, 50.0) / 100.0;
+        let lo_index = (p * last as f32).round() as usize;
+        let hi_index = ((1.0 - p) * last as f32).round() as usize;
+        let lo = *samples.select_nth_unstable_by(lo_index, f32::total_cmp).1;
+        let hi = *samples.select_nth_unstable_by(hi_index, f32::total_cmp).1;
+        Some((lo, hi))
+    }
+
+    /// Lower bound for log scaling when the range starts at or below zero.
+    pub fn min_positive(&self, channel: Channel) -> Option<f32> {
+        self.samples(channel).into_iter().filter(|&v| v > 0.0).min_by(f32::total_cmp)
+    }
+}
@@ -0,0 +1,56 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu_gp::{colormap::ColormapTexture, helpers as ws};
+
+/// Mirrors `Display` in `imshow.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct DisplayUniform {
+    pub range: [f32; 2],
+    pub channel: i32,
+    pub log_scale: u32,
+    pub linear_filter: u32,
+    pub encode_output: u32,
+    pub levels: [f32; 2],
+    pub channels: u32,
+    pub _padding: u32,
+}
+
+/// The image as an unfilterable float texture at binding 0, the colormap and its sampler at 1 and 2.
+pub fn create_texture_layout(init: &ws::IWgpuInit) -> wgpu::BindGroupLayout {
+    let texture = |binding, filterable| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+    init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Image Bind Group Layout"),
+        entries: &[
+            texture(0, false),
+            texture(1, true),
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+pub fn create_texture_bind_group(init: &ws::IWgpuInit, layout: &wgpu::BindGroupLayout, texture_view: &wgpu::TextureView,
+colormap: &ColormapTexture, sampler: &wgpu::Sampler) -> wgpu::BindGroup {
+    init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(texture_view) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&colormap.view) },
+            wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(sampler) },
+        ],
+        label: Some("Image Bind Group")
+    })
+}
@@ -0,0 +1,542 @@
+use std::{iter, path::{Path, PathBuf}, time::Duration};
+use anyhow::{anyhow, bail, Context};
+use bytemuck::Zeroable;
+use wgpu::util::DeviceExt;
+use winit::{
+    dpi::PhysicalSize, event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+use wgpu_gp::{
+    app::{self, Example},
+    array::Array,
+    colormap::{Colormap, ColormapTexture},
+    compare::{self, DifferenceMetric, ImageComparison},
+    fullscreen::{FullscreenPassDescriptor, Quad, QuadPass},
+    helpers as ws,
+};
+
+mod data;
+mod display;
+mod view;
+use data::ImageData;
+use display::{create_texture_bind_group, create_texture_layout, DisplayUniform};
+use view::View;
+
+const USAGE: &str = "usage: imdiff [--metrics] [--threshold T] [--max-pixels N] [--diff OUT.png]
+              [--perceptual] [--range R] [--cmap NAME] A B
+
+Compares two images of the same size. With --metrics, prints PSNR, SSIM, the largest channel
+error and the number of pixels with a channel differing by more than T (default 0, channels in
+0..1), writes a difference heatmap to --diff if given, and exits with 1 when more than N pixels
+(default 0) differ. Without it, opens a viewer.";
+
+/// Time each image stays on screen in flicker mode.
+const FLICKER_INTERVAL: Duration = Duration::from_millis(500);
+
+struct Options {
+    paths: [PathBuf; 2],
+    metrics: bool,
+    threshold: f32,
+    max_pixels: usize,
+    diff_path: Option<PathBuf>,
+    metric: DifferenceMetric,
+    /// Difference mapped to the top of the heatmap; the metric's default when not given.
+    range: Option<f32>,
+    colormap: Colormap,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut paths = Vec::new();
+        let (mut metrics, mut threshold, mut max_pixels, mut diff_path) = (false, 0.0, 0, None);
+        let (mut metric, mut range, mut colormap) = (DifferenceMetric::Absolute, None, Colormap::Magma);
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().with_context(|| format!("{} needs a value", name));
+            match arg.as_str() {
+                "--metrics" => metrics = true,
+                "--threshold" => threshold = value("--threshold")?.parse().context("--threshold")?,
+                "--max-pixels" => max_pixels = value("--max-pixels")?.parse().context("--max-pixels")?,
+                "--diff" => diff_path = Some(value("--diff")?.into()),
+                "--perceptual" => metric = DifferenceMetric::Perceptual,
+                "--range" => range = Some(value("--range")?.parse().context("--range")?),
+                "--cmap" => colormap = value("--cmap")?.parse()?,
+                _ if arg.starts_with("--") => bail!("unknown option {}", arg),
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+        let Ok(paths) = <[PathBuf; 2]>::try_from(paths) else {
+            bail!("expected two images");
+        };
+        Ok(Self { paths, metrics, threshold, max_pixels, diff_path, metric, range, colormap })
+    }
+}
+
+/// An image or `.npy` array as 4-channel data, so both textures have the same format.
+fn load(path: &Path) -> anyhow::Result<ImageData> {
+    let data = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("npy")) {
+        ImageData::from_array(&Array::from_npy(path)?)?
+    } else {
+        ImageData::from_image(&image::open(path)?)
+    };
+    Ok(ImageData::from_rgba(data.width, data.height, &data.to_rgba(), data.normalized))
+}
+
+fn load_pair(options: &Options) -> anyhow::Result<[ImageData; 2]> {
+    let a = load(&options.paths[0]).with_context(|| options.paths[0].display().to_string())?;
+    let b = load(&options.paths[1]).with_context(|| options.paths[1].display().to_string())?;
+    if (a.width, a.height) != (b.width, b.height) {
+        bail!("image sizes differ: {}x{} and {}x{}", a.width, a.height, b.width, b.height);
+    }
+    Ok([a, b])
+}
+
+/// The command-line mode: prints the metrics and returns the exit code.
+fn print_metrics(options: &Options) -> anyhow::Result<i32> {
+    let [a, b] = load_pair(options)?;
+    let (pa, pb) = (a.to_rgba(), b.to_rgba());
+    let comparison = ImageComparison::new(&pa, &pb, a.width, a.height, options.threshold)?;
+    println!("{}", comparison);
+    if let Some(path) = &options.diff_path {
+        let range = options.range.unwrap_or(options.metric.default_range());
+        let map = compare::difference_map(&pa, &pb, options.metric, range, options.colormap);
+        image::save_buffer(path, &map, a.width, a.height, image::ColorType::Rgba8)
+            .with_context(|| path.display().to_string())?;
+    }
+    Ok(if comparison.differing_pixels > options.max_pixels { 1 } else { 0 })
+}
+
+/// Mirrors `Compare` in `imdiff.wgsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, Zeroable)]
+struct CompareUniform {
+    split: f32,
+    split_line: u32,
+    difference: u32,
+    difference_range: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// Each image in its own half of the window, panned and zoomed together.
+    SideBySide,
+    /// Both images on top of each other, the first left of a draggable split.
+    Wipe,
+    /// The images alternate in place.
+    Flicker,
+    /// The per-pixel difference through the colormap.
+    Difference,
+}
+
+impl Mode {
+    fn name(self) -> &'static str {
+        match self {
+            Mode::SideBySide => "side by side",
+            Mode::Wipe => "wipe",
+            Mode::Flicker => "flicker",
+            Mode::Difference => "difference",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Mode::SideBySide => Mode::Wipe,
+            Mode::Wipe => Mode::Flicker,
+            Mode::Flicker => Mode::Difference,
+            Mode::Difference => Mode::SideBySide,
+        }
+    }
+}
+
+/// What a left-button drag moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Drag {
+    Pan,
+    Split,
+}
+
+struct State <'a> {
+    init: ws::IWgpuInit<'a>,
+    compare_pass: QuadPass,
+    colorbar_pass: QuadPass,
+    texture_bind_group: wgpu::BindGroup,
+    display_buffer: wgpu::Buffer,
+    display_bind_group: wgpu::BindGroup,
+    compare_buffer: wgpu::Buffer,
+    compare_bind_group: wgpu::BindGroup,
+    colormap: ColormapTexture,
+    options: Options,
+    images: [ImageData; 2],
+    comparison: ImageComparison,
+    mode: Mode,
+    metric: DifferenceMetric,
+    difference_range: f32,
+    /// The wipe position as a fraction of the window width.
+    split: f32,
+    /// Set while flicker mode shows the second image.
+    show_second: bool,
+    auto_flicker: bool,
+    /// Time since the images last swapped in flicker mode.
+    flicker_time: Duration,
+    linear_filter: bool,
+    view: View,
+    cursor: [f32; 2],
+    drag: Option<Drag>,
+}
+
+impl<'a> State<'a> {
+    fn new(init: ws::IWgpuInit<'a>, options: Options, images: [ImageData; 2]) -> Self {
+        let (pa, pb) = (images[0].to_rgba(), images[1].to_rgba());
+        let comparison = ImageComparison::new(&pa, &pb, images[0].width, images[0].height, options.threshold)
+            .expect("images were checked to have the same size");
+        if let Some(window) = init.window {
+            let _ = window.request_inner_size(PhysicalSize::new(images[0].width * 2, images[0].height));
+        }
+
+        let texture_layout = create_texture_layout(&init);
+        let colormap = ColormapTexture::new(&init, options.colormap);
+        let colormap_sampler = init.device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let first_view = images[0].create_texture_view(&init, 0);
+        let second_view = images[1].create_texture_view(&init, 0);
+        let texture_bind_group = create_texture_bind_group(&init, &texture_layout, &first_view, &colormap, &colormap_sampler);
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let display_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Display Bind Group Layout"),
+            entries: &[uniform_entry(0)],
+        });
+        let display_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Display Uniform"),
+            contents: bytemuck::bytes_of(&DisplayUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let display_bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Display Bind Group"),
+            layout: &display_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: display_buffer.as_entire_binding() }],
+        });
+
+        let compare_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Compare Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                uniform_entry(1),
+            ],
+        });
+        let compare_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Compare Uniform"),
+            contents: bytemuck::bytes_of(&CompareUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let compare_bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Compare Bind Group"),
+            layout: &compare_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&second_view) },
+                wgpu::BindGroupEntry { binding: 1, resource: compare_buffer.as_entire_binding() },
+            ],
+        });
+
+        let shader = format!("{}\n{}", include_str!("imshow.wgsl"), include_str!("imdiff.wgsl"));
+        let mut descriptor = FullscreenPassDescriptor {
+            label: "imdiff",
+            fs_entry: "fs_compare",
+            bind_group_layouts: &[&texture_layout, &display_layout, &compare_layout],
+            ..Default::default()
+        };
+        let compare_pass = QuadPass::new(&init, &shader, &descriptor);
+        descriptor.label = "Colorbar";
+        descriptor.fs_entry = "fs_colorbar";
+        let colorbar_pass = QuadPass::new(&init, &shader, &descriptor);
+
+        let view = View::new((images[0].width, images[0].height), (init.config.width / 2, init.config.height));
+        let metric = options.metric;
+        let difference_range = options.range.unwrap_or(metric.default_range());
+        let state = Self {
+            init,
+            compare_pass,
+            colorbar_pass,
+            texture_bind_group,
+            display_buffer,
+            display_bind_group,
+            compare_buffer,
+            compare_bind_group,
+            colormap,
+            options,
+            images,
+            comparison,
+            mode: Mode::SideBySide,
+            metric,
+            difference_range,
+            split: 0.5,
+            show_second: false,
+            auto_flicker: true,
+            flicker_time: Duration::ZERO,
+            linear_filter: true,
+            view,
+            cursor: [0.0; 2],
+            drag: None,
+        };
+        state.update_title();
+        state
+    }
+
+    /// The area one image is placed in: the left half side by side, the whole window otherwise.
+    fn view_size(&self) -> (u32, u32) {
+        let (width, height) = (self.init.config.width, self.init.config.height);
+        if self.mode == Mode::SideBySide { (width / 2, height) } else { (width, height) }
+    }
+
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.view.resize(self.view_size());
+    }
+
+    /// The cursor relative to the view, folding the right half onto the left side by side.
+    fn view_cursor(&self) -> [f32; 2] {
+        let half = (self.init.config.width / 2) as f32;
+        if self.mode == Mode::SideBySide && self.cursor[0] >= half {
+            [self.cursor[0] - half, self.cursor[1]]
+        } else {
+            self.cursor
+        }
+    }
+
+    fn split_x(&self) -> f32 {
+        self.split * self.init.config.width as f32
+    }
+
+    fn update_title(&self) {
+        let mut title = format!(
+            "imdiff - {} | {} - {}",
+            self.options.paths[0].display(),
+            self.options.paths[1].display(),
+            self.mode.name(),
+        );
+        match self.mode {
+            Mode::Flicker => title += if self.show_second { " (B)" } else { " (A)" },
+            Mode::Difference => title += &format!(" {} 0..{} {}", self.metric.name(), self.difference_range, self.colormap.colormap.name()),
+            _ => {}
+        }
+        let c = &self.comparison;
+        title += &format!(
+            " {:.0}% | PSNR {:.2} dB, SSIM {:.4}, max error {:.4}, {} pixels differ",
+            self.view.scale * 100.0, c.psnr, c.ssim, c.max_error, c.differing_pixels
+        );
+        let [x, y] = self.view.window_to_image(self.view_cursor());
+        if x >= 0.0 && y >= 0.0 {
+            let (x, y) = (x as u32, y as u32);
+            if let (Some(a), Some(b)) = (self.images[0].value_at(x, y, 0), self.images[1].value_at(x, y, 0)) {
+                let format = |p: &[f32]| p.iter().map(|v| format!("{:.4}", v)).collect::<Vec<_>>().join(", ");
+                title += &format!(" | ({}, {}) A [{}] B [{}]", x, y, format(a), format(b));
+            }
+        }
+        self.init.set_title(&title);
+    }
+}
+
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.init.resize(new_size);
+        self.view.resize(self.view_size());
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = [position.x as f32, position.y as f32];
+                match self.drag {
+                    Some(Drag::Pan) => self.view.pan([cursor[0] - self.cursor[0], cursor[1] - self.cursor[1]]),
+                    Some(Drag::Split) => self.split = (cursor[0] / self.init.config.width as f32).clamp(0.0, 1.0),
+                    None => {}
+                }
+                self.cursor = cursor;
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.drag = (*state == ElementState::Pressed).then(|| {
+                    // Grab the split line within a few pixels, pan anywhere else.
+                    if self.mode == Mode::Wipe && (self.cursor[0] - self.split_x()).abs() <= 6.0 { Drag::Split } else { Drag::Pan }
+                });
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 50.0,
+                };
+                self.view.zoom_at(self.view_cursor(), 1.2f32.powf(lines));
+            }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { physical_key: PhysicalKey::Code(code), state: ElementState::Pressed, .. },
+                ..
+            } => match code {
+                KeyCode::KeyM | KeyCode::Tab => self.set_mode(self.mode.next()),
+                KeyCode::Space => {
+                    // Flip by hand; T resumes the automatic flicker.
+                    self.set_mode(Mode::Flicker);
+                    self.auto_flicker = false;
+                    self.show_second = !self.show_second;
+                }
+                KeyCode::KeyT => {
+                    self.auto_flicker = !self.auto_flicker;
+                    self.flicker_time = Duration::ZERO;
+                }
+                KeyCode::KeyD => {
+                    self.metric = self.metric.next();
+                    self.difference_range = self.metric.default_range();
+                }
+                KeyCode::BracketLeft => self.difference_range *= 0.5,
+                KeyCode::BracketRight => self.difference_range *= 2.0,
+                KeyCode::KeyF => self.view.fit_to_window(),
+                KeyCode::Digit1 => self.view.actual_size(),
+                KeyCode::Equal => self.view.zoom_centered(2.0),
+                KeyCode::Minus => self.view.zoom_centered(0.5),
+                KeyCode::KeyN => self.linear_filter = !self.linear_filter,
+                KeyCode::KeyC => {
+                    let next = self.colormap.colormap.next();
+                    self.colormap.set(&self.init, next);
+                }
+                _ => return false,
+            },
+            _ => return false,
+        }
+        self.update_title();
+        true
+    }
+
+    fn update(&mut self, dt: Duration) {
+        self.flicker_time += dt;
+        if self.mode == Mode::Flicker && self.auto_flicker && self.flicker_time >= FLICKER_INTERVAL {
+            self.show_second = !self.show_second;
+            self.flicker_time = Duration::ZERO;
+            self.update_title();
+        }
+
+        let size = (self.init.config.width, self.init.config.height);
+        // Side by side, the second quad is the same placement shifted into the right half.
+        let quads = [self.view.quad_at(0.0, size), self.view.quad_at((size.0 / 2) as f32, size)];
+        self.compare_pass.set_quads(&self.init, &quads);
+        let colorbar = Quad::from_pixels(size.0 as f32 - 36.0, size.1 as f32 * 0.1, 20.0, size.1 as f32 * 0.8, size);
+        self.colorbar_pass.set_quads(&self.init, &[colorbar]);
+
+        let display = DisplayUniform {
+            range: [0.0, 1.0],
+            channel: -1,
+            linear_filter: self.linear_filter as u32,
+            encode_output: !self.init.config.format.is_srgb() as u32,
+            levels: [0.0, 1.0],
+            channels: 4,
+            ..Zeroable::zeroed()
+        };
+        self.init.queue.write_buffer(&self.display_buffer, 0, bytemuck::bytes_of(&display));
+        let (split, split_line) = match self.mode {
+            Mode::SideBySide => ((size.0 / 2) as f32, false),
+            Mode::Wipe => (self.split_x(), true),
+            // Everything is left of the split for the first image and right of it for the second.
+            Mode::Flicker | Mode::Difference => (if self.show_second { f32::MIN } else { f32::MAX }, false),
+        };
+        let compare = CompareUniform {
+            split,
+            split_line: split_line as u32,
+            difference: match (self.mode, self.metric) {
+                (Mode::Difference, DifferenceMetric::Absolute) => 1,
+                (Mode::Difference, DifferenceMetric::Perceptual) => 2,
+                _ => 0,
+            },
+            difference_range: self.difference_range,
+        };
+        self.init.queue.write_buffer(&self.compare_buffer, 0, bytemuck::bytes_of(&compare));
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
+        let output = self.init.get_current_frame()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.init.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder")
+            }
+        );
+        {
+            let color_attachment = ws::create_color_attachment(&view);
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            let bind_groups = [&self.texture_bind_group, &self.display_bind_group, &self.compare_bind_group];
+            let (width, height) = (self.init.config.width, self.init.config.height);
+            if self.mode == Mode::SideBySide {
+                // Each half only shows its own quad, however far the images are zoomed.
+                let half = width / 2;
+                render_pass.set_scissor_rect(0, 0, half.max(1), height);
+                self.compare_pass.draw_range(&mut render_pass, &bind_groups, 0..1);
+                render_pass.set_scissor_rect(half, 0, (width - half).max(1), height);
+                self.compare_pass.draw_range(&mut render_pass, &bind_groups, 1..2);
+                render_pass.set_scissor_rect(0, 0, width, height);
+            } else {
+                self.compare_pass.draw_range(&mut render_pass, &bind_groups, 0..1);
+            }
+            if self.mode == Mode::Difference {
+                self.colorbar_pass.draw(&mut render_pass, &bind_groups);
+            }
+        }
+        self.init.queue.submit(iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+pub fn example<'a>(init: ws::IWgpuInit<'a>, args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    let options = Options::parse(args.iter().cloned()).map_err(|e| anyhow!("{}\n{}\n\n{}", e, USAGE, app::RUN_USAGE))?;
+    let images = load_pair(&options)?;
+    for image in &images {
+        image.check_size(&init)?;
+    }
+    init.set_title("imdiff");
+    Ok(Box::new(State::new(init, options, images)))
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = app::RunOptions::parse(&args).and_then(|(_, args)| Options::parse(args.into_iter())).unwrap_or_else(|e| {
+        eprintln!("{}\n{}\n\n{}", e, USAGE, app::RUN_USAGE);
+        std::process::exit(2);
+    });
+    // The metrics mode needs no window.
+    if options.metrics {
+        match print_metrics(&options) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("{:#}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+    app::run(example);
+}
@@ -0,0 +1,55 @@
+// Appended to imshow.wgsl. uImage holds the first image and uImageB the second, both RGBA with
+// sRGB-encoded colors.
+
+struct Compare {
+    // Window x position in pixels; the first image shows left of it, the second right of it.
+    split: f32,
+    // Set to draw a line along the split.
+    split_line: u32,
+    // 0 shows the images, 1 the absolute and 2 the perceptual difference through the colormap.
+    difference: u32,
+    // Difference mapped to the top of the colormap.
+    difference_range: f32,
+}
+
+@group(2) @binding(0) var uImageB: texture_2d<f32>;
+@group(2) @binding(1) var<uniform> compare: Compare;
+
+fn lab_f(t: f32) -> f32 {
+    return select(t / (3.0 * 0.04280618) + 4.0 / 29.0, pow(t, 1.0 / 3.0), t > 0.008856452);
+}
+
+// CIELAB (D65) of an sRGB-encoded color, as compare::srgb_to_lab.
+fn srgb_to_lab(c: vec3f) -> vec3f {
+    let l = srgb_to_linear(max(c, vec3f(0.0)));
+    let xyz = mat3x3f(
+        vec3f(0.4124, 0.2126, 0.0193),
+        vec3f(0.3576, 0.7152, 0.1192),
+        vec3f(0.1805, 0.0722, 0.9505),
+    ) * l / vec3f(0.95047, 1.0, 1.08883);
+    let f = vec3f(lab_f(xyz.x), lab_f(xyz.y), lab_f(xyz.z));
+    return vec3f(116.0 * f.y - 16.0, 500.0 * (f.x - f.y), 200.0 * (f.y - f.z));
+}
+
+// Same as compare::DifferenceMetric::eval.
+fn difference(a: vec4f, b: vec4f) -> f32 {
+    if compare.difference == 2u {
+        return distance(srgb_to_lab(a.rgb), srgb_to_lab(b.rgb));
+    }
+    let d = abs(a - b);
+    return max(max(d.r, d.g), max(d.b, d.a));
+}
+
+@fragment
+fn fs_compare(in: VertexOutput) -> @location(0) vec4f {
+    let a = texel(uImage, in.uv);
+    let b = texel(uImageB, in.uv);
+    if compare.difference != 0u {
+        return output(colormap(clamp(difference(a, b) / compare.difference_range, 0.0, 1.0)), 1.0);
+    }
+    if compare.split_line != 0u && abs(in.position.x - compare.split) < 1.0 {
+        return output(vec3f(1.0, 0.8, 0.0), 1.0);
+    }
+    let value = select(b, a, in.position.x < compare.split);
+    return output(srgb_to_linear(clamp(value.rgb, vec3f(0.0), vec3f(1.0))), 1.0);
+}
@@ -1,125 +1,548 @@
-use std::iter;
+use std::{iter, path::PathBuf, time::Duration};
+use anyhow::{anyhow, bail, Context};
+use bytemuck::Zeroable;
+use wgpu::util::DeviceExt;
 use winit::{
-    dpi::PhysicalSize, event::{Event, WindowEvent}, 
-    event_loop::{ControlFlow, EventLoop}, window::Window
+    dpi::PhysicalSize, event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
 };
-use wgpu_gp::helpers as ws;
+use wgpu_gp::{
+    app::{self, Example},
+    array::{Array, DType},
+    colormap::{Colormap, ColormapTexture},
+    filter::{Filter, FilterChain, FilterImage},
+    fullscreen::{FullscreenPassDescriptor, Quad, QuadPass},
+    helpers as ws,
+    histogram::{self, Histogram},
+};
+
+mod data;
+mod display;
+mod view;
+use data::{Channel, ImageData};
+use display::{create_texture_bind_group, create_texture_layout, DisplayUniform};
+use view::View;
+
+const USAGE: &str = "usage: imshow [--cmap NAME] [--vmin X] [--vmax X] [--clip PERCENT] [--log]
+              [--shape DxHxW --dtype TYPE [--fortran]] [--filter SPEC ...] [FILE ...]
+
+.npy files are read with their own header; with --shape, files that are not images are read as
+headerless arrays of that shape and --dtype (default f32). --filter runs GPU filters on each
+image in order: gaussian:SIGMA, box:R, sobel, canny:SIGMA:LOW:HIGH, unsharp:SIGMA:AMOUNT,
+median:R, erode:R, dilate:R, resize:WxH, rgb2hsv, hsv2rgb, rgb2ycbcr, ycbcr2rgb, rgb2lab, lab2rgb.";
+
+/// Shape and element type of headerless array files.
+struct RawFormat {
+    shape: Vec<usize>,
+    dtype: DType,
+    fortran_order: bool,
+}
+
+/// Image files from the command line, or the bundled picture when none are given.
+enum ImageSource {
+    Bundled,
+    File(PathBuf),
+}
+
+impl ImageSource {
+    fn name(&self) -> String {
+        match self {
+            ImageSource::Bundled => "happy-tree.png".to_string(),
+            ImageSource::File(path) => path.display().to_string(),
+        }
+    }
+
+    /// Images and arrays larger than the textures `init` supports are an error.
+    fn load(&self, init: &ws::IWgpuInit, raw: Option<&RawFormat>) -> anyhow::Result<ImageData> {
+        let path = match self {
+            ImageSource::Bundled => return Ok(ImageData::from_image(&image::load_from_memory(include_bytes!("happy-tree.png"))?)),
+            ImageSource::File(path) => path,
+        };
+        let data = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("npy")) {
+            ImageData::from_array(&Array::from_npy(path)?)?
+        } else {
+            match raw {
+                Some(raw) if image::ImageFormat::from_path(path).is_err() => {
+                    ImageData::from_array(&Array::from_raw(path, &raw.shape, raw.dtype, raw.fortran_order)?)?
+                }
+                _ => ImageData::from_image(&image::open(path)?),
+            }
+        };
+        data.check_size(init)?;
+        Ok(data)
+    }
+}
+
+struct Options {
+    sources: Vec<ImageSource>,
+    colormap: Colormap,
+    vmin: Option<f32>,
+    vmax: Option<f32>,
+    /// Percentile cut from both ends of the value range when clipping is on.
+    clip: f32,
+    log_scale: bool,
+    raw: Option<RawFormat>,
+    filters: Vec<Filter>,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut options = Self { sources: Vec::new(), colormap: Colormap::Viridis, vmin: None, vmax: None, clip: 1.0, log_scale: false, raw: None, filters: Vec::new() };
+        let (mut shape, mut dtype, mut fortran_order) = (None, DType::F32, false);
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().with_context(|| format!("{} needs a value", name));
+            match arg.as_str() {
+                "--cmap" => options.colormap = value("--cmap")?.parse()?,
+                "--vmin" => options.vmin = Some(value("--vmin")?.parse().context("--vmin")?),
+                "--vmax" => options.vmax = Some(value("--vmax")?.parse().context("--vmax")?),
+                "--clip" => options.clip = value("--clip")?.parse().context("--clip")?,
+                "--log" => options.log_scale = true,
+                "--shape" => {
+                    let text = value("--shape")?;
+                    let dims: Result<Vec<usize>, _> = text.split(['x', ',']).map(str::parse).collect();
+                    shape = Some(dims.with_context(|| format!("bad --shape '{}'", text))?);
+                }
+                "--dtype" => dtype = DType::parse(&value("--dtype")?)?,
+                "--fortran" => fortran_order = true,
+                "--filter" => options.filters.push(value("--filter")?.parse()?),
+                _ if arg.starts_with("--") => bail!("unknown option {}", arg),
+                _ => options.sources.push(ImageSource::File(arg.into())),
+            }
+        }
+        if options.sources.is_empty() {
+            options.sources.push(ImageSource::Bundled);
+        }
+        options.raw = shape.map(|shape| RawFormat { shape, dtype, fortran_order });
+        Ok(options)
+    }
+}
+
+/// What a left-button drag moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Drag {
+    Pan,
+    BlackLevel,
+    WhiteLevel,
+}
 
 struct State <'a> {
     init: ws::IWgpuInit<'a>,
-    pipeline: wgpu::RenderPipeline,
-    bind_group: wgpu::BindGroup,
-    window: &'a Window,
+    image_pass: QuadPass,
+    colorbar_pass: QuadPass,
+    histogram_pass: QuadPass,
+    histogram: Histogram,
+    /// Values spread over the histogram's bins.
+    histogram_range: (f32, f32),
+    /// Set when the histogram has to be recounted before the next frame.
+    histogram_stale: bool,
+    show_histogram: bool,
+    texture_view: wgpu::TextureView,
+    texture_layout: wgpu::BindGroupLayout,
+    texture_bind_group: wgpu::BindGroup,
+    display_buffer: wgpu::Buffer,
+    display_bind_group: wgpu::BindGroup,
+    colormap: ColormapTexture,
+    colormap_sampler: wgpu::Sampler,
+    options: Options,
+    /// Built from `--filter`; P toggles it.
+    filter_chain: Option<FilterChain>,
+    filters_enabled: bool,
+    current: usize,
+    data: ImageData,
+    /// The slice of a volume on screen.
+    slice: u32,
+    channel: Channel,
+    linear_filter: bool,
+    log_scale: bool,
+    auto_clip: bool,
+    /// vmin and vmax in data units.
+    range: (f32, f32),
+    view: View,
+    cursor: [f32; 2],
+    drag: Option<Drag>,
 }
 
 impl<'a> State<'a> {
-    async  fn new(window: &'a Window) -> Self {
-        let init = ws::IWgpuInit::new(&window, 1, None)
-            .await;
-
-        let diffuse_bytes = include_bytes!("happy-tree.png");
-        let diffuse_image = image::load_from_memory(diffuse_bytes).unwrap();
-        let image_texture_view = ws::create_image_texture_view(&init, &diffuse_image);
-        use image::GenericImageView;
-        let dimensions = diffuse_image.dimensions();
-        println!("Image dimensions: {:?}", dimensions);
-        let _ = window.request_inner_size(PhysicalSize::new(dimensions.0, dimensions.1));
-        let image_sampler = ws::create_default_sampler(&init);
-        
-        let shader = init.device.create_shader_module(
-            wgpu::include_wgsl!("imshow.wgsl")
-        );
+    fn new(init: ws::IWgpuInit<'a>, options: Options) -> anyhow::Result<Self> {
+        let (current, data) = options.sources
+            .iter()
+            .enumerate()
+            .find_map(|(i, source)| match source.load(&init, options.raw.as_ref()) {
+                Ok(data) => Some((i, data)),
+                Err(e) => {
+                    log::warn!("{}: {}", source.name(), e);
+                    None
+                }
+            })
+            .context("none of the given images could be read")?;
+        let filter_chain = (!options.filters.is_empty()).then(|| {
+            let mut chain = FilterChain::new(&init);
+            chain.filters = options.filters.clone();
+            chain
+        });
+        let data = apply_filters(&init, filter_chain.as_ref(), data);
+        if let Some(window) = init.window {
+            let _ = window.request_inner_size(PhysicalSize::new(data.width, data.height));
+        }
 
-        let bind_group_layout = init.device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                label: Some(
-                    "Bind Group Layout"
-                ),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None
-                    }
-                ]
-            }
-        );
+        let texture_layout = create_texture_layout(&init);
+        let colormap = ColormapTexture::new(&init, options.colormap);
+        let colormap_sampler = init.device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let texture_view = data.create_texture_view(&init, 0);
+        let texture_bind_group = create_texture_bind_group(&init, &texture_layout, &texture_view, &colormap, &colormap_sampler);
 
-        let bind_group = init.device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&image_texture_view),
+        let display_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Display Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
                     },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&image_sampler),
-                    }
-                ],
-                label: Some("Bind Group")
+                    count: None,
+                },
+                histogram::histogram_layout_entry(1),
+            ],
+        });
+        let display_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Display Uniform"),
+            contents: bytemuck::bytes_of(&DisplayUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let histogram = Histogram::new(&init);
+        let display_bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Display Bind Group"),
+            layout: &display_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: display_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: histogram.buffer.as_entire_binding() },
+            ],
+        });
+
+        let mut descriptor = FullscreenPassDescriptor {
+            label: "imshow",
+            bind_group_layouts: &[&texture_layout, &display_layout],
+            ..Default::default()
+        };
+        let image_pass = QuadPass::new(&init, include_str!("imshow.wgsl"), &descriptor);
+        descriptor.label = "Colorbar";
+        descriptor.fs_entry = "fs_colorbar";
+        let colorbar_pass = QuadPass::new(&init, include_str!("imshow.wgsl"), &descriptor);
+        descriptor.label = "Histogram";
+        descriptor.fs_entry = "fs_histogram";
+        let histogram_pass = QuadPass::new(&init, include_str!("imshow.wgsl"), &descriptor);
+
+        let view = View::new((data.width, data.height), (init.config.width, init.config.height));
+        let log_scale = options.log_scale;
+        let mut state = Self {
+            init,
+            image_pass,
+            colorbar_pass,
+            histogram_pass,
+            histogram,
+            histogram_range: (0.0, 1.0),
+            histogram_stale: true,
+            show_histogram: true,
+            texture_view,
+            texture_layout,
+            texture_bind_group,
+            display_buffer,
+            display_bind_group,
+            colormap,
+            colormap_sampler,
+            filter_chain,
+            filters_enabled: true,
+            options,
+            current,
+            slice: 0,
+            channel: data.default_channel(),
+            data,
+            linear_filter: true,
+            log_scale,
+            auto_clip: true,
+            range: (0.0, 1.0),
+            view,
+            cursor: [0.0; 2],
+            drag: None,
+        };
+        state.update_range();
+        state.update_title();
+        Ok(state)
+    }
+
+    /// Moves `step` images forward or back, skipping files that fail to load.
+    fn step(&mut self, step: isize) {
+        let count = self.options.sources.len() as isize;
+        for i in 1..count {
+            let index = (self.current as isize + step * i).rem_euclid(count) as usize;
+            if self.load(index) {
+                return;
             }
-        );
+        }
+    }
 
-        let pipeline_layout = init.device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&bind_group_layout],
-                push_constant_ranges: &[],
+    /// Reloads the current image, e.g. after toggling the filters.
+    fn reload(&mut self) {
+        self.load(self.current);
+    }
+
+    fn load(&mut self, index: usize) -> bool {
+        match self.options.sources[index].load(&self.init, self.options.raw.as_ref()) {
+            Ok(data) => {
+                self.current = index;
+                let chain = if self.filters_enabled { self.filter_chain.as_ref() } else { None };
+                let data = apply_filters(&self.init, chain, data);
+                self.set_data(data);
+                true
             }
-        );
+            Err(e) => {
+                log::warn!("{}: {}", self.options.sources[index].name(), e);
+                false
+            }
+        }
+    }
 
-        let mut ppl = ws::IRenderPipeline {
-            shader: Some(&shader),
-            pipeline_layout: Some(&pipeline_layout),
-            is_depth_stencil: false,
-            vertex_buffer_layout: &[],
-            ..Default::default()
+    fn set_data(&mut self, data: ImageData) {
+        self.view = View::new((data.width, data.height), self.view.window_size);
+        self.channel = data.default_channel();
+        self.data = data;
+        self.slice = 0;
+        self.upload_slice();
+        self.update_range();
+    }
+
+    fn step_slice(&mut self, step: i32) {
+        let slice = (self.slice as i32 + step).clamp(0, self.data.depth as i32 - 1) as u32;
+        if slice != self.slice {
+            self.slice = slice;
+            self.upload_slice();
+        }
+    }
+
+    fn upload_slice(&mut self) {
+        self.texture_view = self.data.create_texture_view(&self.init, self.slice);
+        self.texture_bind_group = create_texture_bind_group(
+            &self.init, &self.texture_layout, &self.texture_view, &self.colormap, &self.colormap_sampler);
+        self.histogram_stale = true;
+    }
+
+    /// Recomputes vmin and vmax for the displayed channel. Explicit `--vmin`/`--vmax` win; colors
+    /// of image files keep their 0..1 range, everything else is clipped by percentile (or min/max
+    /// with clipping off).
+    fn update_range(&mut self) {
+        let auto = if self.channel == Channel::Color && self.data.normalized {
+            (0.0, 1.0)
+        } else {
+            let percentile = if self.auto_clip { self.options.clip } else { 0.0 };
+            self.data.percentile_range(self.channel, percentile).unwrap_or((0.0, 1.0))
         };
+        self.range = (self.options.vmin.unwrap_or(auto.0), self.options.vmax.unwrap_or(auto.1));
 
-        let pipeline = ppl.new(&init);
+        // The histogram covers all values of the channel and the levels, whichever is wider.
+        let full = if self.channel == Channel::Color && self.data.normalized {
+            (0.0, 1.0)
+        } else {
+            self.data.percentile_range(self.channel, 0.0).unwrap_or((0.0, 1.0))
+        };
+        let (lo, hi) = (full.0.min(self.range.0), full.1.max(self.range.1));
+        self.histogram_range = (lo, if hi > lo { hi } else { lo + 1.0 });
+        self.histogram_stale = true;
+    }
 
-        Self {
-            init,
-            pipeline,
-            bind_group,
-            window
+    /// The histogram panel in window pixels: x, y, width, height.
+    fn histogram_rect(&self) -> [f32; 4] {
+        let height = self.init.config.height as f32;
+        let width = (self.init.config.width as f32 - 80.0).clamp(64.0, 384.0);
+        [16.0, height - 16.0 - 96.0, width, 96.0]
+    }
+
+    /// The value under window x position `x` in the histogram panel.
+    fn histogram_value(&self, x: f32) -> f32 {
+        let rect = self.histogram_rect();
+        let t = ((x - rect[0]) / rect[2]).clamp(0.0, 1.0);
+        self.histogram_range.0 + t * (self.histogram_range.1 - self.histogram_range.0)
+    }
+
+    fn display_uniform(&self) -> DisplayUniform {
+        let (mut lo, mut hi) = self.range;
+        if self.log_scale {
+            // Log scaling needs a positive lower bound; fall back to the smallest positive value.
+            if lo <= 0.0 {
+                lo = self.data.min_positive(self.channel).unwrap_or(1e-6);
+            }
+            (lo, hi) = (lo.ln(), hi.max(lo).ln());
+        }
+        DisplayUniform {
+            range: [lo, hi],
+            channel: self.channel.shader_value(),
+            log_scale: self.log_scale as u32,
+            linear_filter: self.linear_filter as u32,
+            encode_output: !self.init.config.format.is_srgb() as u32,
+            levels: [self.range.0, self.range.1].map(|v| (v - self.histogram_range.0) / (self.histogram_range.1 - self.histogram_range.0)),
+            channels: self.data.channels,
+            _padding: 0,
+        }
+    }
+
+    fn update_title(&self) {
+        let mut title = format!(
+            "imshow - {} ({}/{}) {}x{}x{} {:.0}% {}",
+            self.options.sources[self.current].name(),
+            self.current + 1,
+            self.options.sources.len(),
+            self.data.width,
+            self.data.height,
+            self.data.channels,
+            self.view.scale * 100.0,
+            self.channel.name(),
+        );
+        if self.data.depth > 1 {
+            title += &format!(" slice {}/{}", self.slice + 1, self.data.depth);
+        }
+        if self.channel != Channel::Color {
+            title += &format!(" {}", self.colormap.colormap.name());
         }
+        title += &format!(" [{:.4}, {:.4}]", self.range.0, self.range.1);
+        if self.log_scale {
+            title += " log";
+        }
+        if !self.linear_filter {
+            title += " nearest";
+        }
+        // Pixel inspector: the stored values, not the displayed colors.
+        let [x, y] = self.view.window_to_image(self.cursor);
+        if x >= 0.0 && y >= 0.0 {
+            if let Some(pixel) = self.data.value_at(x as u32, y as u32, self.slice) {
+                let values: Vec<String> = pixel.iter().map(|v| format!("{:.6}", v)).collect();
+                title += &format!(" | ({}, {}) = [{}]", x as u32, y as u32, values.join(", "));
+                if let Some(max) = self.data.max_value {
+                    let integers: Vec<String> = pixel.iter().map(|v| format!("{}", (v * max).round())).collect();
+                    title += &format!(" ({})", integers.join(", "));
+                }
+            }
+        }
+        self.init.set_title(&title);
+    }
+}
 
+impl Example for State<'_> {
+    fn init(&self) -> &ws::IWgpuInit<'_> {
+        &self.init
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         self.init.resize(new_size);
+        self.view.resize((self.init.config.width, self.init.config.height));
     }
 
-    #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = [position.x as f32, position.y as f32];
+                match self.drag {
+                    Some(Drag::Pan) => self.view.pan([cursor[0] - self.cursor[0], cursor[1] - self.cursor[1]]),
+                    Some(Drag::BlackLevel) => self.range.0 = self.histogram_value(cursor[0]).min(self.range.1),
+                    Some(Drag::WhiteLevel) => self.range.1 = self.histogram_value(cursor[0]).max(self.range.0),
+                    None => {}
+                }
+                self.cursor = cursor;
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                self.drag = (*state == ElementState::Pressed).then(|| {
+                    let rect = self.histogram_rect();
+                    let [x, y] = self.cursor;
+                    let in_panel = x >= rect[0] && x <= rect[0] + rect[2] && y >= rect[1] && y <= rect[1] + rect[3];
+                    if !self.show_histogram || !in_panel {
+                        return Drag::Pan;
+                    }
+                    // Grab whichever level marker is closer.
+                    let value = self.histogram_value(x);
+                    if (value - self.range.0).abs() <= (value - self.range.1).abs() { Drag::BlackLevel } else { Drag::WhiteLevel }
+                });
+                match self.drag {
+                    Some(Drag::BlackLevel) => self.range.0 = self.histogram_value(self.cursor[0]).min(self.range.1),
+                    Some(Drag::WhiteLevel) => self.range.1 = self.histogram_value(self.cursor[0]).max(self.range.0),
+                    _ => {}
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 50.0,
+                };
+                self.view.zoom_at(self.cursor, 1.2f32.powf(lines));
+            }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent { physical_key: PhysicalKey::Code(code), state: ElementState::Pressed, .. },
+                ..
+            } => match code {
+                KeyCode::ArrowRight | KeyCode::Space => self.step(1),
+                KeyCode::ArrowLeft | KeyCode::Backspace => self.step(-1),
+                KeyCode::KeyF => self.view.fit_to_window(),
+                KeyCode::Digit1 => self.view.actual_size(),
+                KeyCode::Equal => self.view.zoom_centered(2.0),
+                KeyCode::Minus => self.view.zoom_centered(0.5),
+                KeyCode::PageDown | KeyCode::Period => self.step_slice(1),
+                KeyCode::PageUp | KeyCode::Comma => self.step_slice(-1),
+                KeyCode::KeyN => self.linear_filter = !self.linear_filter,
+                KeyCode::KeyC => {
+                    let next = self.colormap.colormap.next();
+                    self.colormap.set(&self.init, next);
+                }
+                KeyCode::KeyV => {
+                    self.channel = self.data.next_channel(self.channel);
+                    self.update_range();
+                }
+                KeyCode::KeyL => self.log_scale = !self.log_scale,
+                KeyCode::KeyH => self.show_histogram = !self.show_histogram,
+                KeyCode::KeyP if self.filter_chain.is_some() => {
+                    self.filters_enabled = !self.filters_enabled;
+                    self.reload();
+                }
+                KeyCode::KeyA => {
+                    self.auto_clip = !self.auto_clip;
+                    self.update_range();
+                }
+                _ => return false,
+            },
+            _ => return false,
+        }
+        self.update_title();
+        true
     }
 
-    fn update(&mut self) {}
+    fn update(&mut self, _dt: Duration) {
+        self.image_pass.set_quads(&self.init, &[self.view.quad_at(0.0, self.view.window_size)]);
+        let size = (self.init.config.width, self.init.config.height);
+        let colorbar = Quad::from_pixels(size.0 as f32 - 36.0, size.1 as f32 * 0.1, 20.0, size.1 as f32 * 0.8, size);
+        self.colorbar_pass.set_quads(&self.init, &[colorbar]);
+        let [x, y, width, height] = self.histogram_rect();
+        self.histogram_pass.set_quads(&self.init, &[Quad::from_pixels(x, y, width, height, size)]);
+        self.init.queue.write_buffer(&self.display_buffer, 0, bytemuck::bytes_of(&self.display_uniform()));
+    }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError>{
-        let output = self.init.surface.get_current_texture()?;
+        let output = self.init.get_current_frame()?;
         let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
         let mut encoder = self.init.device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder")
             }
         );
+        if self.histogram_stale {
+            self.histogram.compute(&self.init, &mut encoder, &self.texture_view, (self.data.width, self.data.height),
+                self.data.channels, self.histogram_range);
+            self.histogram_stale = false;
+        }
         {
+            // The black clear provides the letterbox bars around the image.
             let color_attachment = ws::create_color_attachment(&view);
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -128,9 +551,14 @@ impl<'a> State<'a> {
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
-            render_pass.set_pipeline(&self.pipeline);
-            render_pass.set_bind_group(0, &self.bind_group, &[]); // NEW!
-            render_pass.draw(0..6 as u32, 0..1);
+            let bind_groups = [&self.texture_bind_group, &self.display_bind_group];
+            self.image_pass.draw(&mut render_pass, &bind_groups);
+            if self.channel != Channel::Color {
+                self.colorbar_pass.draw(&mut render_pass, &bind_groups);
+            }
+            if self.show_histogram {
+                self.histogram_pass.draw(&mut render_pass, &bind_groups);
+            }
         }
         self.init.queue.submit(iter::once(encoder.finish()));
         output.present();
@@ -138,57 +566,35 @@ impl<'a> State<'a> {
     }
 }
 
-fn main() {
-    
-
-    let event_loop = EventLoop::new().unwrap();
-    let window  = Window::new(&event_loop).unwrap();
-    window.set_title("Chapter 02: Vertex buffer ");
-    env_logger::init();
-    
-    event_loop.set_control_flow(ControlFlow::Poll);
-
-    let mut state = pollster::block_on(State::new(&window));
-
-    let _ = event_loop.run(
-        move |event, elwt| {
-            match event {
-                Event::WindowEvent { ref event, window_id  } if window_id == state.window.id() => {
-                    if !state.input(event) {
-                        match event {
-                            WindowEvent::Resized(new_size) => {
-                                state.resize(*new_size);
-                            }
-                            WindowEvent::CloseRequested => {
-                                elwt.exit();
-                            }
-                            WindowEvent::RedrawRequested => {
-                                state.update();
-                                match state.render() {
-                                    Ok(_) => {}
-                                    // Reconfigure the surface if it's lost or outdated
-                                    Err(
-                                        wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
-                                    ) => state.resize(state.init.size),
-                                    // The system is out of memory, we should probably quit
-                                    Err(wgpu::SurfaceError::OutOfMemory | wgpu::SurfaceError::Other) => {
-                                        log::error!("OutOfMemory");
-                                        elwt.exit();
-                                    }
-                    
-                                    // This happens when the a frame takes too long to present
-                                    Err(wgpu::SurfaceError::Timeout) => {
-                                        log::warn!("Surface timeout")
-                                    }
-                                }
-                            }
-                            _ => (),
-                        }
-                    }
-                },
-                _ => (),
-            }
+/// Runs `chain` over 2D data on the GPU and reads the result back, so the inspector and value
+/// ranges see filtered values.
+fn apply_filters(init: &ws::IWgpuInit, chain: Option<&FilterChain>, data: ImageData) -> ImageData {
+    let Some(chain) = chain else {
+        return data;
+    };
+    if data.depth > 1 {
+        log::warn!("filters apply to 2D images only");
+        return data;
+    }
+    let filtered = FilterImage::from_rgba(init, data.width, data.height, &data.to_rgba()).and_then(|input| {
+        let output = chain.apply(init, &input);
+        Ok(ImageData::from_rgba(output.width, output.height, &output.read(init)?, data.normalized))
+    });
+    match filtered {
+        Ok(filtered) => filtered,
+        Err(e) => {
+            log::warn!("filtering failed: {}", e);
+            data
         }
-    );
+    }
+}
+
+pub fn example<'a>(init: ws::IWgpuInit<'a>, args: &[String]) -> anyhow::Result<Box<dyn Example + 'a>> {
+    let options = Options::parse(args.iter().cloned()).map_err(|e| anyhow!("{}\n{}\n\n{}", e, USAGE, app::RUN_USAGE))?;
+    init.set_title("imshow");
+    Ok(Box::new(State::new(init, options)?))
 }
 
+fn main() {
+    app::run(example);
+}
@@ -1,35 +1,129 @@
-struct VertexOutput {
-    @builtin(position) clip_position: vec4f,
-    @location(0) tex_coords: vec2f,
+// Appended to fullscreen::FULLSCREEN_WGSL. fs_main draws the image quad placed by the viewer,
+// fs_colorbar the colormap strip next to it and fs_histogram the histogram panel. imdiff appends
+// imdiff.wgsl to this file and reuses its bindings and helpers.
+
+struct Display {
+    // Values mapped to 0 and 1, already log-transformed when log_scale is set.
+    range: vec2f,
+    // -1 shows the color channels, 0..3 a single channel and 4 luminance through the colormap.
+    channel: i32,
+    log_scale: u32,
+    linear_filter: u32,
+    // Set when the surface is not sRGB and colors have to be encoded by hand.
+    encode_output: u32,
+    // Black and white level markers, as fractions of the histogram's value range.
+    levels: vec2f,
+    // Channels in the image texture.
+    channels: u32,
 }
 
 @group(0) @binding(0) var uImage: texture_2d<f32>;
-@group(0) @binding(1) var uSampler: sampler;
-
-@vertex
-fn vs_main(
-    @builtin(vertex_index) VertexIndex: u32
-) -> VertexOutput {
-     var pos = array<vec2f, 6>(
-        vec2f(0, 0),
-        vec2f(0, 1),
-        vec2f(1, 0),
-        vec2f(1, 0),
-        vec2f(0, 1),
-        vec2f(1, 1)
-    );
-
-
-    var output: VertexOutput;
-    var uik = pos[VertexIndex];
-    output.tex_coords = vec2f(uik.x, uik.y);
-    uik = (2.0*uik-1.0);
-    output.clip_position = vec4f(uik.x, -uik.y, 0.0, 1.0);
-    return output;
+@group(0) @binding(1) var uColormap: texture_2d<f32>;
+@group(0) @binding(2) var uColormapSampler: sampler;
+@group(1) @binding(0) var<uniform> display: Display;
+// 4 x 256 counts followed by each channel's largest count, see histogram::Histogram.
+@group(1) @binding(1) var<storage, read> histogram: array<u32>;
+
+fn fetch(image: texture_2d<f32>, p: vec2i) -> vec4f {
+    let size = vec2i(textureDimensions(image));
+    return textureLoad(image, clamp(p, vec2i(0), size - 1), 0);
+}
+
+// Float textures cannot use a filtering sampler, so bilinear filtering is done here.
+fn texel(image: texture_2d<f32>, uv: vec2f) -> vec4f {
+    let position = uv * vec2f(textureDimensions(image));
+    if display.linear_filter == 0u {
+        return fetch(image, vec2i(floor(position)));
+    }
+    let p = position - 0.5;
+    let base = vec2i(floor(p));
+    let f = fract(p);
+    let top = mix(fetch(image, base), fetch(image, base + vec2i(1, 0)), f.x);
+    let bottom = mix(fetch(image, base + vec2i(0, 1)), fetch(image, base + vec2i(1, 1)), f.x);
+    return mix(top, bottom, f.y);
+}
+
+fn normalize_value(v: f32) -> f32 {
+    var x = v;
+    if display.log_scale != 0u {
+        x = log(max(v, exp(display.range.x)));
+    }
+    return clamp((x - display.range.x) / max(display.range.y - display.range.x, 1e-20), 0.0, 1.0);
+}
+
+fn srgb_to_linear(c: vec3f) -> vec3f {
+    return select(pow((c + 0.055) / 1.055, vec3f(2.4)), c / 12.92, c <= vec3f(0.04045));
+}
+
+fn linear_to_srgb(c: vec3f) -> vec3f {
+    return select(1.055 * pow(c, vec3f(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3f(0.0031308));
+}
+
+fn colormap(t: f32) -> vec3f {
+    let size = f32(textureDimensions(uColormap).x);
+    let u = (t * (size - 1.0) + 0.5) / size;
+    return textureSampleLevel(uColormap, uColormapSampler, vec2f(u, 0.5), 0.0).rgb;
+}
+
+fn output(linear: vec3f, alpha: f32) -> vec4f {
+    if display.encode_output != 0u {
+        return vec4f(linear_to_srgb(linear), alpha);
+    }
+    return vec4f(linear, alpha);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let value = texel(uImage, in.uv);
+    if display.channel < 0 {
+        // Pixel values are display (sRGB-encoded) values, as in 8-bit image files.
+        let rgb = vec3f(normalize_value(value.r), normalize_value(value.g), normalize_value(value.b));
+        return output(srgb_to_linear(rgb), 1.0);
+    }
+    var v: f32;
+    if display.channel == 4 {
+        v = dot(value.rgb, vec3f(0.2126, 0.7152, 0.0722));
+    } else {
+        v = value[display.channel];
+    }
+    // NaN shows the background.
+    if v != v {
+        return vec4f(0.0, 0.0, 0.0, 1.0);
+    }
+    return output(colormap(normalize_value(v)), 1.0);
 }
 
 @fragment
-fn fs_main(ou: VertexOutput) -> @location(0) vec4f {
-    let vColor = textureSample(uImage, uSampler, ou.tex_coords);
-    return vColor;
-}   
\ No newline at end of file
+fn fs_colorbar(in: VertexOutput) -> @location(0) vec4f {
+    return output(colormap(1.0 - in.uv.y), 1.0);
+}
+
+@fragment
+fn fs_histogram(in: VertexOutput) -> @location(0) vec4f {
+    let pixel = fwidth(in.uv.x);
+    let bin = min(u32(in.uv.x * 256.0), 255u);
+    let height = 1.0 - in.uv.y;
+
+    // A single channel in gray, otherwise the color channels overlaid.
+    var first = 0u;
+    var last = min(display.channels, 3u);
+    if display.channel >= 0 && display.channel < 4 {
+        first = u32(display.channel);
+        last = first + 1u;
+    }
+    var color = vec3f(0.02);
+    for (var c = first; c < last; c++) {
+        let peak = max(histogram[1024u + c], 1u);
+        if height <= f32(histogram[c * 256u + bin]) / f32(peak) {
+            if last - first == 1u {
+                color += vec3f(0.6);
+            } else {
+                color[c] += 0.7;
+            }
+        }
+    }
+    if abs(in.uv.x - display.levels.x) < pixel || abs(in.uv.x - display.levels.y) < pixel {
+        color = vec3f(1.0, 0.8, 0.0);
+    }
+    return output(color, 1.0);
+}
@@ -0,0 +1,100 @@
+use wgpu_gp::fullscreen::Quad;
+
+const MIN_SCALE: f32 = 1.0 / 64.0;
+const MAX_SCALE: f32 = 256.0;
+
+/// Placement of an image inside the window: `scale` window pixels per image pixel and the
+/// window position of the image's top-left corner.
+#[derive(Clone, Copy, Debug)]
+pub struct View {
+    pub image_size: (u32, u32),
+    pub window_size: (u32, u32),
+    pub scale: f32,
+    pub offset: [f32; 2],
+    /// Refit on resize until the user zooms or pans.
+    pub fit: bool,
+}
+
+impl View {
+    pub fn new(image_size: (u32, u32), window_size: (u32, u32)) -> Self {
+        let mut view = Self { image_size, window_size, scale: 1.0, offset: [0.0; 2], fit: true };
+        view.fit_to_window();
+        view
+    }
+
+    /// Largest scale that shows the whole image, centered with letterbox bars.
+    pub fn fit_to_window(&mut self) {
+        let (iw, ih) = (self.image_size.0.max(1) as f32, self.image_size.1.max(1) as f32);
+        let (ww, wh) = (self.window_size.0 as f32, self.window_size.1 as f32);
+        self.scale = (ww / iw).min(wh / ih).max(MIN_SCALE);
+        self.center();
+        self.fit = true;
+    }
+
+    /// One image pixel per window pixel, centered.
+    pub fn actual_size(&mut self) {
+        self.scale = 1.0;
+        self.center();
+        self.fit = false;
+    }
+
+    pub fn resize(&mut self, window_size: (u32, u32)) {
+        let old = self.window_size;
+        self.window_size = window_size;
+        if self.fit {
+            self.fit_to_window();
+        } else {
+            // Keep whatever was at the window center in the center.
+            self.offset[0] += (window_size.0 as f32 - old.0 as f32) * 0.5;
+            self.offset[1] += (window_size.1 as f32 - old.1 as f32) * 0.5;
+        }
+    }
+
+    /// Multiplies the scale by `factor`, keeping the image point under `cursor` (window pixels) fixed.
+    pub fn zoom_at(&mut self, cursor: [f32; 2], factor: f32) {
+        let scale = (self.scale * factor).clamp(MIN_SCALE, MAX_SCALE);
+        let ratio = scale / self.scale;
+        self.offset = [
+            cursor[0] - (cursor[0] - self.offset[0]) * ratio,
+            cursor[1] - (cursor[1] - self.offset[1]) * ratio,
+        ];
+        self.scale = scale;
+        self.fit = false;
+    }
+
+    pub fn zoom_centered(&mut self, factor: f32) {
+        let center = [self.window_size.0 as f32 * 0.5, self.window_size.1 as f32 * 0.5];
+        self.zoom_at(center, factor);
+    }
+
+    pub fn pan(&mut self, delta: [f32; 2]) {
+        self.offset[0] += delta[0];
+        self.offset[1] += delta[1];
+        self.fit = false;
+    }
+
+    /// Image pixel coordinates (fractional) under a window position.
+    pub fn window_to_image(&self, position: [f32; 2]) -> [f32; 2] {
+        [(position[0] - self.offset[0]) / self.scale, (position[1] - self.offset[1]) / self.scale]
+    }
+
+    /// The quad covering the image on screen, moved right by `x` window pixels inside a target of
+    /// `target_size`, which is larger than `window_size` when the view covers part of the window.
+    /// Parts outside the target are clipped by the rasterizer.
+    pub fn quad_at(&self, x: f32, target_size: (u32, u32)) -> Quad {
+        Quad::from_pixels(
+            self.offset[0] + x,
+            self.offset[1],
+            self.image_size.0 as f32 * self.scale,
+            self.image_size.1 as f32 * self.scale,
+            target_size,
+        )
+    }
+
+    fn center(&mut self) {
+        self.offset = [
+            (self.window_size.0 as f32 - self.image_size.0 as f32 * self.scale) * 0.5,
+            (self.window_size.1 as f32 - self.image_size.1 as f32 * self.scale) * 0.5,
+        ];
+    }
+}
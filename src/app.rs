@@ -0,0 +1,324 @@
+use std::{
+    cell::Cell,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use image::RgbaImage;
+use winit::{
+    dpi::PhysicalSize,
+    event::{DeviceEvent, ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::WindowBuilder,
+};
+
+use crate::{capture, depth::DepthConfig, helpers::IWgpuInit, record::Recorder};
+
+/// Frames per second simulated when time does not come from the clock, e.g. in tests.
+pub const FIXED_FRAME_RATE: u32 = 60;
+/// Time step between frames at `FIXED_FRAME_RATE`.
+pub const FIXED_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / FIXED_FRAME_RATE as u64);
+
+/// Frame size of headless runs without `--size`.
+const DEFAULT_SIZE: PhysicalSize<u32> = PhysicalSize { width: 800, height: 600 };
+
+/// The options `run` handles for every example; the remaining arguments go to the example.
+pub const RUN_USAGE: &str = "common options:
+  --frames N          render N frames with a fixed time step, then exit
+  --screenshot FILE   save the last frame as an image and exit (after one frame unless --frames)
+  --size WxH          window or frame size in pixels
+  --samples N         MSAA sample count for scenes rendered directly to the window (default 1),
+                      lowered to what the adapter supports; M cycles through those counts
+  --backend LIST      graphics backends to try, e.g. vulkan,metal,dx12,gl
+  --depth FORMAT      depth buffer format: Depth16Unorm, Depth24Plus (default),
+                      Depth24PlusStencil8, Depth32Float or Depth32FloatStencil8
+  --reversed-z        map near to depth 1 and far to 0
+  --headless          render offscreen without opening a window
+  --record PATH       record frames with a fixed time step to PATH.gif, PATH.y4m or PNGs in a
+                      directory
+  --record-every N    record every Nth frame (default 1)";
+
+/// A scene driven by `run` in a window, or by `render_frames` without one. Implementors own
+/// their `IWgpuInit` and render to `IWgpuInit::get_current_frame`.
+pub trait Example {
+    fn init(&self) -> &IWgpuInit<'_>;
+
+    fn resize(&mut self, new_size: PhysicalSize<u32>);
+
+    /// Returns true when the event was used and should not be handled further.
+    fn input(&mut self, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    fn device_event(&mut self, _event: &DeviceEvent) {}
+
+    /// Called before each frame with the time since the previous one.
+    fn update(&mut self, _dt: Duration) {}
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>;
+
+    /// Switches to the supported sample count nearest to `requested` with
+    /// `IWgpuInit::set_sample_count`, rebuilds what depends on it and returns the count in
+    /// effect; `None` when the example's sample count is fixed.
+    fn set_sample_count(&mut self, _requested: u32) -> Option<u32> {
+        None
+    }
+}
+
+/// The common command-line options, see `RUN_USAGE`.
+#[derive(Clone, Debug)]
+pub struct RunOptions {
+    pub frames: Option<u32>,
+    pub screenshot: Option<PathBuf>,
+    pub size: Option<PhysicalSize<u32>>,
+    pub samples: u32,
+    pub backends: wgpu::Backends,
+    pub depth: DepthConfig,
+    pub headless: bool,
+    pub record: Option<PathBuf>,
+    pub record_every: u32,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self { frames: None, screenshot: None, size: None, samples: 1, backends: wgpu::Backends::all(),
+            depth: DepthConfig::default(), headless: false,
+            record: None, record_every: 1 }
+    }
+}
+
+impl RunOptions {
+    /// Takes the common options out of `args` and returns them with the remaining arguments.
+    pub fn parse(args: &[String]) -> anyhow::Result<(Self, Vec<String>)> {
+        let mut options = Self::default();
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().with_context(|| format!("{} needs a value", name));
+            match arg.as_str() {
+                "--frames" => {
+                    let frames: u32 = value("--frames")?.parse().context("--frames")?;
+                    if frames == 0 {
+                        bail!("--frames must be at least 1");
+                    }
+                    options.frames = Some(frames);
+                }
+                "--screenshot" => options.screenshot = Some(value("--screenshot")?.into()),
+                "--size" => {
+                    let size = value("--size")?;
+                    let (width, height) = size.split_once('x').with_context(|| format!("--size {}: expected WxH", size))?;
+                    let size = PhysicalSize::new(
+                        width.parse().with_context(|| format!("--size {}", size))?,
+                        height.parse().with_context(|| format!("--size {}", size))?,
+                    );
+                    if size.width == 0 || size.height == 0 {
+                        bail!("--size must not be empty");
+                    }
+                    options.size = Some(size);
+                }
+                "--samples" => options.samples = value("--samples")?.parse().context("--samples")?,
+                "--backend" => {
+                    let list = value("--backend")?;
+                    options.backends = wgpu::Backends::from_comma_list(list);
+                    if options.backends.is_empty() {
+                        bail!("--backend {}: no known backend", list);
+                    }
+                }
+                "--depth" => options.depth.format = DepthConfig::parse_format(value("--depth")?)?,
+                "--reversed-z" => options.depth.reversed_z = true,
+                "--headless" => options.headless = true,
+                "--record" => options.record = Some(value("--record")?.into()),
+                "--record-every" => {
+                    options.record_every = value("--record-every")?.parse().context("--record-every")?;
+                    if options.record_every == 0 {
+                        bail!("--record-every must be at least 1");
+                    }
+                }
+                _ => rest.push(arg.clone()),
+            }
+        }
+        if !options.samples.is_power_of_two() {
+            bail!("--samples must be a power of two");
+        }
+        Ok((options, rest))
+    }
+
+    /// Frames to render before exiting, `None` to run until the window is closed.
+    pub fn frame_limit(&self) -> Option<u32> {
+        self.frames.or((self.screenshot.is_some() || self.headless).then_some(1))
+    }
+}
+
+/// Runs an example built from the command-line arguments (without the program name), see
+/// `RUN_USAGE` for the options handled here. By default it opens a window and runs until the
+/// window is closed. Errors are printed and exit with status 2.
+pub fn run<F>(build: F)
+where
+    F: for<'a> FnOnce(IWgpuInit<'a>, &[String]) -> anyhow::Result<Box<dyn Example + 'a>>,
+{
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    env_logger::init();
+    let (options, args) = RunOptions::parse(&args).unwrap_or_else(|e| {
+        eprintln!("{:#}\n{}", e, RUN_USAGE);
+        std::process::exit(2);
+    });
+    let result = if options.headless { run_headless(build, &options, &args) } else { run_window(build, &options, &args) };
+    if let Err(e) = result {
+        eprintln!("{:#}", e);
+        std::process::exit(2);
+    }
+}
+
+fn run_headless<F>(build: F, options: &RunOptions, args: &[String]) -> anyhow::Result<()>
+where
+    F: for<'a> FnOnce(IWgpuInit<'a>, &[String]) -> anyhow::Result<Box<dyn Example + 'a>>,
+{
+    let size = options.size.unwrap_or(DEFAULT_SIZE);
+    let mut init = pollster::block_on(IWgpuInit::new_headless(size, options.samples, None, options.backends, false))?;
+    init.set_depth(options.depth)?;
+    let mut example = build(init, args)?;
+    let mut recorder = options.record.as_deref().map(|path| Recorder::new(path, options.record_every)).transpose()?;
+    for _ in 0..options.frame_limit().unwrap_or(1) {
+        example.update(FIXED_TIMESTEP);
+        example.render()?;
+        if let Some(recorder) = &mut recorder {
+            recorder.capture(example.init())?;
+        }
+    }
+    if let Some(recorder) = recorder {
+        recorder.finish(example.init())?;
+    }
+    if let Some(path) = &options.screenshot {
+        save_image(&capture::read_frame(example.init())?, path)?;
+    }
+    Ok(())
+}
+
+fn run_window<F>(build: F, options: &RunOptions, args: &[String]) -> anyhow::Result<()>
+where
+    F: for<'a> FnOnce(IWgpuInit<'a>, &[String]) -> anyhow::Result<Box<dyn Example + 'a>>,
+{
+    let event_loop = EventLoop::new()?;
+    let mut builder = WindowBuilder::new();
+    if let Some(size) = options.size {
+        builder = builder.with_inner_size(size);
+    }
+    let window = builder.build(&event_loop)?;
+
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let window = &window;
+    let mut init = pollster::block_on(IWgpuInit::new_with_backends(window, options.samples, None, options.backends));
+    init.set_depth(options.depth)?;
+    if options.screenshot.is_some() || options.record.is_some() {
+        init.set_offscreen(true);
+    }
+    let mut example = build(init, args)?;
+    log::info!("MSAA {}x", example.init().sample_count);
+    let mut recorder = options.record.as_deref().map(|path| Recorder::new(path, options.record_every)).transpose()?;
+    // Scripted runs and recordings step a fixed time so their frames are reproducible.
+    let fixed_timestep = options.frame_limit().is_some() || recorder.is_some();
+    let frame_limit = options.frame_limit();
+    let mut frames = 0;
+    let mut last_frame = Instant::now();
+    let result = Cell::new(Ok(()));
+
+    event_loop.run(
+        |event, elwt| {
+            match event {
+                Event::WindowEvent { ref event, window_id }
+                    if window_id == window.id() && !example.input(event) => {
+                    match event {
+                        WindowEvent::Resized(new_size) => {
+                            example.resize(*new_size);
+                        }
+                        WindowEvent::CloseRequested => {
+                            elwt.exit();
+                        }
+                        WindowEvent::KeyboardInput {
+                            event: KeyEvent { physical_key: PhysicalKey::Code(KeyCode::KeyM), state: ElementState::Pressed, .. },
+                            ..
+                        } => {
+                            cycle_sample_count(example.as_mut());
+                        }
+                        WindowEvent::RedrawRequested => {
+                            let now = Instant::now();
+                            example.update(if fixed_timestep { FIXED_TIMESTEP } else { now - last_frame });
+                            last_frame = now;
+                            match example.render() {
+                                Ok(_) => {
+                                    frames += 1;
+                                    if let Some(Err(e)) = recorder.as_mut().map(|r| r.capture(example.init())) {
+                                        result.set(Err(e));
+                                        elwt.exit();
+                                    }
+                                }
+                                // Reconfigure the surface if it's lost or outdated
+                                Err(
+                                    wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated,
+                                ) => {
+                                    let size = example.init().size;
+                                    example.resize(size);
+                                }
+                                // The system is out of memory, we should probably quit
+                                Err(wgpu::SurfaceError::OutOfMemory | wgpu::SurfaceError::Other) => {
+                                    log::error!("OutOfMemory");
+                                    elwt.exit();
+                                }
+
+                                // This happens when the a frame takes too long to present
+                                Err(wgpu::SurfaceError::Timeout) => {
+                                    log::warn!("Surface timeout")
+                                }
+                            }
+                            if frame_limit.is_some_and(|limit| frames >= limit) {
+                                if let Some(path) = &options.screenshot {
+                                    result.set(capture::read_frame(example.init()).and_then(|frame| save_image(&frame, path)));
+                                }
+                                elwt.exit();
+                            }
+                            window.request_redraw();
+                        }
+                        _ => (),
+                    }
+                },
+                Event::DeviceEvent { ref event, .. } => {
+                    example.device_event(event);
+                },
+                _ => (),
+            }
+        }
+    )?;
+    result.into_inner()?;
+    if let Some(recorder) = recorder {
+        recorder.finish(example.init())?;
+    }
+    Ok(())
+}
+
+/// Moves an example to the next supported sample count, wrapping around to 1.
+fn cycle_sample_count(example: &mut dyn Example) {
+    let current = example.init().sample_count;
+    let counts = example.init().supported_sample_counts();
+    let next = counts.iter().copied().find(|&count| count > current).unwrap_or(1);
+    match example.set_sample_count(next) {
+        Some(count) => log::info!("MSAA {}x", count),
+        None => log::info!("this example has a fixed sample count"),
+    }
+}
+
+/// Updates and renders a headless example `frames` times, `dt` apart, and reads back the last
+/// frame.
+pub fn render_frames(example: &mut dyn Example, frames: u32, dt: Duration) -> anyhow::Result<RgbaImage> {
+    for _ in 0..frames.max(1) {
+        example.update(dt);
+        example.render()?;
+    }
+    capture::read_frame(example.init())
+}
+
+fn save_image(image: &RgbaImage, path: &Path) -> anyhow::Result<()> {
+    image.save(path).with_context(|| format!("failed to save {}", path.display()))
+}
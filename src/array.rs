@@ -0,0 +1,430 @@
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context, Result};
+
+use crate::helpers::IWgpuInit;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarKind {
+    Float,
+    Int,
+    UInt,
+    Bool,
+}
+
+/// Element type of a `.npy` or raw file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DType {
+    pub kind: ScalarKind,
+    /// Bytes per element.
+    pub size: usize,
+    pub big_endian: bool,
+}
+
+impl DType {
+    pub const F32: DType = DType { kind: ScalarKind::Float, size: 4, big_endian: false };
+
+    /// Parses NumPy descriptors (`<f4`, `>u2`, `|b1`) as well as `f32`, `u16`, `float64`, ...;
+    /// without a byte order prefix the data is little-endian. Sizes count bytes after a byte order
+    /// prefix, as in NumPy, and bits otherwise, except for `f2`, `f4`, `f8`, `i1`, ... which only
+    /// make sense as bytes.
+    pub fn parse(s: &str) -> Result<Self> {
+        let (prefixed, big_endian, rest) = match s.as_bytes().first() {
+            Some(b'>') => (true, true, &s[1..]),
+            Some(b'<' | b'|' | b'=') => (true, false, &s[1..]),
+            _ => (false, false, s),
+        };
+        let named = match rest {
+            "float16" | "half" => Some((ScalarKind::Float, 2)),
+            "float32" | "float" => Some((ScalarKind::Float, 4)),
+            "float64" | "double" => Some((ScalarKind::Float, 8)),
+            "int8" => Some((ScalarKind::Int, 1)),
+            "int16" => Some((ScalarKind::Int, 2)),
+            "int32" => Some((ScalarKind::Int, 4)),
+            "int64" => Some((ScalarKind::Int, 8)),
+            "uint8" => Some((ScalarKind::UInt, 1)),
+            "uint16" => Some((ScalarKind::UInt, 2)),
+            "uint32" => Some((ScalarKind::UInt, 4)),
+            "uint64" => Some((ScalarKind::UInt, 8)),
+            "bool" => Some((ScalarKind::Bool, 1)),
+            _ => None,
+        };
+        let (kind, size) = match named {
+            Some(named) => named,
+            None => {
+                let kind = match rest.chars().next() {
+                    Some('f') => ScalarKind::Float,
+                    Some('i') => ScalarKind::Int,
+                    Some('u') => ScalarKind::UInt,
+                    Some('b') => ScalarKind::Bool,
+                    _ => bail!("unsupported dtype '{}'", s),
+                };
+                let number: usize = rest[1..].parse().with_context(|| format!("unsupported dtype '{}'", s))?;
+                let bits = !prefixed && (matches!(number, 16 | 32 | 64) || (number == 8 && kind != ScalarKind::Float));
+                let size = if bits { number / 8 } else { number };
+                (kind, size)
+            }
+        };
+        let valid = match kind {
+            ScalarKind::Float => matches!(size, 2 | 4 | 8),
+            ScalarKind::Int | ScalarKind::UInt => matches!(size, 1 | 2 | 4 | 8),
+            ScalarKind::Bool => size == 1,
+        };
+        ensure!(valid, "unsupported dtype '{}'", s);
+        Ok(Self { kind, size, big_endian })
+    }
+
+    /// Converts one element; integers larger than 2^24 lose precision.
+    fn read(&self, bytes: &[u8]) -> f32 {
+        let mut buf = [0u8; 8];
+        buf[..self.size].copy_from_slice(bytes);
+        if self.big_endian {
+            buf[..self.size].reverse();
+        }
+        let bits = u64::from_le_bytes(buf);
+        match (self.kind, self.size) {
+            (ScalarKind::Float, 2) => f16_to_f32(bits as u16),
+            (ScalarKind::Float, 4) => f32::from_bits(bits as u32),
+            (ScalarKind::Float, _) => f64::from_bits(bits) as f32,
+            (ScalarKind::Int, size) => {
+                let shift = 64 - 8 * size as u32;
+                (((bits << shift) as i64) >> shift) as f32
+            }
+            (ScalarKind::UInt, _) => bits as f32,
+            (ScalarKind::Bool, _) => (bits != 0) as u8 as f32,
+        }
+    }
+}
+
+/// How an `Array` maps to a texture: 2D arrays are `(height, width)`, 3D arrays are
+/// `(depth, height, width)` unless the last axis has at most 4 entries, in which case it holds
+/// channels, and 4D arrays are `(depth, height, width, channels)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArrayLayout {
+    pub width: u32,
+    pub height: u32,
+    /// 1 for 2D arrays.
+    pub depth: u32,
+    /// 1 to 4 as stored; textures pad 3 channels to 4.
+    pub channels: u32,
+}
+
+impl ArrayLayout {
+    /// Channels per texel: 1, 2 or 4.
+    pub fn texel_channels(&self) -> u32 {
+        if self.channels == 3 { 4 } else { self.channels }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        match self.texel_channels() {
+            1 => wgpu::TextureFormat::R32Float,
+            2 => wgpu::TextureFormat::Rg32Float,
+            _ => wgpu::TextureFormat::Rgba32Float,
+        }
+    }
+}
+
+/// An n-dimensional array converted to f32, stored in C (row-major) order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Array {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+impl Array {
+    pub fn from_npy(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::parse_npy(&bytes).with_context(|| format!("invalid .npy file {}", path.display()))
+    }
+
+    /// Parses the NumPy `.npy` format, versions 1 to 3.
+    pub fn parse_npy(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() >= 10 && &bytes[..6] == b"\x93NUMPY", "missing .npy magic");
+        let (header_len, start) = match bytes[6] {
+            1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+            2 | 3 => {
+                ensure!(bytes.len() >= 12, "truncated header");
+                (u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12)
+            }
+            version => bail!("unsupported .npy version {}", version),
+        };
+        let header = bytes.get(start..start + header_len).context("truncated header")?;
+        let header = std::str::from_utf8(header).context("header is not text")?;
+        let descr = header_value(header, "descr")?;
+        let dtype = DType::parse(descr.trim_matches(|c| c == '\'' || c == '"'))?;
+        let fortran_order = match header_value(header, "fortran_order")? {
+            "True" => true,
+            "False" => false,
+            other => bail!("bad fortran_order '{}'", other),
+        };
+        let shape = parse_shape(header_value(header, "shape")?)?;
+        Self::from_bytes(&bytes[start + header_len..], shape, dtype, fortran_order)
+    }
+
+    /// Reads headerless binary data of the given shape and element type.
+    pub fn from_raw(path: impl AsRef<Path>, shape: &[usize], dtype: DType, fortran_order: bool) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_bytes(&bytes, shape.to_vec(), dtype, fortran_order).with_context(|| format!("invalid raw file {}", path.display()))
+    }
+
+    pub fn from_bytes(bytes: &[u8], shape: Vec<usize>, dtype: DType, fortran_order: bool) -> Result<Self> {
+        let count: usize = shape.iter().product();
+        let needed = count * dtype.size;
+        ensure!(bytes.len() >= needed, "{} bytes of data, shape {:?} of {}-byte elements needs {}", bytes.len(), shape, dtype.size, needed);
+        let values: Vec<f32> = bytes[..needed].chunks_exact(dtype.size).map(|b| dtype.read(b)).collect();
+        let data = if fortran_order && shape.len() > 1 { fortran_to_c(&values, &shape) } else { values };
+        Ok(Self { shape, data })
+    }
+
+    pub fn layout(&self) -> Result<ArrayLayout> {
+        let dims: Vec<u32> = self.shape.iter().map(|&d| d as u32).collect();
+        let (depth, height, width, channels) = match dims[..] {
+            [w] => (1, 1, w, 1),
+            [h, w] => (1, h, w, 1),
+            [h, w, c] if c <= 4 => (1, h, w, c),
+            [d, h, w] => (d, h, w, 1),
+            [d, h, w, c] if c <= 4 => (d, h, w, c),
+            _ => bail!("cannot show an array of shape {:?} as an image", self.shape),
+        };
+        ensure!(width > 0 && height > 0 && depth > 0 && channels > 0, "empty array of shape {:?}", self.shape);
+        Ok(ArrayLayout { width, height, depth, channels })
+    }
+
+    /// The data with 3-channel texels padded to 4 (alpha 1), ready for upload.
+    pub fn texels(&self) -> Result<Vec<f32>> {
+        let layout = self.layout()?;
+        if layout.channels != 3 {
+            return Ok(self.data.clone());
+        }
+        Ok(self.data.chunks_exact(3).flat_map(|c| [c[0], c[1], c[2], 1.0]).collect())
+    }
+
+    /// Uploads the array as an R32Float, Rg32Float or Rgba32Float texture, 3D when the layout has
+    /// more than one slice. Float textures are not filterable by default; read them with
+    /// `textureLoad` or a non-filtering sampler.
+    pub fn create_texture(&self, init: &IWgpuInit) -> Result<wgpu::Texture> {
+        let layout = self.layout()?;
+        let limits = init.device.limits();
+        let (dimension, max) = if layout.depth > 1 {
+            (wgpu::TextureDimension::D3, limits.max_texture_dimension_3d)
+        } else {
+            (wgpu::TextureDimension::D2, limits.max_texture_dimension_2d)
+        };
+        ensure!(
+            layout.width <= max && layout.height <= max && layout.depth <= max,
+            "array of shape {:?} exceeds the texture size limit {}", self.shape, max
+        );
+        let size = wgpu::Extent3d { width: layout.width, height: layout.height, depth_or_array_layers: layout.depth };
+        let texture = init.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Array Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension,
+            format: layout.format(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        init.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&self.texels()?),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * layout.texel_channels() * layout.width),
+                rows_per_image: Some(layout.height),
+            },
+            size,
+        );
+        Ok(texture)
+    }
+
+    pub fn create_texture_view(&self, init: &IWgpuInit) -> Result<wgpu::TextureView> {
+        Ok(self.create_texture(init)?.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+}
+
+/// The raw text of `key`'s value in a `.npy` header dictionary.
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str> {
+    let quoted = [format!("'{}'", key), format!("\"{}\"", key)];
+    let at = quoted.iter().find_map(|q| header.find(q.as_str()).map(|i| i + q.len())).with_context(|| format!("header lacks '{}'", key))?;
+    let rest = header[at..].trim_start().strip_prefix(':').context("malformed header")?.trim_start();
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|i| i + 1)
+    } else {
+        rest.find([',', '}'])
+    };
+    Ok(rest[..end.context("malformed header")?].trim())
+}
+
+fn parse_shape(text: &str) -> Result<Vec<usize>> {
+    let inner = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')).context("malformed shape")?;
+    inner
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.trim_end_matches('L').parse::<usize>().with_context(|| format!("bad shape entry '{}'", s)))
+        .collect()
+}
+
+/// Reorders column-major values into row-major order.
+fn fortran_to_c(values: &[f32], shape: &[usize]) -> Vec<f32> {
+    let mut strides = vec![1; shape.len()];
+    for i in 1..shape.len() {
+        strides[i] = strides[i - 1] * shape[i - 1];
+    }
+    let mut index = vec![0; shape.len()];
+    let mut out = Vec::with_capacity(values.len());
+    for _ in 0..values.len() {
+        out.push(values[index.iter().zip(&strides).map(|(i, s)| i * s).sum::<usize>()]);
+        // Increment the multi-index with the last axis fastest.
+        for axis in (0..shape.len()).rev() {
+            index[axis] += 1;
+            if index[axis] < shape[axis] {
+                break;
+            }
+            index[axis] = 0;
+        }
+    }
+    out
+}
+
+fn f16_to_f32(h: u16) -> f32 {
+    let sign = if h & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((h >> 10) & 0x1f) as i32;
+    let mantissa = (h & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => sign * f32::INFINITY,
+        31 => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `.npy` file of the given format version with `header` padded as NumPy pads it.
+    fn npy(version: u8, header: &str, data: &[u8]) -> Vec<u8> {
+        let prefix = if version == 1 { 10 } else { 12 };
+        let mut header = header.to_string();
+        while !(prefix + header.len() + 1).is_multiple_of(64) {
+            header.push(' ');
+        }
+        header.push('\n');
+        let mut bytes = b"\x93NUMPY".to_vec();
+        bytes.extend([version, 0]);
+        if version == 1 {
+            bytes.extend((header.len() as u16).to_le_bytes());
+        } else {
+            bytes.extend((header.len() as u32).to_le_bytes());
+        }
+        bytes.extend(header.as_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    fn le_f32(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn dtype_names() {
+        assert_eq!(DType::parse("<f4").unwrap(), DType::F32);
+        assert_eq!(DType::parse("f32").unwrap(), DType::F32);
+        assert_eq!(DType::parse(">f8").unwrap(), DType { kind: ScalarKind::Float, size: 8, big_endian: true });
+        assert_eq!(DType::parse("|b1").unwrap(), DType { kind: ScalarKind::Bool, size: 1, big_endian: false });
+        assert_eq!(DType::parse("u16").unwrap(), DType { kind: ScalarKind::UInt, size: 2, big_endian: false });
+        assert_eq!(DType::parse("i8").unwrap(), DType { kind: ScalarKind::Int, size: 1, big_endian: false });
+        assert_eq!(DType::parse("f2").unwrap(), DType { kind: ScalarKind::Float, size: 2, big_endian: false });
+        assert!(DType::parse("<f3").is_err());
+        assert!(DType::parse("c8").is_err());
+    }
+
+    #[test]
+    fn npy_v1() {
+        let bytes = npy(1, "{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }", &le_f32(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]));
+        let array = Array::parse_npy(&bytes).unwrap();
+        assert_eq!(array.shape, [2, 3]);
+        assert_eq!(array.data, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn npy_v2_big_endian() {
+        let data: Vec<u8> = [1.5f64, -2.0, 1e10].iter().flat_map(|v| v.to_be_bytes()).collect();
+        let array = Array::parse_npy(&npy(2, "{'descr': '>f8', 'fortran_order': False, 'shape': (3,), }", &data)).unwrap();
+        assert_eq!(array.shape, [3]);
+        assert_eq!(array.data, [1.5, -2.0, 1e10]);
+    }
+
+    #[test]
+    fn npy_v3_bool() {
+        let array = Array::parse_npy(&npy(3, "{'descr': '|b1', 'fortran_order': False, 'shape': (1, 4), }", &[0, 1, 2, 0])).unwrap();
+        assert_eq!(array.shape, [1, 4]);
+        assert_eq!(array.data, [0.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn npy_fortran_order() {
+        // Column-major data of a 2x3x4 array whose element (i, j, k) is 100i + 10j + k.
+        let shape = [2, 3, 4];
+        let mut column_major = Vec::new();
+        for k in 0..4 {
+            for j in 0..3 {
+                for i in 0..2 {
+                    column_major.push((100 * i + 10 * j + k) as f32);
+                }
+            }
+        }
+        let header = "{'descr': '<f4', 'fortran_order': True, 'shape': (2, 3, 4), }";
+        let array = Array::parse_npy(&npy(1, header, &le_f32(&column_major))).unwrap();
+        assert_eq!(array.shape, shape);
+        let row_major: Vec<f32> = (0..2)
+            .flat_map(|i| (0..3).flat_map(move |j| (0..4).map(move |k| (100 * i + 10 * j + k) as f32)))
+            .collect();
+        assert_eq!(array.data, row_major);
+    }
+
+    #[test]
+    fn f16_special_values() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        // Smallest and largest subnormals.
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x03ff), 1023.0 * 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x8001), -(2f32.powi(-24)));
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+        assert!(f16_to_f32(0xfc01).is_nan());
+    }
+
+    #[test]
+    fn header_values() {
+        let header = "{\"descr\": \"<u2\", 'fortran_order': False, 'shape': (5L, 7), }";
+        assert_eq!(header_value(header, "descr").unwrap(), "\"<u2\"");
+        assert_eq!(header_value(header, "fortran_order").unwrap(), "False");
+        assert_eq!(parse_shape(header_value(header, "shape").unwrap()).unwrap(), [5, 7]);
+        assert_eq!(parse_shape("()").unwrap(), Vec::<usize>::new());
+        assert!(parse_shape("(2, x)").is_err());
+    }
+
+    #[test]
+    fn npy_errors() {
+        let truncated = npy(1, "{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }", &le_f32(&[0.0; 5]));
+        assert!(Array::parse_npy(&truncated).is_err());
+        let no_shape = npy(1, "{'descr': '<f4', 'fortran_order': False, }", &le_f32(&[0.0; 6]));
+        assert!(Array::parse_npy(&no_shape).is_err());
+        let short_header = &npy(1, "{'descr': '<f4', 'fortran_order': False, 'shape': (2,), }", &[])[..20];
+        assert!(Array::parse_npy(short_header).is_err());
+        assert!(Array::parse_npy(b"\x93NUMPX\x01\x00\x00\x00").is_err());
+        assert!(Array::parse_npy(&npy(4, "{}", &[])).is_err());
+    }
+}
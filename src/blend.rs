@@ -0,0 +1,77 @@
+use anyhow::{bail, Result};
+
+/// How a color target combines fragment output with what it holds, see
+/// `IRenderPipeline::blend`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Overwrites the target; for opaque geometry.
+    Replace,
+    /// `src * a + dst * (1 - a)`, for straight (non-premultiplied) colors.
+    Alpha,
+    /// `src + dst * (1 - a)`, for colors already multiplied by their alpha.
+    PremultipliedAlpha,
+    /// `src * a + dst`, brightening; for glows and particles. Keeps the target's alpha.
+    Additive,
+    /// `src * dst`, darkening; alpha is ignored and the target's is kept.
+    Multiply,
+    /// `src + dst - src * dst`, the inverse of multiplying the inverses; brightens without
+    /// exceeding 1. Keeps the target's alpha.
+    Screen,
+    /// Any other blend state, e.g. for accumulation targets.
+    Custom(wgpu::BlendState),
+}
+
+impl BlendMode {
+    /// The named presets, in the order a key press cycles through them.
+    pub const PRESETS: [BlendMode; 6] = [
+        BlendMode::Replace,
+        BlendMode::Alpha,
+        BlendMode::PremultipliedAlpha,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+    ];
+
+    /// A preset by its `name`.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match Self::PRESETS.into_iter().find(|mode| mode.name() == name) {
+            Some(mode) => Ok(mode),
+            None => {
+                let names: Vec<&str> = Self::PRESETS.iter().map(BlendMode::name).collect();
+                bail!("unknown blend mode {}, expected one of {}", name, names.join(", "))
+            }
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Replace => "replace",
+            BlendMode::Alpha => "alpha",
+            BlendMode::PremultipliedAlpha => "premultiplied",
+            BlendMode::Additive => "additive",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Custom(_) => "custom",
+        }
+    }
+
+    /// Whether fragment colors must be multiplied by their alpha before blending.
+    pub fn is_premultiplied(&self) -> bool {
+        matches!(self, BlendMode::PremultipliedAlpha)
+    }
+
+    pub fn state(&self) -> wgpu::BlendState {
+        use wgpu::{BlendComponent, BlendFactor as F, BlendOperation};
+        let component = |src_factor, dst_factor| BlendComponent { src_factor, dst_factor, operation: BlendOperation::Add };
+        let keep_alpha = component(F::Zero, F::One);
+        match *self {
+            BlendMode::Replace => wgpu::BlendState::REPLACE,
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::PremultipliedAlpha => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState { color: component(F::SrcAlpha, F::One), alpha: keep_alpha },
+            BlendMode::Multiply => wgpu::BlendState { color: component(F::Dst, F::Zero), alpha: keep_alpha },
+            BlendMode::Screen => wgpu::BlendState { color: component(F::One, F::OneMinusSrc), alpha: keep_alpha },
+            BlendMode::Custom(state) => state,
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{
+    EuclideanSpace, InnerSpace, Matrix3, Matrix4, Point3, Quaternion, Rad, Rotation, Rotation3, SquareMatrix,
+    Vector3, Zero,
+};
+use wgpu::util::DeviceExt;
+
+use crate::depth::DepthConfig;
+use crate::helpers::IWgpuInit;
+use crate::model::{ModelCamera, Projection as ModelProjection};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective { fovy: Rad<f32>, znear: f32, zfar: f32 },
+    /// `height` is the extent of the view volume along Y; the width follows the aspect ratio.
+    Orthographic { height: f32, znear: f32, zfar: f32 },
+}
+
+/// A right-handed camera looking down its local -Z axis with +Y up.
+///
+/// Projection matrices map view depth into wgpu's 0..1 clip range directly (near -> 0, far -> 1),
+/// or near -> 1, far -> 0 when the `DepthConfig` they are built for has `reversed_z` set.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    /// Rotation from camera space to world space.
+    pub rotation: Quaternion<f32>,
+    pub projection: Projection,
+    pub aspect: f32,
+}
+
+impl Camera {
+    pub fn perspective(fovy: impl Into<Rad<f32>>, aspect: f32, znear: f32, zfar: f32) -> Self {
+        Self::with_projection(Projection::Perspective { fovy: fovy.into(), znear, zfar }, aspect)
+    }
+
+    pub fn orthographic(height: f32, aspect: f32, znear: f32, zfar: f32) -> Self {
+        Self::with_projection(Projection::Orthographic { height, znear, zfar }, aspect)
+    }
+
+    pub fn with_projection(projection: Projection, aspect: f32) -> Self {
+        Self {
+            position: Point3::origin(),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            projection,
+            aspect,
+        }
+    }
+
+    /// Builds a camera from a glTF camera and the world transform of the node holding it.
+    /// `aspect` is only used when the file does not specify one.
+    pub fn from_model_camera(camera: &ModelCamera, transform: Matrix4<f32>, aspect: f32) -> Self {
+        let projection = match camera.projection {
+            ModelProjection::Perspective { yfov, znear, zfar, .. } => {
+                Projection::Perspective { fovy: Rad(yfov), znear, zfar: zfar.unwrap_or(1000.0) }
+            }
+            ModelProjection::Orthographic { ymag, znear, zfar, .. } => {
+                Projection::Orthographic { height: 2.0 * ymag, znear, zfar }
+            }
+        };
+        let aspect = match camera.projection {
+            ModelProjection::Perspective { aspect_ratio: Some(a), .. } => a,
+            ModelProjection::Orthographic { xmag, ymag, .. } if ymag != 0.0 => xmag / ymag,
+            _ => aspect,
+        };
+        let basis = Matrix3::from_cols(
+            transform.x.truncate().normalize(),
+            transform.y.truncate().normalize(),
+            transform.z.truncate().normalize(),
+        );
+        Self {
+            position: Point3::from_vec(transform.w.truncate()),
+            rotation: Quaternion::from(basis).normalize(),
+            ..Self::with_projection(projection, aspect)
+        }
+    }
+
+    pub fn look_at(&mut self, target: Point3<f32>, up: Vector3<f32>) {
+        let forward = (target - self.position).normalize();
+        let right = forward.cross(up).normalize();
+        let up = right.cross(forward);
+        self.rotation = Quaternion::from(Matrix3::from_cols(right, up, -forward)).normalize();
+    }
+
+    /// Yaw about world +Y, then pitch about the camera's right axis, then roll about its view axis.
+    /// (0, 0, 0) looks down -Z.
+    pub fn set_euler(&mut self, yaw: impl Into<Rad<f32>>, pitch: impl Into<Rad<f32>>, roll: impl Into<Rad<f32>>) {
+        self.rotation = Quaternion::from_angle_y(yaw) * Quaternion::from_angle_x(pitch) * Quaternion::from_angle_z(roll);
+    }
+
+    /// The (yaw, pitch) that `set_euler` would need to reproduce the current view direction.
+    pub fn yaw_pitch(&self) -> (Rad<f32>, Rad<f32>) {
+        let f = self.forward();
+        (Rad((-f.x).atan2(-f.z)), Rad(f.y.clamp(-1.0, 1.0).asin()))
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        self.rotation.rotate_vector(-Vector3::unit_z())
+    }
+
+    pub fn right(&self) -> Vector3<f32> {
+        self.rotation.rotate_vector(Vector3::unit_x())
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        self.rotation.rotate_vector(Vector3::unit_y())
+    }
+
+    /// Keeps the aspect ratio in sync with the surface; call it after `IWgpuInit::resize`.
+    pub fn resize(&mut self, init: &IWgpuInit) {
+        if let Some(aspect) = init.aspect_ratio() {
+            self.aspect = aspect;
+        }
+    }
+
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        Matrix4::from(self.rotation.conjugate()) * Matrix4::from_translation(-self.position.to_vec())
+    }
+
+    pub fn projection_matrix(&self, depth: &DepthConfig) -> Matrix4<f32> {
+        match self.projection {
+            Projection::Perspective { fovy, znear, zfar } => {
+                let f = 1.0 / (fovy.0 * 0.5).tan();
+                let (c, d) = if depth.reversed_z {
+                    (znear / (zfar - znear), znear * zfar / (zfar - znear))
+                } else {
+                    (zfar / (znear - zfar), znear * zfar / (znear - zfar))
+                };
+                #[rustfmt::skip]
+                let m = Matrix4::new(
+                    f / self.aspect, 0.0, 0.0, 0.0,
+                    0.0, f, 0.0, 0.0,
+                    0.0, 0.0, c, -1.0,
+                    0.0, 0.0, d, 0.0,
+                );
+                m
+            }
+            Projection::Orthographic { height, znear, zfar } => {
+                let (c, d) = if depth.reversed_z {
+                    (1.0 / (zfar - znear), zfar / (zfar - znear))
+                } else {
+                    (1.0 / (znear - zfar), znear / (znear - zfar))
+                };
+                #[rustfmt::skip]
+                let m = Matrix4::new(
+                    2.0 / (height * self.aspect), 0.0, 0.0, 0.0,
+                    0.0, 2.0 / height, 0.0, 0.0,
+                    0.0, 0.0, c, 0.0,
+                    0.0, 0.0, d, 1.0,
+                );
+                m
+            }
+        }
+    }
+
+    pub fn view_projection_matrix(&self, depth: &DepthConfig) -> Matrix4<f32> {
+        self.projection_matrix(depth) * self.view_matrix()
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CameraUniform {
+    pub view: [[f32; 4]; 4],
+    pub projection: [[f32; 4]; 4],
+    pub view_projection: [[f32; 4]; 4],
+    pub inverse_view_projection: [[f32; 4]; 4],
+    /// xyz is the camera position in world space, w is unused.
+    pub position: [f32; 4],
+}
+
+impl CameraUniform {
+    pub fn new(camera: &Camera, depth: &DepthConfig) -> Self {
+        let view_projection = camera.view_projection_matrix(depth);
+        Self {
+            view: camera.view_matrix().into(),
+            projection: camera.projection_matrix(depth).into(),
+            view_projection: view_projection.into(),
+            inverse_view_projection: view_projection.invert().unwrap_or(Matrix4::zero()).into(),
+            position: camera.position.to_homogeneous().into(),
+        }
+    }
+}
+
+/// Uniform buffer plus bind group holding a `CameraUniform`, visible to the vertex and fragment
+/// stages at binding 0. The matching WGSL is:
+///
+/// ```wgsl
+/// struct Camera {
+///     view: mat4x4f,
+///     projection: mat4x4f,
+///     view_projection: mat4x4f,
+///     inverse_view_projection: mat4x4f,
+///     position: vec4f,
+/// };
+/// @group(0) @binding(0) var<uniform> camera: Camera;
+/// ```
+pub struct CameraBinding {
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl CameraBinding {
+    pub fn new(init: &IWgpuInit, camera: &Camera) -> Self {
+        let buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Uniform Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(camera, &init.depth)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        Self { buffer, bind_group_layout, bind_group }
+    }
+
+    pub fn update(&self, init: &IWgpuInit, camera: &Camera) {
+        init.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&CameraUniform::new(camera, &init.depth)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Vector4};
+
+    use super::*;
+
+    /// Clip-space depth of the point `distance` in front of the camera.
+    fn depth(camera: &Camera, reversed_z: bool, distance: f32) -> f32 {
+        let config = DepthConfig { reversed_z, ..Default::default() };
+        let clip = camera.projection_matrix(&config) * Vector4::new(0.0, 0.0, -distance, 1.0);
+        clip.z / clip.w
+    }
+
+    fn cameras() -> [Camera; 2] {
+        [Camera::perspective(Deg(60.0), 1.5, 0.1, 100.0), Camera::orthographic(4.0, 1.5, 0.1, 100.0)]
+    }
+
+    #[test]
+    fn near_and_far_map_to_zero_and_one() {
+        for camera in cameras() {
+            assert!(depth(&camera, false, 0.1).abs() < 1e-5, "{:?}", camera.projection);
+            assert!((depth(&camera, false, 100.0) - 1.0).abs() < 1e-5, "{:?}", camera.projection);
+            let middle = depth(&camera, false, 10.0);
+            assert!(middle > 0.0 && middle < 1.0);
+        }
+    }
+
+    #[test]
+    fn reversed_z_maps_near_to_one_and_far_to_zero() {
+        for camera in cameras() {
+            assert!((depth(&camera, true, 0.1) - 1.0).abs() < 1e-5, "{:?}", camera.projection);
+            assert!(depth(&camera, true, 100.0).abs() < 1e-5, "{:?}", camera.projection);
+            assert!(depth(&camera, true, 1.0) > depth(&camera, true, 10.0));
+        }
+    }
+
+    #[test]
+    fn look_at_faces_the_target() {
+        let mut camera = Camera::perspective(Deg(45.0), 1.0, 0.1, 10.0);
+        camera.position = Point3::new(1.0, 2.0, 3.0);
+        camera.look_at(Point3::new(0.0, 0.0, 0.0), Vector3::unit_y());
+        let expected = (Point3::new(0.0, 0.0, 0.0) - camera.position).normalize();
+        assert!((camera.forward() - expected).magnitude() < 1e-5);
+        let view = camera.view_matrix() * camera.position.to_homogeneous();
+        assert!(view.truncate().magnitude() < 1e-5);
+    }
+}
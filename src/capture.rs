@@ -0,0 +1,89 @@
+use anyhow::{bail, Context, Result};
+use image::RgbaImage;
+
+use crate::helpers::IWgpuInit;
+
+/// Reads back the last offscreen frame, see `IWgpuInit::new_headless` and `IWgpuInit::set_offscreen`.
+pub fn read_frame(init: &IWgpuInit) -> Result<RgbaImage> {
+    let texture = init.offscreen.as_ref().context("only offscreen frames can be read back")?;
+    read_texture(init, texture)
+}
+
+/// Copies an 8-bit RGBA or BGRA texture into an image, waiting for the GPU. sRGB textures give
+/// their encoded values, as stored in PNG files. The texture needs `COPY_SRC` usage.
+pub fn read_texture(init: &IWgpuInit, texture: &wgpu::Texture) -> Result<RgbaImage> {
+    let layout = ReadbackLayout::new(texture)?;
+    let buffer = layout.create_buffer(&init.device);
+    let mut encoder = init.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Frame Readback") });
+    layout.copy(&mut encoder, texture, &buffer);
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    let _ = init.device.poll(wgpu::Maintain::Wait);
+    receiver.recv().context("readback was dropped")?.context("failed to map readback buffer")?;
+    let image = layout.read(&buffer);
+    buffer.unmap();
+    Ok(image)
+}
+
+/// How a texture is laid out in a readback buffer, with rows padded to
+/// `COPY_BYTES_PER_ROW_ALIGNMENT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ReadbackLayout {
+    pub width: u32,
+    pub height: u32,
+    padded_row: u32,
+    bgra: bool,
+}
+
+impl ReadbackLayout {
+    pub fn new(texture: &wgpu::Texture) -> Result<Self> {
+        let bgra = match texture.format() {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => bail!("cannot read back {:?} textures", format),
+        };
+        let (width, height) = (texture.width(), texture.height());
+        let padded_row = (4 * width).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        Ok(Self { width, height, padded_row, bgra })
+    }
+
+    pub fn create_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Readback"),
+            size: (self.padded_row * self.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn copy(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, buffer: &wgpu::Buffer) {
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 },
+        );
+    }
+
+    /// The image in a mapped buffer `copy` wrote to.
+    pub fn read(&self, buffer: &wgpu::Buffer) -> RgbaImage {
+        let data = buffer.slice(..).get_mapped_range();
+        let row_bytes = 4 * self.width as usize;
+        let mut pixels: Vec<u8> = data.chunks_exact(self.padded_row as usize).flat_map(|row| row[..row_bytes].to_vec()).collect();
+        if self.bgra {
+            pixels.chunks_exact_mut(4).for_each(|p| p.swap(0, 2));
+        }
+        RgbaImage::from_raw(self.width, self.height, pixels).expect("readback has the row size of the layout")
+    }
+}
@@ -0,0 +1,227 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+
+use crate::helpers::IWgpuInit;
+
+/// Number of entries in a `ColormapTexture`.
+pub const COLORMAP_SIZE: u32 = 256;
+
+/// Colormaps for displaying scalar data, evaluated to sRGB colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colormap {
+    Gray,
+    Viridis,
+    Magma,
+    Inferno,
+    Plasma,
+    Turbo,
+    /// Diverging red-white-blue, low values red.
+    RdBu,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 7] = [
+        Colormap::Gray,
+        Colormap::Viridis,
+        Colormap::Magma,
+        Colormap::Inferno,
+        Colormap::Plasma,
+        Colormap::Turbo,
+        Colormap::RdBu,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Colormap::Gray => "gray",
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Inferno => "inferno",
+            Colormap::Plasma => "plasma",
+            Colormap::Turbo => "turbo",
+            Colormap::RdBu => "rdbu",
+        }
+    }
+
+    /// The following colormap in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&c| c == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// The sRGB color at `t`, clamped to 0..1.
+    ///
+    /// The perceptually uniform maps use the 6th degree polynomial fits of the matplotlib
+    /// tables, turbo uses Google's published polynomial approximation.
+    pub fn eval(self, t: f32) -> [f32; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let rgb = match self {
+            Colormap::Gray => [t; 3],
+            Colormap::Viridis => polynomial(&VIRIDIS, t),
+            Colormap::Magma => polynomial(&MAGMA, t),
+            Colormap::Inferno => polynomial(&INFERNO, t),
+            Colormap::Plasma => polynomial(&PLASMA, t),
+            Colormap::Turbo => turbo(t),
+            Colormap::RdBu => piecewise(&RDBU, t),
+        };
+        rgb.map(|c| c.clamp(0.0, 1.0))
+    }
+
+    /// `size` evenly spaced colors from 0 to 1 as opaque sRGB bytes.
+    pub fn to_rgba8(self, size: u32) -> Vec<[u8; 4]> {
+        let scale = 1.0 / (size.max(2) - 1) as f32;
+        (0..size)
+            .map(|i| {
+                let [r, g, b] = self.eval(i as f32 * scale).map(|c| (c * 255.0).round() as u8);
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Colormap {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name() == lower || (lower == "grey" && *c == Colormap::Gray))
+            .ok_or_else(|| {
+                let names: Vec<_> = Self::ALL.iter().map(|c| c.name()).collect();
+                anyhow!("unknown colormap '{}', expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+/// A `COLORMAP_SIZE` x 1 `Rgba8UnormSrgb` texture holding a colormap, so a filtering sampler
+/// returns linear colors. Texel centers are at `(t * (COLORMAP_SIZE - 1) + 0.5) / COLORMAP_SIZE`.
+pub struct ColormapTexture {
+    pub colormap: Colormap,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl ColormapTexture {
+    pub fn new(init: &IWgpuInit, colormap: Colormap) -> Self {
+        let texture = init.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Colormap"),
+            size: wgpu::Extent3d { width: COLORMAP_SIZE, height: 1, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut map = Self { colormap, texture, view };
+        map.set(init, colormap);
+        map
+    }
+
+    /// Rewrites the texture in place; bind groups using `view` stay valid.
+    pub fn set(&mut self, init: &IWgpuInit, colormap: Colormap) {
+        self.colormap = colormap;
+        let texels = colormap.to_rgba8(COLORMAP_SIZE);
+        init.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * COLORMAP_SIZE),
+                rows_per_image: Some(1),
+            },
+            wgpu::Extent3d { width: COLORMAP_SIZE, height: 1, depth_or_array_layers: 1 },
+        );
+    }
+}
+
+fn polynomial(c: &[[f32; 3]; 7], t: f32) -> [f32; 3] {
+    let mut rgb = [0.0; 3];
+    for (i, channel) in rgb.iter_mut().enumerate() {
+        *channel = c.iter().rev().fold(0.0, |acc, k| acc * t + k[i]);
+    }
+    rgb
+}
+
+fn turbo(t: f32) -> [f32; 3] {
+    let powers = [1.0, t, t * t, t * t * t, t * t * t * t, t * t * t * t * t];
+    TURBO.map(|k| k.iter().zip(powers).map(|(k, p)| k * p).sum())
+}
+
+/// Linear interpolation between evenly spaced 8-bit stops.
+fn piecewise(stops: &[[u8; 3]], t: f32) -> [f32; 3] {
+    let x = t * (stops.len() - 1) as f32;
+    let i = (x.floor() as usize).min(stops.len() - 2);
+    let f = x - i as f32;
+    let (a, b) = (stops[i], stops[i + 1]);
+    [0, 1, 2].map(|c| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * f) / 255.0)
+}
+
+const VIRIDIS: [[f32; 3]; 7] = [
+    [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+    [0.105_093_04, 1.404_613_5, 1.384_590_2],
+    [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+    [-4.634_230_5, -5.799_101, -19.332_441],
+    [6.228_27, 14.179_933, 56.690_55],
+    [4.776_385, -13.745_145, -65.353_033],
+    [-5.435_456, 4.645_852_6, 26.312_435],
+];
+
+const MAGMA: [[f32; 3]; 7] = [
+    [-0.002_136_485, -0.000_749_655_05, -0.005_386_127_7],
+    [0.251_660_54, 0.677_523_24, 2.494_026_6],
+    [8.353_717, -3.577_719_5, 0.314_467_9],
+    [-27.668_733, 14.264_731, -13.649_213],
+    [52.176_14, -27.943_606, 12.944_169],
+    [-50.768_524, 29.046_583, 4.234_153],
+    [18.655_705, -11.489_774, -5.601_961_4],
+];
+
+const INFERNO: [[f32; 3]; 7] = [
+    [0.000_218_940_37, 0.001_651_004_6, -0.019_480_899],
+    [0.106_513_42, 0.563_956_44, 3.932_712_3],
+    [11.602_493, -3.972_854, -15.942_394],
+    [-41.703_995, 17.436_4, 44.354_145],
+    [77.162_94, -33.402_36, -81.807_31],
+    [-71.319_43, 32.626_064, 73.209_52],
+    [25.131_126, -12.242_669, -23.070_325],
+];
+
+const PLASMA: [[f32; 3]; 7] = [
+    [0.058_732_344, 0.023_336_709, 0.543_340_2],
+    [2.176_514_6, 0.238_383_42, 0.753_960_45],
+    [-2.689_460_5, -7.455_851, 3.110_8],
+    [6.130_348, 42.346_19, -28.518_854],
+    [-11.107_436, -82.666_31, 60.139_85],
+    [10.023_066, 71.413_62, -54.072_186],
+    [-3.658_714, -22.931_534, 18.191_908],
+];
+
+/// Per channel coefficients of t^0 .. t^5.
+const TURBO: [[f32; 6]; 3] = [
+    [0.135_721_38, 4.615_392_6, -42.660_324, 132.131_08, -152.942_4, 59.286_38],
+    [0.091_402_61, 2.194_188_4, 4.842_966_6, -14.185_033, 4.277_298_5, 2.829_566],
+    [0.106_673_3, 12.641_946, -60.582_047, 110.362_77, -89.903_11, 27.348_25],
+];
+
+/// ColorBrewer RdBu, 11 classes.
+const RDBU: [[u8; 3]; 11] = [
+    [0x67, 0x00, 0x1f],
+    [0xb2, 0x18, 0x2b],
+    [0xd6, 0x60, 0x4d],
+    [0xf4, 0xa5, 0x82],
+    [0xfd, 0xdb, 0xc7],
+    [0xf7, 0xf7, 0xf7],
+    [0xd1, 0xe5, 0xf0],
+    [0x92, 0xc5, 0xde],
+    [0x43, 0x93, 0xc3],
+    [0x21, 0x66, 0xac],
+    [0x05, 0x30, 0x61],
+];
@@ -0,0 +1,253 @@
+use std::fmt;
+
+use anyhow::bail;
+
+use crate::colormap::Colormap;
+
+/// Standard deviation and radius of the Gaussian window SSIM is averaged over.
+const SSIM_SIGMA: f32 = 1.5;
+const SSIM_RADIUS: i32 = 5;
+
+/// How two pixels are compared in difference views and maps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DifferenceMetric {
+    /// Largest difference of any channel, alpha included, in 0..1.
+    Absolute,
+    /// CIE76 color difference (ΔE) of the sRGB colors; about 2.3 is just noticeable.
+    Perceptual,
+}
+
+impl DifferenceMetric {
+    pub fn name(self) -> &'static str {
+        match self {
+            DifferenceMetric::Absolute => "absolute",
+            DifferenceMetric::Perceptual => "perceptual",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            DifferenceMetric::Absolute => DifferenceMetric::Perceptual,
+            DifferenceMetric::Perceptual => DifferenceMetric::Absolute,
+        }
+    }
+
+    /// The difference the top of a heatmap stands for unless the caller picks one.
+    pub fn default_range(self) -> f32 {
+        match self {
+            DifferenceMetric::Absolute => 0.1,
+            DifferenceMetric::Perceptual => 10.0,
+        }
+    }
+
+    /// Same as `difference` in `imdiff.wgsl`.
+    pub fn eval(self, a: [f32; 4], b: [f32; 4]) -> f32 {
+        match self {
+            DifferenceMetric::Absolute => (0..4).map(|c| (a[c] - b[c]).abs()).fold(0.0, f32::max),
+            DifferenceMetric::Perceptual => {
+                let (la, lb) = (srgb_to_lab([a[0], a[1], a[2]]), srgb_to_lab([b[0], b[1], b[2]]));
+                (0..3).map(|c| (la[c] - lb[c]).powi(2)).sum::<f32>().sqrt()
+            }
+        }
+    }
+}
+
+/// Summary of how far two images of the same size are apart. Pixels are RGBA with channels in
+/// 0..1, as decoded from image files.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageComparison {
+    pub width: u32,
+    pub height: u32,
+    /// Peak signal-to-noise ratio over all four channels in dB; infinite for identical images.
+    pub psnr: f64,
+    /// Mean structural similarity of the luma, 1 for identical images.
+    pub ssim: f64,
+    /// Largest difference of any channel.
+    pub max_error: f32,
+    /// Pixels with a channel that differs by more than the tolerance.
+    pub differing_pixels: usize,
+}
+
+impl ImageComparison {
+    /// Compares `a` and `b`, counting pixels whose channels differ by more than `tolerance`.
+    pub fn new(a: &[[f32; 4]], b: &[[f32; 4]], width: u32, height: u32, tolerance: f32) -> anyhow::Result<Self> {
+        let count = (width * height) as usize;
+        if a.len() != count || b.len() != count {
+            bail!("expected {}x{} pixels, got {} and {}", width, height, a.len(), b.len());
+        }
+        let (mut squared_error, mut max_error, mut differing_pixels) = (0.0f64, 0.0f32, 0);
+        for (pa, pb) in a.iter().zip(b) {
+            let error = DifferenceMetric::Absolute.eval(*pa, *pb);
+            squared_error += (0..4).map(|c| ((pa[c] - pb[c]) as f64).powi(2)).sum::<f64>();
+            max_error = max_error.max(error);
+            if error > tolerance {
+                differing_pixels += 1;
+            }
+        }
+        let mse = squared_error / (4 * count.max(1)) as f64;
+        let psnr = if mse > 0.0 { -10.0 * mse.log10() } else { f64::INFINITY };
+        Ok(Self { width, height, psnr, ssim: ssim(a, b, width, height), max_error, differing_pixels })
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+impl fmt::Display for ImageComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "size:             {}x{}", self.width, self.height)?;
+        writeln!(f, "PSNR:             {:.2} dB", self.psnr)?;
+        writeln!(f, "SSIM:             {:.6}", self.ssim)?;
+        writeln!(f, "max error:        {:.6} ({:.0}/255)", self.max_error, self.max_error * 255.0)?;
+        write!(
+            f,
+            "differing pixels: {} ({:.4}%)",
+            self.differing_pixels,
+            100.0 * self.differing_pixels as f64 / self.pixel_count().max(1) as f64
+        )
+    }
+}
+
+/// 8-bit RGBA bytes, e.g. from `image::RgbaImage`, as pixels in 0..1.
+pub fn rgba8_pixels(bytes: &[u8]) -> Vec<[f32; 4]> {
+    bytes.chunks_exact(4).map(|p| [0, 1, 2, 3].map(|c| p[c] as f32 / 255.0)).collect()
+}
+
+/// An RGBA8 heatmap of the per-pixel difference, with `range` mapped to the top of `colormap`.
+pub fn difference_map(a: &[[f32; 4]], b: &[[f32; 4]], metric: DifferenceMetric, range: f32, colormap: Colormap) -> Vec<u8> {
+    a.iter()
+        .zip(b)
+        .flat_map(|(pa, pb)| {
+            let t = (metric.eval(*pa, *pb) / range.max(1e-20)).clamp(0.0, 1.0);
+            let [r, g, b] = colormap.eval(t).map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+            [r, g, b, 255]
+        })
+        .collect()
+}
+
+/// CIELAB (D65, L in 0..100) of an sRGB-encoded color, as `rgb_to_lab` in `filter/color.wgsl`.
+pub fn srgb_to_lab(c: [f32; 3]) -> [f32; 3] {
+    const WHITE: [f32; 3] = [0.95047, 1.0, 1.08883];
+    let l = c.map(|v| v.max(0.0)).map(|v| if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) });
+    let xyz = [
+        0.4124 * l[0] + 0.3576 * l[1] + 0.1805 * l[2],
+        0.2126 * l[0] + 0.7152 * l[1] + 0.0722 * l[2],
+        0.0193 * l[0] + 0.1192 * l[1] + 0.9505 * l[2],
+    ];
+    let f = [0, 1, 2].map(|i| {
+        let t = xyz[i] / WHITE[i];
+        if t > 0.008856452 { t.cbrt() } else { t / (3.0 * 0.04280618) + 4.0 / 29.0 }
+    });
+    [116.0 * f[1] - 16.0, 500.0 * (f[0] - f[1]), 200.0 * (f[1] - f[2])]
+}
+
+/// Mean SSIM (Wang et al. 2004) of the Rec. 601 luma with a Gaussian window and edges clamped.
+fn ssim(a: &[[f32; 4]], b: &[[f32; 4]], width: u32, height: u32) -> f64 {
+    const C1: f32 = 0.01 * 0.01;
+    const C2: f32 = 0.03 * 0.03;
+    let luma = |p: &[f32; 4]| 0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2];
+    let x: Vec<f32> = a.iter().map(luma).collect();
+    let y: Vec<f32> = b.iter().map(luma).collect();
+    let product = |p: &[f32], q: &[f32]| -> Vec<f32> { p.iter().zip(q).map(|(u, v)| u * v).collect() };
+    let (w, h) = (width as usize, height as usize);
+    let mu_x = gaussian_blur(&x, w, h);
+    let mu_y = gaussian_blur(&y, w, h);
+    let xx = gaussian_blur(&product(&x, &x), w, h);
+    let yy = gaussian_blur(&product(&y, &y), w, h);
+    let xy = gaussian_blur(&product(&x, &y), w, h);
+    let total: f64 = (0..w * h)
+        .map(|i| {
+            let (mx, my) = (mu_x[i], mu_y[i]);
+            let (vx, vy, cov) = (xx[i] - mx * mx, yy[i] - my * my, xy[i] - mx * my);
+            let s = ((2.0 * mx * my + C1) * (2.0 * cov + C2)) / ((mx * mx + my * my + C1) * (vx + vy + C2));
+            s as f64
+        })
+        .sum();
+    total / (w * h).max(1) as f64
+}
+
+fn gaussian_blur(values: &[f32], width: usize, height: usize) -> Vec<f32> {
+    let weights: Vec<f32> = (-SSIM_RADIUS..=SSIM_RADIUS).map(|i| (-(i * i) as f32 / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp()).collect();
+    let sum: f32 = weights.iter().sum();
+    let weights: Vec<f32> = weights.iter().map(|w| w / sum).collect();
+    let pass = |source: &[f32], step: (usize, usize)| -> Vec<f32> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as i32, (i / width) as i32);
+                weights
+                    .iter()
+                    .zip(-SSIM_RADIUS..)
+                    .map(|(w, d)| {
+                        let sx = (x + d * step.0 as i32).clamp(0, width as i32 - 1) as usize;
+                        let sy = (y + d * step.1 as i32).clamp(0, height as i32 - 1) as usize;
+                        w * source[sy * width + sx]
+                    })
+                    .sum()
+            })
+            .collect()
+    };
+    pass(&pass(values, (1, 0)), (0, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An 8x8 image with a gradient, so SSIM has structure to compare.
+    fn gradient(offset: f32) -> Vec<[f32; 4]> {
+        (0..64).map(|i| [0.2 + 0.005 * i as f32 + offset; 4]).collect()
+    }
+
+    #[test]
+    fn identical_images() {
+        let a = gradient(0.0);
+        let comparison = ImageComparison::new(&a, &a, 8, 8, 0.0).unwrap();
+        assert_eq!(comparison.psnr, f64::INFINITY);
+        assert!((comparison.ssim - 1.0).abs() < 1e-9, "SSIM {}", comparison.ssim);
+        assert_eq!(comparison.max_error, 0.0);
+        assert_eq!(comparison.differing_pixels, 0);
+    }
+
+    #[test]
+    fn constant_offset() {
+        // An error of 0.1 in every channel is an MSE of 0.01, 20 dB.
+        let (a, b) = (gradient(0.0), gradient(0.1));
+        let comparison = ImageComparison::new(&a, &b, 8, 8, 0.0).unwrap();
+        assert!((comparison.psnr - 20.0).abs() < 1e-3, "PSNR {}", comparison.psnr);
+        assert!((comparison.max_error - 0.1).abs() < 1e-5);
+        assert_eq!(comparison.differing_pixels, 64);
+        // A brightness shift keeps the structure but still lowers SSIM.
+        assert!(comparison.ssim < 1.0 && comparison.ssim > 0.5, "SSIM {}", comparison.ssim);
+    }
+
+    #[test]
+    fn tolerance() {
+        let a = vec![[0.5; 4]; 4];
+        let mut b = a.clone();
+        b[1][0] = 0.55;
+        b[2][3] = 0.7;
+        let differing = |tolerance| ImageComparison::new(&a, &b, 2, 2, tolerance).unwrap().differing_pixels;
+        assert_eq!(differing(0.0), 2);
+        assert_eq!(differing(0.1), 1);
+        assert_eq!(differing(0.25), 0);
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let a = vec![[0.0; 4]; 6];
+        assert!(ImageComparison::new(&a, &a, 2, 2, 0.0).is_err());
+        assert!(ImageComparison::new(&a, &a[..4], 2, 2, 0.0).is_err());
+    }
+
+    #[test]
+    fn lab() {
+        let white = srgb_to_lab([1.0; 3]);
+        // Not exactly neutral, the sRGB matrix is rounded to four digits.
+        assert!((white[0] - 100.0).abs() < 0.01 && white[1].abs() < 0.02 && white[2].abs() < 0.02, "{:?}", white);
+        assert_eq!(srgb_to_lab([0.0; 3]), [0.0; 3]);
+        // Pure sRGB red is about (53.2, 80.1, 67.2).
+        let red = srgb_to_lab([1.0, 0.0, 0.0]);
+        assert!((red[0] - 53.24).abs() < 0.1 && (red[1] - 80.09).abs() < 0.2 && (red[2] - 67.2).abs() < 0.2, "{:?}", red);
+    }
+}
@@ -0,0 +1,496 @@
+use std::f32::consts::FRAC_PI_2;
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Point3, Quaternion, Rad, Rotation, Vector2, Vector3};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+use crate::camera::Camera;
+
+/// Something that turns winit input into camera motion.
+///
+/// Feed it every `WindowEvent` from `State::input` and every `DeviceEvent` from the event loop,
+/// then call `update` once per frame with the frame delta. Mouse motion and wheel notches are
+/// accumulated between updates and applied in full, so only key-driven motion scales with it.
+pub trait CameraController {
+    /// Returns `true` if the event was consumed.
+    fn process_window_event(&mut self, event: &WindowEvent) -> bool;
+
+    fn process_device_event(&mut self, _event: &DeviceEvent) -> bool {
+        false
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: Duration);
+}
+
+/// Pixels of `PixelDelta` scrolling that count as one wheel notch.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+fn scroll_lines(delta: &MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, y) => *y,
+        MouseScrollDelta::PixelDelta(p) => p.y as f32 / PIXELS_PER_LINE,
+    }
+}
+
+/// Zooms by `1 - sensitivity` per notch, so every notch covers the same fraction of the distance
+/// whether near or far, and opposite notches cancel out.
+fn zoom(distance: f32, scroll: f32, sensitivity: f32, min: f32, max: f32) -> f32 {
+    (distance * (1.0 - sensitivity).powf(scroll)).clamp(min, max)
+}
+
+fn pressed_key(event: &KeyEvent) -> Option<(KeyCode, bool)> {
+    match event.physical_key {
+        PhysicalKey::Code(code) => Some((code, event.state == ElementState::Pressed)),
+        PhysicalKey::Unidentified(_) => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitBindings {
+    pub rotate: MouseButton,
+    pub pan: MouseButton,
+}
+
+impl Default for OrbitBindings {
+    fn default() -> Self {
+        Self { rotate: MouseButton::Left, pan: MouseButton::Right }
+    }
+}
+
+/// Orbits around `target`: drag to rotate, drag with the pan button to move the target,
+/// wheel to zoom.
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub bindings: OrbitBindings,
+    /// Radians per unit of mouse motion.
+    pub rotate_sensitivity: f32,
+    /// Fraction of the distance moved per unit of mouse motion.
+    pub pan_sensitivity: f32,
+    /// Fraction of the distance zoomed per wheel notch.
+    pub zoom_sensitivity: f32,
+    rotating: bool,
+    panning: bool,
+    rotate_delta: Vector2<f32>,
+    pan_delta: Vector2<f32>,
+    scroll: f32,
+}
+
+impl OrbitController {
+    pub fn new(target: Point3<f32>, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: Rad(0.0),
+            pitch: Rad(0.0),
+            min_distance: 0.05,
+            max_distance: 1000.0,
+            bindings: OrbitBindings::default(),
+            rotate_sensitivity: 0.005,
+            pan_sensitivity: 0.002,
+            zoom_sensitivity: 0.1,
+            rotating: false,
+            panning: false,
+            rotate_delta: Vector2::new(0.0, 0.0),
+            pan_delta: Vector2::new(0.0, 0.0),
+            scroll: 0.0,
+        }
+    }
+
+    /// Starts orbiting from wherever `camera` currently is, looking at `target`.
+    pub fn from_camera(camera: &Camera, target: Point3<f32>) -> Self {
+        let offset = camera.position - target;
+        let distance = offset.magnitude();
+        let mut controller = Self::new(target, distance);
+        if distance > 0.0 {
+            controller.yaw = Rad(offset.x.atan2(offset.z));
+            controller.pitch = Rad((offset.y / distance).clamp(-1.0, 1.0).asin());
+        }
+        controller
+    }
+}
+
+impl CameraController for OrbitController {
+    fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                if *button == self.bindings.rotate {
+                    self.rotating = pressed;
+                } else if *button == self.bindings.pan {
+                    self.panning = pressed;
+                } else {
+                    return false;
+                }
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += scroll_lines(delta);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn process_device_event(&mut self, event: &DeviceEvent) -> bool {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            let delta = Vector2::new(delta.0 as f32, delta.1 as f32);
+            if self.rotating {
+                self.rotate_delta += delta;
+                return true;
+            }
+            if self.panning {
+                self.pan_delta += delta;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn update(&mut self, camera: &mut Camera, _dt: Duration) {
+        self.yaw -= Rad(self.rotate_delta.x * self.rotate_sensitivity);
+        let limit = FRAC_PI_2 - 0.001;
+        self.pitch = Rad((self.pitch.0 + self.rotate_delta.y * self.rotate_sensitivity).clamp(-limit, limit));
+        self.distance = zoom(self.distance, self.scroll, self.zoom_sensitivity, self.min_distance, self.max_distance);
+
+        let pan = self.pan_delta * self.pan_sensitivity * self.distance;
+        self.target += camera.up() * pan.y - camera.right() * pan.x;
+
+        let (sy, cy) = self.yaw.0.sin_cos();
+        let (sp, cp) = self.pitch.0.sin_cos();
+        camera.position = self.target + Vector3::new(sy * cp, sp, cy * cp) * self.distance;
+        camera.look_at(self.target, Vector3::unit_y());
+
+        self.rotate_delta = Vector2::new(0.0, 0.0);
+        self.pan_delta = Vector2::new(0.0, 0.0);
+        self.scroll = 0.0;
+    }
+}
+
+/// Shoemake arcball: dragging rolls a virtual trackball centered on the window, with no
+/// fixed up axis. Needs `CursorMoved` and `Resized` window events.
+pub struct ArcballController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    /// Rotation from camera space to world space.
+    pub orientation: Quaternion<f32>,
+    pub button: MouseButton,
+    /// Fraction of the distance zoomed per wheel notch.
+    pub zoom_sensitivity: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    size: PhysicalSize<u32>,
+    cursor: Option<PhysicalPosition<f64>>,
+    dragging: bool,
+    pending: Quaternion<f32>,
+    scroll: f32,
+}
+
+impl ArcballController {
+    pub fn new(target: Point3<f32>, distance: f32, size: PhysicalSize<u32>) -> Self {
+        let identity = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+        Self {
+            target,
+            distance,
+            orientation: identity,
+            button: MouseButton::Left,
+            zoom_sensitivity: 0.1,
+            min_distance: 0.05,
+            max_distance: 1000.0,
+            size,
+            cursor: None,
+            dragging: false,
+            pending: identity,
+            scroll: 0.0,
+        }
+    }
+
+    pub fn from_camera(camera: &Camera, target: Point3<f32>, size: PhysicalSize<u32>) -> Self {
+        let mut controller = Self::new(target, (camera.position - target).magnitude(), size);
+        controller.orientation = camera.rotation;
+        controller
+    }
+
+    /// Maps a cursor position onto the unit sphere (or the hyperbolic sheet outside it).
+    fn project(&self, p: PhysicalPosition<f64>) -> Vector3<f32> {
+        let radius = 0.5 * self.size.width.min(self.size.height).max(1) as f32;
+        let x = (p.x as f32 - 0.5 * self.size.width as f32) / radius;
+        let y = (0.5 * self.size.height as f32 - p.y as f32) / radius;
+        let d2 = x * x + y * y;
+        if d2 <= 0.5 {
+            Vector3::new(x, y, (1.0 - d2).sqrt())
+        } else {
+            Vector3::new(x, y, 0.5 / d2.sqrt()).normalize()
+        }
+    }
+
+    fn move_cursor(&mut self, position: PhysicalPosition<f64>) {
+        if let (true, Some(last)) = (self.dragging, self.cursor) {
+            let (a, b) = (self.project(last), self.project(position));
+            // The ball turns from a to b, so the camera has to turn the other way.
+            self.pending = self.pending * Quaternion::from_arc(b, a, None);
+        }
+        self.cursor = Some(position);
+    }
+}
+
+impl CameraController for ArcballController {
+    fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Resized(size) => {
+                self.size = *size;
+                false
+            }
+            WindowEvent::MouseInput { state, button, .. } if *button == self.button => {
+                self.dragging = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.move_cursor(*position);
+                self.dragging
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += scroll_lines(delta);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, _dt: Duration) {
+        self.orientation = (self.orientation * self.pending).normalize();
+        self.distance = zoom(self.distance, self.scroll, self.zoom_sensitivity, self.min_distance, self.max_distance);
+        camera.rotation = self.orientation;
+        camera.position = self.target + self.orientation.rotate_vector(Vector3::unit_z()) * self.distance;
+
+        self.pending = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+        self.scroll = 0.0;
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FlyBindings {
+    pub forward: KeyCode,
+    pub backward: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    pub boost: KeyCode,
+    /// Mouse-look only while this button is held; `None` looks whenever the mouse moves,
+    /// which is what you want with a grabbed cursor.
+    pub look: Option<MouseButton>,
+}
+
+impl Default for FlyBindings {
+    fn default() -> Self {
+        Self {
+            forward: KeyCode::KeyW,
+            backward: KeyCode::KeyS,
+            left: KeyCode::KeyA,
+            right: KeyCode::KeyD,
+            up: KeyCode::KeyE,
+            down: KeyCode::KeyQ,
+            boost: KeyCode::ShiftLeft,
+            look: Some(MouseButton::Right),
+        }
+    }
+}
+
+/// WASD + mouse-look free camera. The wheel changes the movement speed.
+pub struct FlyController {
+    pub bindings: FlyBindings,
+    /// World units per second.
+    pub speed: f32,
+    pub boost_factor: f32,
+    /// Radians per unit of mouse motion.
+    pub look_sensitivity: f32,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    held: [bool; 7],
+    looking: bool,
+    look_delta: Vector2<f32>,
+    scroll: f32,
+}
+
+impl FlyController {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            bindings: FlyBindings::default(),
+            speed,
+            boost_factor: 4.0,
+            look_sensitivity: 0.003,
+            yaw: Rad(0.0),
+            pitch: Rad(0.0),
+            held: [false; 7],
+            looking: false,
+            look_delta: Vector2::new(0.0, 0.0),
+            scroll: 0.0,
+        }
+    }
+
+    pub fn from_camera(camera: &Camera, speed: f32) -> Self {
+        let mut controller = Self::new(speed);
+        (controller.yaw, controller.pitch) = camera.yaw_pitch();
+        controller
+    }
+
+    fn keys(&self) -> [KeyCode; 7] {
+        let b = &self.bindings;
+        [b.forward, b.backward, b.left, b.right, b.up, b.down, b.boost]
+    }
+}
+
+impl CameraController for FlyController {
+    fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } => {
+                let Some((code, pressed)) = pressed_key(event) else {
+                    return false;
+                };
+                match self.keys().iter().position(|&k| k == code) {
+                    Some(i) => {
+                        self.held[i] = pressed;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } if Some(*button) == self.bindings.look => {
+                self.looking = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += scroll_lines(delta);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn process_device_event(&mut self, event: &DeviceEvent) -> bool {
+        match event {
+            DeviceEvent::MouseMotion { delta } if self.looking || self.bindings.look.is_none() => {
+                self.look_delta += Vector2::new(delta.0 as f32, delta.1 as f32);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, camera: &mut Camera, dt: Duration) {
+        self.yaw -= Rad(self.look_delta.x * self.look_sensitivity);
+        let limit = FRAC_PI_2 - 0.001;
+        self.pitch = Rad((self.pitch.0 - self.look_delta.y * self.look_sensitivity).clamp(-limit, limit));
+        camera.set_euler(self.yaw, self.pitch, Rad(0.0));
+
+        self.speed = (self.speed * 1.1f32.powf(self.scroll)).max(1e-3);
+
+        let axis = |pos: usize, neg: usize| (self.held[pos] as i32 - self.held[neg] as i32) as f32;
+        let forward = camera.forward();
+        let right = camera.right();
+        let direction = forward * axis(0, 1) + right * axis(3, 2) + Vector3::unit_y() * axis(4, 5);
+        if direction.magnitude2() > 0.0 {
+            let boost = if self.held[6] { self.boost_factor } else { 1.0 };
+            camera.position += direction.normalize() * self.speed * boost * dt.as_secs_f32();
+        }
+
+        self.look_delta = Vector2::new(0.0, 0.0);
+        self.scroll = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Deg;
+
+    use super::*;
+
+    const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.001;
+
+    fn camera() -> Camera {
+        Camera::perspective(Deg(45.0), 1.0, 0.1, 100.0)
+    }
+
+    #[test]
+    fn orbit_pitch_is_clamped() {
+        let mut camera = camera();
+        let mut orbit = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 5.0);
+        for dy in [1e6, -1e6] {
+            orbit.rotate_delta = Vector2::new(0.0, dy);
+            orbit.update(&mut camera, Duration::ZERO);
+            assert!(orbit.pitch.0.abs() <= PITCH_LIMIT);
+            assert!((orbit.pitch.0.abs() - PITCH_LIMIT).abs() < 1e-6);
+            assert!(camera.forward().x.is_finite());
+            assert!(((camera.position - orbit.target).magnitude() - 5.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn fly_pitch_is_clamped() {
+        let mut camera = camera();
+        let mut fly = FlyController::new(1.0);
+        for dy in [1e6, -1e6] {
+            fly.look_delta = Vector2::new(0.0, dy);
+            fly.update(&mut camera, Duration::ZERO);
+            assert!(fly.pitch.0.abs() <= PITCH_LIMIT);
+            assert!(camera.forward().y.abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn orbit_zoom_is_exponential_and_bounded() {
+        let mut camera = camera();
+        let mut orbit = OrbitController::new(Point3::new(0.0, 0.0, 0.0), 10.0);
+        orbit.scroll = 1.0;
+        orbit.update(&mut camera, Duration::ZERO);
+        assert!((orbit.distance - 9.0).abs() < 1e-4);
+        orbit.scroll = 3.0;
+        orbit.update(&mut camera, Duration::ZERO);
+        orbit.scroll = -3.0;
+        orbit.update(&mut camera, Duration::ZERO);
+        assert!((orbit.distance - 9.0).abs() < 1e-4);
+
+        orbit.scroll = 1e4;
+        orbit.update(&mut camera, Duration::ZERO);
+        assert_eq!(orbit.distance, orbit.min_distance);
+        orbit.scroll = -1e4;
+        orbit.update(&mut camera, Duration::ZERO);
+        assert_eq!(orbit.distance, orbit.max_distance);
+    }
+
+    #[test]
+    fn arcball_zoom_is_bounded() {
+        let mut camera = camera();
+        let mut arcball = ArcballController::new(Point3::new(0.0, 0.0, 0.0), 10.0, PhysicalSize::new(800, 600));
+        arcball.scroll = 1e4;
+        arcball.update(&mut camera, Duration::ZERO);
+        assert_eq!(arcball.distance, arcball.min_distance);
+        arcball.scroll = -1e4;
+        arcball.update(&mut camera, Duration::ZERO);
+        assert_eq!(arcball.distance, arcball.max_distance);
+    }
+
+    #[test]
+    fn arcball_keeps_a_unit_quaternion() {
+        let mut camera = camera();
+        let mut arcball = ArcballController::new(Point3::new(0.0, 0.0, 0.0), 5.0, PhysicalSize::new(800, 600));
+        arcball.dragging = true;
+        for i in 0..200 {
+            // Circles around the window, inside and outside the ball.
+            let angle = i as f64 * 0.37;
+            let radius = 50.0 + (i % 7) as f64 * 60.0;
+            arcball.move_cursor(PhysicalPosition::new(400.0 + radius * angle.cos(), 300.0 + radius * angle.sin()));
+            arcball.update(&mut camera, Duration::ZERO);
+            assert!((arcball.orientation.magnitude() - 1.0).abs() < 1e-5);
+            assert!(((camera.position - arcball.target).magnitude() - 5.0).abs() < 1e-3);
+        }
+        assert!(arcball.orientation.s < 1.0 - 1e-3, "dragging did not rotate the camera");
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{bail, Result};
+
+use crate::info::format_name;
+
+/// Formats a `DepthConfig` can use.
+pub const DEPTH_FORMATS: [wgpu::TextureFormat; 5] = [
+    wgpu::TextureFormat::Depth16Unorm,
+    wgpu::TextureFormat::Depth24Plus,
+    wgpu::TextureFormat::Depth24PlusStencil8,
+    wgpu::TextureFormat::Depth32Float,
+    wgpu::TextureFormat::Depth32FloatStencil8,
+];
+
+/// The depth buffer of the frame attachments, shared by `create_depth_view`, `IRenderPipeline`
+/// and `RenderTarget` through `IWgpuInit::depth`.
+///
+/// With `reversed_z`, near maps to 1 and far to 0, which spreads float precision evenly over the
+/// view range: the depth test becomes `GreaterEqual` and attachments clear to 0. Camera matrices
+/// take the config to match, see `Camera::projection_matrix`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DepthConfig {
+    pub format: wgpu::TextureFormat,
+    pub reversed_z: bool,
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        Self { format: wgpu::TextureFormat::Depth24Plus, reversed_z: false }
+    }
+}
+
+impl DepthConfig {
+    /// A format of `DEPTH_FORMATS` by name, ignoring case, e.g. `depth32float`.
+    pub fn parse_format(name: &str) -> Result<wgpu::TextureFormat> {
+        match DEPTH_FORMATS.into_iter().find(|&format| format_name(format).eq_ignore_ascii_case(name)) {
+            Some(format) => Ok(format),
+            None => {
+                let names: Vec<String> = DEPTH_FORMATS.into_iter().map(format_name).collect();
+                bail!("unknown depth format {}, expected one of {}", name, names.join(", "))
+            }
+        }
+    }
+
+    /// Device features the format needs.
+    pub fn required_features(&self) -> wgpu::Features {
+        self.format.required_features()
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.format.has_stencil_aspect()
+    }
+
+    /// Depth test that keeps the nearest fragments.
+    pub fn compare(&self) -> wgpu::CompareFunction {
+        if self.reversed_z {
+            wgpu::CompareFunction::GreaterEqual
+        } else {
+            wgpu::CompareFunction::LessEqual
+        }
+    }
+
+    /// The farthest depth, which attachments are cleared to.
+    pub fn clear_value(&self) -> f32 {
+        if self.reversed_z {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
+    /// Clears depth to `clear_value` and stencil, if any, to 0.
+    pub fn attachment<'a>(&self, view: &'a wgpu::TextureView) -> wgpu::RenderPassDepthStencilAttachment<'a> {
+        self.attachment_with_ops(view, wgpu::LoadOp::Clear(self.clear_value()), wgpu::LoadOp::Clear(0))
+    }
+
+    /// Attachment for a pass that clears or keeps depth and stencil; both are stored. `stencil`
+    /// is ignored for formats without a stencil aspect.
+    pub fn attachment_with_ops<'a>(&self, view: &'a wgpu::TextureView, depth: wgpu::LoadOp<f32>,
+    stencil: wgpu::LoadOp<u32>) -> wgpu::RenderPassDepthStencilAttachment<'a> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view,
+            depth_ops: Some(wgpu::Operations { load: depth, store: wgpu::StoreOp::Store }),
+            stencil_ops: self.has_stencil().then_some(wgpu::Operations { load: stencil, store: wgpu::StoreOp::Store }),
+        }
+    }
+}
+
+/// Stencil state comparing against the pass's reference value with `compare` and replacing the
+/// stored value on `pass_op`, for both faces. E.g. `Always`/`Replace` marks pixels and
+/// `Equal`/`Keep` draws only where they are marked.
+pub fn stencil_state(compare: wgpu::CompareFunction, pass_op: wgpu::StencilOperation) -> wgpu::StencilState {
+    let face = wgpu::StencilFaceState {
+        compare,
+        fail_op: wgpu::StencilOperation::Keep,
+        depth_fail_op: wgpu::StencilOperation::Keep,
+        pass_op,
+    };
+    wgpu::StencilState { front: face, back: face, read_mask: 0xff, write_mask: 0xff }
+}
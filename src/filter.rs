@@ -0,0 +1,435 @@
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Error, Result};
+use bytemuck::{Pod, Zeroable};
+use image::DynamicImage;
+use wgpu::util::DeviceExt;
+
+use crate::helpers::IWgpuInit;
+
+/// Bindings of every filter stage, prepended to each `src/shaders/filter` shader.
+const FILTER_COMMON_WGSL: &str = include_str!("shaders/filter/common.wgsl");
+
+/// Format of every image a filter reads or writes.
+pub const FILTER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Passes that grow strong Canny edges into connected weak ones; each reaches one pixel further.
+const HYSTERESIS_PASSES: usize = 8;
+
+/// An `Rgba32Float` image that filters read as a texture and write as a storage texture.
+pub struct FilterImage {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FilterImage {
+    pub fn new(init: &IWgpuInit, width: u32, height: u32) -> Self {
+        let texture = init.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Filter Image"),
+            size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FILTER_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { texture, view, width: width.max(1), height: height.max(1) }
+    }
+
+    /// Uploads `width * height` RGBA pixels, rows from top to bottom; fails for empty images or
+    /// too few pixels.
+    pub fn from_rgba(init: &IWgpuInit, width: u32, height: u32, pixels: &[[f32; 4]]) -> Result<Self> {
+        ensure!(width > 0 && height > 0, "cannot filter an empty {}x{} image", width, height);
+        ensure!(
+            pixels.len() >= width as usize * height as usize,
+            "{} pixels for a {}x{} image", pixels.len(), width, height
+        );
+        let image = Self::new(init, width, height);
+        init.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &image.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(pixels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(16 * width),
+                rows_per_image: Some(height),
+            },
+            image.texture.size(),
+        );
+        Ok(image)
+    }
+
+    /// The image's values normalized to 0..1, still sRGB-encoded.
+    pub fn from_image(init: &IWgpuInit, image: &DynamicImage) -> Result<Self> {
+        let rgba = image.to_rgba16();
+        let pixels: Vec<[f32; 4]> = rgba.pixels().map(|p| p.0.map(|v| v as f32 / 65535.0)).collect();
+        Self::from_rgba(init, rgba.width(), rgba.height(), &pixels)
+    }
+
+    /// Copies the image back to the CPU, blocking until the GPU is done.
+    pub fn read(&self, init: &IWgpuInit) -> Result<Vec<[f32; 4]>> {
+        let row_bytes = 16 * self.width;
+        let padded = row_bytes.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Filter Readback"),
+            size: (padded * self.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = init.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Filter Readback") });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            self.texture.size(),
+        );
+        init.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        let _ = init.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().context("readback was dropped")?.context("failed to map readback buffer")?;
+        let data = slice.get_mapped_range();
+        let pixels = data
+            .chunks_exact(padded as usize)
+            .flat_map(|row| bytemuck::cast_slice::<u8, [f32; 4]>(&row[..row_bytes as usize]).to_vec())
+            .collect();
+        drop(data);
+        buffer.unmap();
+        Ok(pixels)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorConversion {
+    RgbToHsv,
+    HsvToRgb,
+    /// BT.601 full range with chroma centered on 0.5.
+    RgbToYCbCr,
+    YCbCrToRgb,
+    /// CIELAB under D65 with L in 0..100.
+    RgbToLab,
+    LabToRgb,
+}
+
+/// One step of a `FilterChain`. Inputs are taken as sRGB-encoded values where it matters
+/// (color conversions, the luminance used for edges).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Filter {
+    GaussianBlur { sigma: f32 },
+    BoxBlur { radius: u32 },
+    /// Gradient magnitude of the luminance, as gray.
+    Sobel,
+    /// Binary edges; gradient magnitudes between `low` and `high` count when connected to
+    /// ones above `high`.
+    Canny { sigma: f32, low: f32, high: f32 },
+    UnsharpMask { sigma: f32, amount: f32 },
+    /// Radius 1 (3x3) or 2 (5x5).
+    Median { radius: u32 },
+    Erode { radius: u32 },
+    Dilate { radius: u32 },
+    Resize { width: u32, height: u32 },
+    Convert(ColorConversion),
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    /// Parses `name[:arg[:arg]]`, e.g. `gaussian:2`, `canny:1.4:0.1:0.3`, `resize:640x480`, `rgb2lab`.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or_default();
+        let args: Vec<&str> = parts.collect();
+        let arg = |i: usize, default: f32| -> Result<f32> {
+            args.get(i).map_or(Ok(default), |a| a.parse().with_context(|| format!("bad argument '{}' in filter '{}'", a, s)))
+        };
+        Ok(match name {
+            "gaussian" => Filter::GaussianBlur { sigma: arg(0, 2.0)? },
+            "box" => Filter::BoxBlur { radius: arg(0, 2.0)? as u32 },
+            "sobel" => Filter::Sobel,
+            "canny" => Filter::Canny { sigma: arg(0, 1.4)?, low: arg(1, 0.1)?, high: arg(2, 0.3)? },
+            "unsharp" => Filter::UnsharpMask { sigma: arg(0, 2.0)?, amount: arg(1, 1.0)? },
+            "median" => Filter::Median { radius: arg(0, 1.0)? as u32 },
+            "erode" => Filter::Erode { radius: arg(0, 1.0)? as u32 },
+            "dilate" => Filter::Dilate { radius: arg(0, 1.0)? as u32 },
+            "resize" => {
+                let size = args.first().context("resize needs WIDTHxHEIGHT")?;
+                let (w, h) = size.split_once('x').with_context(|| format!("bad size '{}'", size))?;
+                let (width, height) = (w.parse()?, h.parse()?);
+                ensure!(width > 0 && height > 0, "resize to an empty size '{}'", size);
+                Filter::Resize { width, height }
+            }
+            "rgb2hsv" => Filter::Convert(ColorConversion::RgbToHsv),
+            "hsv2rgb" => Filter::Convert(ColorConversion::HsvToRgb),
+            "rgb2ycbcr" => Filter::Convert(ColorConversion::RgbToYCbCr),
+            "ycbcr2rgb" => Filter::Convert(ColorConversion::YCbCrToRgb),
+            "rgb2lab" => Filter::Convert(ColorConversion::RgbToLab),
+            "lab2rgb" => Filter::Convert(ColorConversion::LabToRgb),
+            _ => bail!("unknown filter '{}'", s),
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct FilterParams {
+    values: [f32; 4],
+    mode: u32,
+    radius: i32,
+    _padding: [u32; 2],
+}
+
+impl FilterParams {
+    fn new(values: [f32; 4], mode: u32, radius: u32) -> Self {
+        Self { values, mode, radius: radius as i32, _padding: [0; 2] }
+    }
+}
+
+/// What a stage of a multi-pass filter reads.
+#[derive(Clone, Copy)]
+enum StageInput {
+    /// The previous stage's result (or the filter's input for the first stage).
+    Previous,
+    /// The filter's input as `input_texture` and the previous stage's result as `aux_texture`.
+    FilterInput,
+}
+
+struct FilterPipelines {
+    separable_h: wgpu::ComputePipeline,
+    separable_v: wgpu::ComputePipeline,
+    sobel_gradient: wgpu::ComputePipeline,
+    sobel_magnitude: wgpu::ComputePipeline,
+    canny_suppress: wgpu::ComputePipeline,
+    canny_hysteresis: wgpu::ComputePipeline,
+    canny_finish: wgpu::ComputePipeline,
+    unsharp: wgpu::ComputePipeline,
+    median: wgpu::ComputePipeline,
+    resize_h: wgpu::ComputePipeline,
+    resize_v: wgpu::ComputePipeline,
+    color: wgpu::ComputePipeline,
+}
+
+/// An ordered list of compute filters applied on the GPU, each producing a new `FilterImage`.
+pub struct FilterChain {
+    pub filters: Vec<Filter>,
+    layout: wgpu::BindGroupLayout,
+    pipelines: FilterPipelines,
+}
+
+impl FilterChain {
+    pub fn new(init: &IWgpuInit) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Filter Bind Group Layout"),
+            entries: &[
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: FILTER_FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(3),
+            ],
+        });
+        let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Filter Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let module = |name: &str, source: &str| {
+            init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(name),
+                source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!("{}\n{}", FILTER_COMMON_WGSL, source))),
+            })
+        };
+        let pipeline = |module: &wgpu::ShaderModule, entry_point: &str| {
+            init.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let separable = module("Separable Filter", include_str!("shaders/filter/separable.wgsl"));
+        let sobel = module("Sobel Filter", include_str!("shaders/filter/sobel.wgsl"));
+        let canny = module("Canny Filter", include_str!("shaders/filter/canny.wgsl"));
+        let unsharp = module("Unsharp Mask", include_str!("shaders/filter/unsharp.wgsl"));
+        let median = module("Median Filter", include_str!("shaders/filter/median.wgsl"));
+        let resize = module("Lanczos Resize", include_str!("shaders/filter/resize.wgsl"));
+        let color = module("Color Conversion", include_str!("shaders/filter/color.wgsl"));
+        let pipelines = FilterPipelines {
+            separable_h: pipeline(&separable, "horizontal"),
+            separable_v: pipeline(&separable, "vertical"),
+            sobel_gradient: pipeline(&sobel, "gradient"),
+            sobel_magnitude: pipeline(&sobel, "magnitude"),
+            canny_suppress: pipeline(&canny, "suppress"),
+            canny_hysteresis: pipeline(&canny, "hysteresis"),
+            canny_finish: pipeline(&canny, "finish"),
+            unsharp: pipeline(&unsharp, "main"),
+            median: pipeline(&median, "main"),
+            resize_h: pipeline(&resize, "horizontal"),
+            resize_v: pipeline(&resize, "vertical"),
+            color: pipeline(&color, "main"),
+        };
+        Self { filters: Vec::new(), layout, pipelines }
+    }
+
+    pub fn push(&mut self, filter: Filter) -> &mut Self {
+        self.filters.push(filter);
+        self
+    }
+
+    /// Records every filter into `encoder` and returns the final image; `input` is returned
+    /// unchanged (as a copy) for an empty chain.
+    pub fn run(&self, init: &IWgpuInit, encoder: &mut wgpu::CommandEncoder, input: &FilterImage) -> FilterImage {
+        let mut current: Option<FilterImage> = None;
+        for filter in &self.filters {
+            let next = self.run_filter(init, encoder, current.as_ref().unwrap_or(input), filter);
+            current = Some(next);
+        }
+        current.unwrap_or_else(|| {
+            let copy = FilterImage::new(init, input.width, input.height);
+            encoder.copy_texture_to_texture(input.texture.as_image_copy(), copy.texture.as_image_copy(), input.texture.size());
+            copy
+        })
+    }
+
+    /// Runs the chain in its own submission.
+    pub fn apply(&self, init: &IWgpuInit, input: &FilterImage) -> FilterImage {
+        let mut encoder = init.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Filter Chain") });
+        let output = self.run(init, &mut encoder, input);
+        init.queue.submit(std::iter::once(encoder.finish()));
+        output
+    }
+
+    fn run_filter(&self, init: &IWgpuInit, encoder: &mut wgpu::CommandEncoder, input: &FilterImage, filter: &Filter) -> FilterImage {
+        let p = &self.pipelines;
+        let (w, h) = (input.width, input.height);
+        let gaussian_radius = |sigma: f32| (sigma * 3.0).ceil().max(1.0) as u32;
+        // Each stage: pipeline, params, output size and what it reads.
+        let separable = |mode: u32, radius: u32, sigma: f32| {
+            let params = FilterParams::new([sigma, 0.0, 0.0, 0.0], mode, radius);
+            vec![(&p.separable_h, params, (w, h), StageInput::Previous), (&p.separable_v, params, (w, h), StageInput::Previous)]
+        };
+        let none = FilterParams::new([0.0; 4], 0, 0);
+        let stages = match *filter {
+            Filter::GaussianBlur { sigma } => separable(1, gaussian_radius(sigma), sigma),
+            Filter::BoxBlur { radius } => separable(0, radius, 0.0),
+            Filter::Erode { radius } => separable(2, radius, 0.0),
+            Filter::Dilate { radius } => separable(3, radius, 0.0),
+            Filter::Sobel => vec![(&p.sobel_magnitude, none, (w, h), StageInput::Previous)],
+            Filter::Canny { sigma, low, high } => {
+                let mut stages = separable(1, gaussian_radius(sigma), sigma);
+                stages.push((&p.sobel_gradient, none, (w, h), StageInput::Previous));
+                stages.push((&p.canny_suppress, FilterParams::new([low, high, 0.0, 0.0], 0, 0), (w, h), StageInput::Previous));
+                stages.extend((0..HYSTERESIS_PASSES).map(|_| (&p.canny_hysteresis, none, (w, h), StageInput::Previous)));
+                stages.push((&p.canny_finish, none, (w, h), StageInput::Previous));
+                stages
+            }
+            Filter::UnsharpMask { sigma, amount } => {
+                let mut stages = separable(1, gaussian_radius(sigma), sigma);
+                stages.push((&p.unsharp, FilterParams::new([amount, 0.0, 0.0, 0.0], 0, 0), (w, h), StageInput::FilterInput));
+                stages
+            }
+            Filter::Median { radius } => vec![(&p.median, FilterParams::new([0.0; 4], 0, radius.clamp(1, 2)), (w, h), StageInput::Previous)],
+            Filter::Resize { width, height } => {
+                let (width, height) = (width.max(1), height.max(1));
+                vec![(&p.resize_h, none, (width, h), StageInput::Previous), (&p.resize_v, none, (width, height), StageInput::Previous)]
+            }
+            Filter::Convert(conversion) => {
+                let mode = match conversion {
+                    ColorConversion::RgbToHsv => 0,
+                    ColorConversion::HsvToRgb => 1,
+                    ColorConversion::RgbToYCbCr => 2,
+                    ColorConversion::YCbCrToRgb => 3,
+                    ColorConversion::RgbToLab => 4,
+                    ColorConversion::LabToRgb => 5,
+                };
+                vec![(&p.color, FilterParams::new([0.0; 4], mode, 0), (w, h), StageInput::Previous)]
+            }
+        };
+
+        let mut current: Option<FilterImage> = None;
+        for (pipeline, params, (out_w, out_h), stage_input) in stages {
+            let output = FilterImage::new(init, out_w, out_h);
+            let previous = current.as_ref().unwrap_or(input);
+            let (source, aux) = match stage_input {
+                StageInput::Previous => (previous, previous),
+                StageInput::FilterInput => (input, previous),
+            };
+            // Uniforms are written per stage; `queue.write_buffer` would only keep the last values
+            // by the time the encoder is submitted.
+            let params_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Filter Params"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Filter Bind Group"),
+                layout: &self.layout,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&source.view) },
+                    wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&output.view) },
+                    wgpu::BindGroupEntry { binding: 2, resource: params_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::TextureView(&aux.view) },
+                ],
+            });
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Filter Pass"), timestamp_writes: None });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(out_w.div_ceil(WORKGROUP_SIZE), out_h.div_ceil(WORKGROUP_SIZE), 1);
+            drop(pass);
+            current = Some(output);
+        }
+        current.expect("every filter has at least one stage")
+    }
+}
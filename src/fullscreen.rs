@@ -0,0 +1,158 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::blend::BlendMode;
+use crate::helpers::{self as ws, IWgpuInit};
+use crate::instance::{InstanceBuffer, InstanceData};
+
+/// Vertex stages for image-space passes: `vs_main` (a single oversized triangle) and `vs_quad`
+/// (instanced rectangles), both producing `VertexOutput { position, uv }` with uv (0, 0) at the
+/// top-left, so textures come out upright despite wgpu's y-up clip space. Fragment shaders are
+/// appended to it.
+pub const FULLSCREEN_WGSL: &str = include_str!("shaders/fullscreen.wgsl");
+
+#[derive(Clone, Debug)]
+pub struct FullscreenPassDescriptor<'a> {
+    pub label: &'a str,
+    pub fs_entry: &'a str,
+    pub bind_group_layouts: &'a [&'a wgpu::BindGroupLayout],
+    /// Empty means a single target in the surface format.
+    pub color_formats: &'a [wgpu::TextureFormat],
+    pub sample_count: u32,
+    /// Per color target, see `IRenderPipeline::blend`.
+    pub blend: &'a [BlendMode],
+}
+
+impl Default for FullscreenPassDescriptor<'_> {
+    fn default() -> Self {
+        Self { label: "Fullscreen Pass", fs_entry: "fs_main", bind_group_layouts: &[], color_formats: &[], sample_count: 1,
+            blend: &[] }
+    }
+}
+
+/// A pipeline drawing one triangle that covers the whole target; only the fragment stage and
+/// its bindings are supplied by the caller.
+pub struct FullscreenPass {
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+impl FullscreenPass {
+    /// `fragment` is WGSL appended to `FULLSCREEN_WGSL`.
+    pub fn new(init: &IWgpuInit, fragment: &str, descriptor: &FullscreenPassDescriptor) -> Self {
+        let shader = create_shader(init, fragment, descriptor.label);
+        Self::with_module(init, &shader, descriptor)
+    }
+
+    /// Uses a module that already contains `FULLSCREEN_WGSL`, e.g. to build several entry points
+    /// from one compilation.
+    pub fn with_module(init: &IWgpuInit, shader: &wgpu::ShaderModule, descriptor: &FullscreenPassDescriptor) -> Self {
+        Self { pipeline: create_pipeline(init, shader, descriptor, "vs_main", wgpu::PrimitiveTopology::TriangleList, &[]) }
+    }
+
+    /// Binds `bind_groups` to groups 0, 1, ... and draws.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, bind_groups: &[&wgpu::BindGroup]) {
+        render_pass.set_pipeline(&self.pipeline);
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32, *bind_group, &[]);
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Destination and source rectangles of one `QuadPass` instance, each as (left, top, right,
+/// bottom) with y pointing down: `rect` in 0..1 target coordinates, `uv` in texture coordinates.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Quad {
+    pub rect: [f32; 4],
+    pub uv: [f32; 4],
+}
+
+impl Quad {
+    /// The whole texture stretched over `rect`.
+    pub fn new(rect: [f32; 4]) -> Self {
+        Self { rect, uv: [0.0, 0.0, 1.0, 1.0] }
+    }
+
+    /// The whole texture drawn at pixel position (x, y) with the given size, in a target of
+    /// `target_size` pixels. The rectangle may extend past the target's edges.
+    pub fn from_pixels(x: f32, y: f32, width: f32, height: f32, target_size: (u32, u32)) -> Self {
+        let (tw, th) = (target_size.0.max(1) as f32, target_size.1.max(1) as f32);
+        Self::new([x / tw, y / th, (x + width) / tw, (y + height) / th])
+    }
+}
+
+impl InstanceData for Quad {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
+}
+
+/// Like `FullscreenPass`, but draws textured rectangles into sub-regions of the target, one
+/// instance per `Quad`.
+pub struct QuadPass {
+    pub pipeline: wgpu::RenderPipeline,
+    pub quads: InstanceBuffer<Quad>,
+}
+
+impl QuadPass {
+    pub fn new(init: &IWgpuInit, fragment: &str, descriptor: &FullscreenPassDescriptor) -> Self {
+        let shader = create_shader(init, fragment, descriptor.label);
+        let layout = [Quad::vertex_buffer_layout()];
+        Self {
+            pipeline: create_pipeline(init, &shader, descriptor, "vs_quad", wgpu::PrimitiveTopology::TriangleStrip, &layout),
+            quads: InstanceBuffer::new(init, &[Quad::new([0.0, 0.0, 1.0, 1.0])]),
+        }
+    }
+
+    /// Replaces the quads drawn by `draw` and `draw_range`.
+    pub fn set_quads(&mut self, init: &IWgpuInit, quads: &[Quad]) {
+        self.quads.set(init, quads);
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, bind_groups: &[&wgpu::BindGroup]) {
+        self.draw_range(render_pass, bind_groups, self.quads.range());
+    }
+
+    /// Draws a subset of the quads, e.g. to switch textures between them.
+    pub fn draw_range(&self, render_pass: &mut wgpu::RenderPass, bind_groups: &[&wgpu::BindGroup], quads: std::ops::Range<u32>) {
+        if quads.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            render_pass.set_bind_group(i as u32, *bind_group, &[]);
+        }
+        self.quads.bind(render_pass, 0);
+        render_pass.draw(0..4, quads);
+    }
+}
+
+fn create_shader(init: &IWgpuInit, fragment: &str, label: &str) -> wgpu::ShaderModule {
+    init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!("{}\n{}", FULLSCREEN_WGSL, fragment))),
+    })
+}
+
+fn create_pipeline(init: &IWgpuInit, shader: &wgpu::ShaderModule, descriptor: &FullscreenPassDescriptor, vs_entry: &str,
+topology: wgpu::PrimitiveTopology, vertex_buffer_layout: &[wgpu::VertexBufferLayout]) -> wgpu::RenderPipeline {
+    let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(descriptor.label),
+        bind_group_layouts: descriptor.bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+    let mut ppl = ws::IRenderPipeline {
+        shader: Some(shader),
+        pipeline_layout: Some(&pipeline_layout),
+        vertex_buffer_layout,
+        topology,
+        is_depth_stencil: false,
+        color_formats: descriptor.color_formats,
+        blend: descriptor.blend,
+        sample_count: Some(descriptor.sample_count),
+        vs_entry: vs_entry.to_string(),
+        fs_entry: descriptor.fs_entry.to_string(),
+        ..Default::default()
+    };
+    ppl.new(init)
+}
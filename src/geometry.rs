@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{InnerSpace, Vector2, Vector3};
+
+use crate::helpers::IWgpuInit;
+use crate::mesh::Mesh;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    /// xyz is the tangent, w is the bitangent sign (bitangent = cross(normal, tangent) * w).
+    pub tangent: [f32; 4],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x2,
+        3 => Float32x4,
+    ];
+
+    pub fn new(position: [f32; 3], normal: [f32; 3], uv: [f32; 2]) -> Self {
+        Self { position, normal, uv, tangent: [0.0; 4] }
+    }
+
+    pub fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Indexed triangle list geometry, ready to be uploaded with `to_mesh`.
+/// All generators are centered on the origin, use +Y as up and counter-clockwise front faces.
+#[derive(Clone, Debug, Default)]
+pub struct Geometry {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Geometry {
+    pub fn to_mesh(&self, init: &IWgpuInit) -> Mesh {
+        Mesh::new(init, &self.vertices, &self.indices, wgpu::PrimitiveTopology::TriangleList)
+    }
+
+    pub fn cube(size: f32, subdivisions: u32) -> Self {
+        let h = size * 0.5;
+        let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+            // (normal, right, up) with right x up == normal
+            ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+            ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+            ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+            ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+            ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ];
+        let mut geometry = Self::default();
+        for (n, r, u) in faces {
+            let (n, r, u) = (Vector3::from(n), Vector3::from(r), Vector3::from(u));
+            let top_left = (n - r + u) * h;
+            geometry.push_grid(top_left, r * size, -u * size, n, subdivisions.max(1), subdivisions.max(1));
+        }
+        geometry.compute_tangents();
+        geometry
+    }
+
+    /// A flat grid in the XZ plane facing +Y.
+    pub fn plane(width: f32, depth: f32, x_segments: u32, z_segments: u32) -> Self {
+        let mut geometry = Self::default();
+        let top_left = Vector3::new(-width * 0.5, 0.0, -depth * 0.5);
+        geometry.push_grid(
+            top_left,
+            Vector3::new(width, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, depth),
+            Vector3::unit_y(),
+            x_segments.max(1),
+            z_segments.max(1),
+        );
+        geometry.compute_tangents();
+        geometry
+    }
+
+    pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> Self {
+        let stacks = stacks.max(2);
+        let profile: Vec<ProfilePoint> = (0..=stacks)
+            .map(|i| {
+                let phi = -PI * 0.5 + PI * i as f32 / stacks as f32;
+                let (s, c) = phi.sin_cos();
+                ProfilePoint { radius: radius * c, y: radius * s, normal: Vector2::new(c, s), v: 1.0 - i as f32 / stacks as f32 }
+            })
+            .collect();
+        let mut geometry = Self::default();
+        geometry.push_lathe(&profile, sectors.max(3));
+        geometry.compute_tangents();
+        geometry
+    }
+
+    pub fn icosphere(radius: f32, subdivisions: u32) -> Self {
+        let t = (1.0 + 5f32.sqrt()) * 0.5;
+        let mut positions: Vec<Vector3<f32>> = [
+            [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+            [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+            [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+        ]
+        .iter()
+        .map(|p| Vector3::from(*p).normalize())
+        .collect();
+        let mut triangles: Vec<[u32; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+
+        for _ in 0..subdivisions {
+            let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+            let mut midpoint = |a: u32, b: u32, positions: &mut Vec<Vector3<f32>>| -> u32 {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    positions.push(((positions[a as usize] + positions[b as usize]) * 0.5).normalize());
+                    positions.len() as u32 - 1
+                })
+            };
+            let mut next = Vec::with_capacity(triangles.len() * 4);
+            for [a, b, c] in triangles {
+                let ab = midpoint(a, b, &mut positions);
+                let bc = midpoint(b, c, &mut positions);
+                let ca = midpoint(c, a, &mut positions);
+                next.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+            }
+            triangles = next;
+        }
+
+        let mut geometry = Self::default();
+        for p in &positions {
+            geometry.vertices.push(Vertex::new((p * radius).into(), (*p).into(), spherical_uv(*p)));
+        }
+        // Triangles crossing the u = 0/1 seam get their low-u vertices duplicated with u + 1
+        // so the texture does not wrap backwards across the whole sphere. The poles have no
+        // longitude of their own, so they get a copy per triangle at the middle of the other two.
+        let is_pole = |p: &Vector3<f32>| p.x.abs() < 1e-6 && p.z.abs() < 1e-6;
+        let mut seam: HashMap<u32, u32> = HashMap::new();
+        for tri in &mut triangles {
+            let poles = tri.map(|i| is_pole(&positions[i as usize]));
+            let us = tri.map(|i| geometry.vertices[i as usize].uv[0]);
+            let sides = || us.iter().zip(poles).filter(|(_, pole)| !pole).map(|(u, _)| *u);
+            let max_u = sides().fold(f32::MIN, f32::max);
+            let min_u = sides().fold(f32::MAX, f32::min);
+            if max_u - min_u > 0.5 {
+                for ((index, u), pole) in tri.iter_mut().zip(us).zip(poles) {
+                    if u < 0.5 && !pole {
+                        *index = *seam.entry(*index).or_insert_with(|| {
+                            let mut v = geometry.vertices[*index as usize];
+                            v.uv[0] += 1.0;
+                            geometry.vertices.push(v);
+                            geometry.vertices.len() as u32 - 1
+                        });
+                    }
+                }
+            }
+            if let Some(k) = poles.iter().position(|&pole| pole) {
+                let (a, b) = (tri[(k + 1) % 3], tri[(k + 2) % 3]);
+                let mut v = geometry.vertices[tri[k] as usize];
+                v.uv[0] = 0.5 * (geometry.vertices[a as usize].uv[0] + geometry.vertices[b as usize].uv[0]);
+                geometry.vertices.push(v);
+                tri[k] = geometry.vertices.len() as u32 - 1;
+            }
+            geometry.push_triangle(tri[0], tri[1], tri[2]);
+        }
+        geometry.compute_tangents();
+        geometry
+    }
+
+    pub fn cylinder(radius: f32, height: f32, radial_segments: u32, height_segments: u32, capped: bool) -> Self {
+        Self::frustum(radius, radius, height, radial_segments, height_segments, capped)
+    }
+
+    pub fn cone(radius: f32, height: f32, radial_segments: u32, height_segments: u32, capped: bool) -> Self {
+        Self::frustum(radius, 0.0, height, radial_segments, height_segments, capped)
+    }
+
+    pub fn torus(major_radius: f32, minor_radius: f32, radial_segments: u32, tubular_segments: u32) -> Self {
+        let radial_segments = radial_segments.max(3);
+        let tubular_segments = tubular_segments.max(3);
+        let mut geometry = Self::default();
+        for i in 0..=tubular_segments {
+            let theta = 2.0 * PI * i as f32 / tubular_segments as f32;
+            let (st, ct) = theta.sin_cos();
+            for j in 0..=radial_segments {
+                let phi = 2.0 * PI * j as f32 / radial_segments as f32;
+                let (sp, cp) = phi.sin_cos();
+                let normal = Vector3::new(cp * st, sp, cp * ct);
+                let position = Vector3::new(major_radius * st, 0.0, major_radius * ct) + normal * minor_radius;
+                geometry.vertices.push(Vertex::new(
+                    position.into(),
+                    normal.into(),
+                    [i as f32 / tubular_segments as f32, 1.0 - j as f32 / radial_segments as f32],
+                ));
+            }
+        }
+        let stride = radial_segments + 1;
+        for i in 0..tubular_segments {
+            for j in 0..radial_segments {
+                let a = i * stride + j;
+                geometry.push_quad(a, a + stride, a + stride + 1, a + 1);
+            }
+        }
+        geometry.compute_tangents();
+        geometry
+    }
+
+    /// A cylinder of the given `height` with hemispherical ends, so the total height is
+    /// `height + 2 * radius`.
+    pub fn capsule(radius: f32, height: f32, radial_segments: u32, cap_rings: u32) -> Self {
+        let cap_rings = cap_rings.max(1);
+        let total = height + 2.0 * radius;
+        let mut profile = Vec::with_capacity(2 * cap_rings as usize + 2);
+        for (offset, start) in [(-height * 0.5, -PI * 0.5), (height * 0.5, 0.0)] {
+            for i in 0..=cap_rings {
+                let phi = start + PI * 0.5 * i as f32 / cap_rings as f32;
+                let (s, c) = phi.sin_cos();
+                let y = offset + radius * s;
+                profile.push(ProfilePoint { radius: radius * c, y, normal: Vector2::new(c, s), v: 0.5 - y / total });
+            }
+        }
+        let mut geometry = Self::default();
+        geometry.push_lathe(&profile, radial_segments.max(3));
+        geometry.compute_tangents();
+        geometry
+    }
+
+    fn frustum(bottom: f32, top: f32, height: f32, radial_segments: u32, height_segments: u32, capped: bool) -> Self {
+        let radial_segments = radial_segments.max(3);
+        let height_segments = height_segments.max(1);
+        let normal = Vector2::new(height, bottom - top).normalize();
+        let profile: Vec<ProfilePoint> = (0..=height_segments)
+            .map(|i| {
+                let t = i as f32 / height_segments as f32;
+                ProfilePoint { radius: bottom + (top - bottom) * t, y: height * (t - 0.5), normal, v: 1.0 - t }
+            })
+            .collect();
+        let mut geometry = Self::default();
+        geometry.push_lathe(&profile, radial_segments);
+        if capped {
+            if bottom > 0.0 {
+                geometry.push_disk(bottom, -height * 0.5, -1.0, radial_segments);
+            }
+            if top > 0.0 {
+                geometry.push_disk(top, height * 0.5, 1.0, radial_segments);
+            }
+        }
+        geometry.compute_tangents();
+        geometry
+    }
+
+    /// Appends a (nu x nv)-segment grid spanning `top_left + s * right + t * down`.
+    fn push_grid(&mut self, top_left: Vector3<f32>, right: Vector3<f32>, down: Vector3<f32>, normal: Vector3<f32>, nu: u32, nv: u32) {
+        let base = self.vertices.len() as u32;
+        for j in 0..=nv {
+            for i in 0..=nu {
+                let (s, t) = (i as f32 / nu as f32, j as f32 / nv as f32);
+                let position = top_left + right * s + down * t;
+                self.vertices.push(Vertex::new(position.into(), normal.into(), [s, t]));
+            }
+        }
+        let stride = nu + 1;
+        for j in 0..nv {
+            for i in 0..nu {
+                let a = base + j * stride + i;
+                self.push_quad(a + stride, a + stride + 1, a + 1, a);
+            }
+        }
+    }
+
+    /// Revolves `profile` (ordered bottom to top) around the Y axis.
+    fn push_lathe(&mut self, profile: &[ProfilePoint], segments: u32) {
+        let base = self.vertices.len() as u32;
+        for p in profile {
+            for j in 0..=segments {
+                let u = j as f32 / segments as f32;
+                let (s, c) = (2.0 * PI * u).sin_cos();
+                self.vertices.push(Vertex::new(
+                    [p.radius * s, p.y, p.radius * c],
+                    [p.normal.x * s, p.normal.y, p.normal.x * c],
+                    [u, p.v],
+                ));
+            }
+        }
+        let stride = segments + 1;
+        for (i, ring) in profile.windows(2).enumerate() {
+            for j in 0..segments {
+                let a = base + i as u32 * stride + j;
+                let (b, c, d) = (a + 1, a + stride + 1, a + stride);
+                // Skip the degenerate half of quads touching a pole.
+                if ring[0].radius > 0.0 {
+                    self.push_triangle(a, b, c);
+                }
+                if ring[1].radius > 0.0 {
+                    self.push_triangle(a, c, d);
+                }
+            }
+        }
+    }
+
+    fn push_disk(&mut self, radius: f32, y: f32, facing: f32, segments: u32) {
+        let center = self.vertices.len() as u32;
+        self.vertices.push(Vertex::new([0.0, y, 0.0], [0.0, facing, 0.0], [0.5, 0.5]));
+        for j in 0..=segments {
+            let (s, c) = (2.0 * PI * j as f32 / segments as f32).sin_cos();
+            self.vertices.push(Vertex::new(
+                [radius * s, y, radius * c],
+                [0.0, facing, 0.0],
+                [0.5 + 0.5 * s, 0.5 + 0.5 * c * facing],
+            ));
+        }
+        for j in 0..segments {
+            let (a, b) = (center + 1 + j, center + 2 + j);
+            if facing > 0.0 {
+                self.push_triangle(center, a, b);
+            } else {
+                self.push_triangle(center, b, a);
+            }
+        }
+    }
+
+    fn push_quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.push_triangle(a, b, c);
+        self.push_triangle(a, c, d);
+    }
+
+    fn push_triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    /// Recomputes per-vertex tangents from the UV layout (Lengyel's method), orthogonalized
+    /// against the normal.
+    pub fn compute_tangents(&mut self) {
+        let mut tangents = vec![Vector3::new(0.0f32, 0.0, 0.0); self.vertices.len()];
+        let mut bitangents = tangents.clone();
+        for tri in self.indices.chunks_exact(3) {
+            let [v0, v1, v2] = [tri[0], tri[1], tri[2]].map(|i| self.vertices[i as usize]);
+            let e1 = Vector3::from(v1.position) - Vector3::from(v0.position);
+            let e2 = Vector3::from(v2.position) - Vector3::from(v0.position);
+            let d1 = Vector2::from(v1.uv) - Vector2::from(v0.uv);
+            let d2 = Vector2::from(v2.uv) - Vector2::from(v0.uv);
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < f32::EPSILON {
+                continue;
+            }
+            let r = 1.0 / det;
+            let t = (e1 * d2.y - e2 * d1.y) * r;
+            let b = (e2 * d1.x - e1 * d2.x) * r;
+            for &i in tri {
+                tangents[i as usize] += t;
+                bitangents[i as usize] += b;
+            }
+        }
+        for (v, (t, b)) in self.vertices.iter_mut().zip(tangents.into_iter().zip(bitangents)) {
+            let n = Vector3::from(v.normal);
+            let mut tangent = t - n * n.dot(t);
+            if tangent.magnitude2() < 1e-12 {
+                // No usable UV gradient (e.g. a pole), pick any direction perpendicular to n.
+                let axis = if n.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+                tangent = axis - n * n.dot(axis);
+            }
+            let tangent = tangent.normalize();
+            let w = if n.cross(tangent).dot(b) < 0.0 { -1.0 } else { 1.0 };
+            v.tangent = [tangent.x, tangent.y, tangent.z, w];
+        }
+    }
+}
+
+struct ProfilePoint {
+    radius: f32,
+    y: f32,
+    /// (radial, vertical) components of the surface normal.
+    normal: Vector2<f32>,
+    v: f32,
+}
+
+fn spherical_uv(p: Vector3<f32>) -> [f32; 2] {
+    let u = p.x.atan2(p.z) / (2.0 * PI);
+    [if u < 0.0 { u + 1.0 } else { u }, p.y.clamp(-1.0, 1.0).acos() / PI]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generators() -> Vec<(&'static str, Geometry)> {
+        vec![
+            ("cube", Geometry::cube(2.0, 3)),
+            ("plane", Geometry::plane(2.0, 1.0, 4, 2)),
+            ("uv_sphere", Geometry::uv_sphere(1.5, 16, 8)),
+            ("icosphere", Geometry::icosphere(1.5, 2)),
+            ("cylinder", Geometry::cylinder(1.0, 2.0, 12, 2, true)),
+            ("cone", Geometry::cone(1.0, 2.0, 12, 2, true)),
+            ("torus", Geometry::torus(1.0, 0.3, 8, 16)),
+            ("capsule", Geometry::capsule(0.5, 1.0, 12, 4)),
+        ]
+    }
+
+    #[test]
+    fn indices_in_range() {
+        for (name, g) in generators() {
+            assert!(!g.indices.is_empty(), "{name}");
+            assert_eq!(g.indices.len() % 3, 0, "{name}");
+            let n = g.vertices.len() as u32;
+            assert!(g.indices.iter().all(|&i| i < n), "{name}: index out of range for {n} vertices");
+        }
+    }
+
+    #[test]
+    fn faces_agree_with_vertex_normals() {
+        for (name, g) in generators() {
+            for tri in g.indices.chunks_exact(3) {
+                let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| g.vertices[i as usize]);
+                let (pa, pb, pc) = (Vector3::from(a.position), Vector3::from(b.position), Vector3::from(c.position));
+                let face = (pb - pa).cross(pc - pa);
+                if face.magnitude() < 1e-6 {
+                    continue;
+                }
+                let normal = Vector3::from(a.normal) + Vector3::from(b.normal) + Vector3::from(c.normal);
+                assert!(face.normalize().dot(normal.normalize()) > 0.5, "{name}: triangle {tri:?} is not counter-clockwise");
+                // Outward: the smooth normals point away from the origin (the plane runs through
+                // it), except for the torus whose surface wraps around its tube.
+                if name != "torus" {
+                    let center = (pa + pb + pc) / 3.0;
+                    assert!(normal.dot(center) > -1e-6, "{name}: triangle {tri:?} faces inward");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn normals_and_tangents_are_orthonormal() {
+        for (name, g) in generators() {
+            for v in &g.vertices {
+                let n = Vector3::from(v.normal);
+                let t = Vector3::new(v.tangent[0], v.tangent[1], v.tangent[2]);
+                assert!((n.magnitude() - 1.0).abs() < 1e-4, "{name}: normal {n:?}");
+                assert!((t.magnitude() - 1.0).abs() < 1e-4, "{name}: tangent {t:?}");
+                assert!(n.dot(t).abs() < 1e-4, "{name}: tangent {t:?} is not perpendicular to {n:?}");
+                assert!(v.tangent[3] == 1.0 || v.tangent[3] == -1.0, "{name}: {:?}", v.tangent);
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_follow_uv() {
+        // The plane's u runs along +X and v along +Z, so the bitangent cross(+Y, +X) = -Z
+        // has to be flipped.
+        let g = Geometry::plane(2.0, 2.0, 1, 1);
+        for v in &g.vertices {
+            assert_eq!(v.tangent, [1.0, 0.0, 0.0, -1.0]);
+        }
+
+        let mut g = Geometry {
+            vertices: vec![
+                Vertex::new([0.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+                Vertex::new([0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
+                Vertex::new([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
+            ],
+            indices: vec![0, 1, 2],
+        };
+        g.compute_tangents();
+        for v in &g.vertices {
+            // u runs along +Y, v along -X = cross(+Z, +Y).
+            assert_eq!(v.tangent, [0.0, 1.0, 0.0, 1.0]);
+        }
+    }
+
+    #[test]
+    fn icosphere_uvs() {
+        let g = Geometry::icosphere(1.0, 2);
+        for tri in g.indices.chunks_exact(3) {
+            let us = [tri[0], tri[1], tri[2]].map(|i| g.vertices[i as usize].uv[0]);
+            let spread = us.iter().cloned().fold(f32::MIN, f32::max) - us.iter().cloned().fold(f32::MAX, f32::min);
+            assert!(spread < 0.5, "triangle {tri:?} wraps around the seam: {us:?}");
+        }
+        let poles: Vec<&Vertex> = g.vertices.iter().filter(|v| v.position[0] == 0.0 && v.position[2] == 0.0).collect();
+        assert!(poles.len() > 2);
+        // Every pole copy in use sits between its neighbours instead of at u = 0.
+        for tri in g.indices.chunks_exact(3) {
+            for (k, &i) in tri.iter().enumerate() {
+                let v = g.vertices[i as usize];
+                if v.position[0] == 0.0 && v.position[2] == 0.0 {
+                    let others = [tri[(k + 1) % 3], tri[(k + 2) % 3]].map(|j| g.vertices[j as usize].uv[0]);
+                    assert!((v.uv[0] - 0.5 * (others[0] + others[1])).abs() < 1e-6);
+                }
+            }
+        }
+    }
+}
@@ -1,22 +1,54 @@
+use anyhow::Context;
 use image::DynamicImage;
 use winit::window::Window;
 
+use crate::{blend::BlendMode, depth::DepthConfig, info::AdapterReport};
+
+/// Features `IWgpuInit` enables on its device; the adapter must support them.
+pub const DEVICE_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+/// Features `IWgpuInit` enables when the adapter has them, e.g. for `Depth32FloatStencil8`.
+pub const OPTIONAL_DEVICE_FEATURES: wgpu::Features = wgpu::Features::DEPTH32FLOAT_STENCIL8;
+
+/// Sample counts wgpu can support; 1 always is.
+const SAMPLE_COUNTS: [u32; 5] = [1, 2, 4, 8, 16];
+
+/// Color format of the offscreen frames of a headless `IWgpuInit`.
+pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
 pub struct IWgpuInit<'a> {
     pub instance: wgpu::Instance,
-    pub surface: wgpu::Surface<'a>,
+    /// `None` when headless; frames then go to `offscreen`.
+    pub surface: Option<wgpu::Surface<'a>>,
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
+    /// MSAA samples of frame attachments, negotiated to one the color and depth formats
+    /// support, see `set_sample_count`.
     pub sample_count: u32,
+    /// The sample count asked for in the constructor or `set_sample_count`, which
+    /// `sample_count` is negotiated from.
+    pub requested_sample_count: u32,
+    /// Depth format and direction of frame attachments and pipelines, see `set_depth`.
+    pub depth: DepthConfig,
+    pub window: Option<&'a Window>,
+    /// The texture frames are rendered to when headless or after `set_offscreen`, sized and
+    /// formatted after `config`.
+    pub offscreen: Option<wgpu::Texture>,
 }
 
 impl <'a> IWgpuInit <'a> {
     pub async fn new(window: &'a Window, sample_count: u32, limits: Option<wgpu::Limits>) -> IWgpuInit<'a> {
-        let limits_device = limits.unwrap_or(wgpu::Limits::default());
+        Self::new_with_backends(window, sample_count, limits, wgpu::Backends::all()).await
+    }
+
+    /// Same as `new`, restricted to the given backends, e.g. from `wgpu::Backends::from_comma_list`.
+    pub async fn new_with_backends(window: &'a Window, sample_count: u32, limits: Option<wgpu::Limits>,
+    backends: wgpu::Backends) -> IWgpuInit<'a> {
         let size = window.inner_size();
-        let instance = wgpu::Instance::default();
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { backends, ..Default::default() });
         let surface = instance.create_surface( window).unwrap();
         let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::default(),
@@ -25,18 +57,15 @@ impl <'a> IWgpuInit <'a> {
         })
         .await
         .expect("Failed to fun an appropriate adapter");
-        
-        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
-            label: None,
-            required_features: wgpu::Features::default() | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-            required_limits: limits_device,
-            memory_hints: wgpu::MemoryHints::Performance,
-        }, None).await.expect("Failed to create device");
+
+        let (device, queue) = request_device(&adapter, limits).await.expect("Failed to create device");
 
         let surface_caps = surface.get_capabilities(&adapter);
         let format = surface_caps.formats[0];
+        // Copies into the surface show offscreen frames, see `set_offscreen`.
+        let usage = wgpu::TextureUsages::RENDER_ATTACHMENT | (surface_caps.usages & wgpu::TextureUsages::COPY_DST);
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format,
             width: size.width,
             height: size.height,
@@ -46,36 +75,242 @@ impl <'a> IWgpuInit <'a> {
             desired_maximum_frame_latency: 2,
         };
         surface.configure(&device, &config);
-
+        let requested_sample_count = sample_count;
+        let sample_count = negotiate_sample_count(&adapter, &[format, DepthConfig::default().format], requested_sample_count);
 
         Self {
             instance,
-            surface,
+            surface: Some(surface),
             adapter,
             device,
             queue,
             config,
             size,
             sample_count,
+            requested_sample_count,
+            depth: DepthConfig::default(),
+            window: Some(window),
+            offscreen: None,
         }
     }
 
+    /// Renders to an offscreen `HEADLESS_FORMAT` texture instead of a window, e.g. for tests and
+    /// screenshots. With `software`, only a CPU adapter such as llvmpipe or WARP is accepted, so
+    /// results do not depend on the GPU; otherwise `WGPU_ADAPTER_NAME` is honored as in the wgpu
+    /// examples.
+    pub async fn new_headless(size: winit::dpi::PhysicalSize<u32>, sample_count: u32, limits: Option<wgpu::Limits>,
+    backends: wgpu::Backends, software: bool) -> anyhow::Result<IWgpuInit<'static>> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { backends, ..Default::default() });
+        let adapter = if software {
+            instance
+                .enumerate_adapters(backends)
+                .into_iter()
+                .find(|adapter| adapter.get_info().device_type == wgpu::DeviceType::Cpu)
+                .context("no software adapter found")?
+        } else {
+            wgpu::util::initialize_adapter_from_env_or_default(&instance, None)
+                .await
+                .context("no adapter found")?
+        };
+        let (device, queue) = request_device(&adapter, limits).await?;
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: HEADLESS_FORMAT,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let offscreen = create_offscreen_texture(&device, &config);
+        let requested_sample_count = sample_count;
+        let sample_count = negotiate_sample_count(&adapter, &[HEADLESS_FORMAT, DepthConfig::default().format], requested_sample_count);
+        Ok(IWgpuInit {
+            instance,
+            surface: None,
+            adapter,
+            device,
+            queue,
+            size: winit::dpi::PhysicalSize::new(config.width, config.height),
+            config,
+            sample_count,
+            requested_sample_count,
+            depth: DepthConfig::default(),
+            window: None,
+            offscreen: Some(offscreen),
+        })
+    }
+
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
 
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+            if self.offscreen.is_some() {
+                self.offscreen = Some(create_offscreen_texture(&self.device, &self.config));
+            }
+        }
+    }
+
+    /// Sample counts frame attachments can have, see `sample_count`.
+    pub fn supported_sample_counts(&self) -> Vec<u32> {
+        supported_sample_counts(&self.adapter, &[self.config.format, self.depth.format])
+    }
+
+    /// Switches to the supported sample count nearest to `requested` and returns it. Pipelines,
+    /// MSAA and depth views created for the previous count have to be rebuilt.
+    pub fn set_sample_count(&mut self, requested: u32) -> u32 {
+        self.requested_sample_count = requested;
+        self.sample_count = negotiate_sample_count(&self.adapter, &[self.config.format, self.depth.format], requested);
+        self.sample_count
+    }
+
+    /// Switches the depth format and direction, lowering `sample_count` if the format needs it.
+    /// Fails when the device cannot render to the format. Pipelines and depth views created
+    /// before have to be rebuilt.
+    pub fn set_depth(&mut self, depth: DepthConfig) -> anyhow::Result<()> {
+        let missing = depth.required_features() - self.device.features();
+        if !missing.is_empty() {
+            anyhow::bail!("{:?} needs the device features {:?}", depth.format, missing);
+        }
+        let usages = self.adapter.get_texture_format_features(depth.format).allowed_usages;
+        if !usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) {
+            anyhow::bail!("{:?} cannot be rendered to on this adapter", depth.format);
+        }
+        self.depth = depth;
+        self.set_sample_count(self.requested_sample_count);
+        Ok(())
+    }
+
+    /// Renders the following frames to `offscreen`, where they can be read back, e.g. for
+    /// screenshots. Presenting copies them to the window when the surface allows it. Headless
+    /// setups always render offscreen.
+    pub fn set_offscreen(&mut self, enabled: bool) {
+        if self.surface.is_none() {
+            return;
+        }
+        self.offscreen = enabled.then(|| create_offscreen_texture(&self.device, &self.config));
+    }
+
+    /// Width over height of the surface, `None` while the window is minimized.
+    pub fn aspect_ratio(&self) -> Option<f32> {
+        if self.size.width > 0 && self.size.height > 0 {
+            Some(self.size.width as f32 / self.size.height as f32)
+        } else {
+            None
+        }
+    }
+
+    /// The texture to render the next frame to: the offscreen texture when there is one,
+    /// otherwise the surface's next image.
+    pub fn get_current_frame(&self) -> Result<Frame, wgpu::SurfaceError> {
+        let surface_texture = self.surface.as_ref().map(|surface| surface.get_current_texture()).transpose()?;
+        match (&self.offscreen, surface_texture) {
+            (Some(texture), surface_texture) => {
+                let copy = self.config.usage.contains(wgpu::TextureUsages::COPY_DST);
+                Ok(Frame {
+                    texture: texture.clone(),
+                    surface_texture,
+                    copy: copy.then(|| (self.device.clone(), self.queue.clone())),
+                })
+            }
+            (None, Some(surface_texture)) => {
+                Ok(Frame { texture: surface_texture.texture.clone(), surface_texture: Some(surface_texture), copy: None })
+            }
+            (None, None) => Err(wgpu::SurfaceError::Lost),
+        }
+    }
+
+    /// Sets the window title; headless setups have none and ignore it.
+    pub fn set_title(&self, title: &str) {
+        if let Some(window) = self.window {
+            window.set_title(title);
+        }
+    }
+}
+
+/// A frame being rendered, see `IWgpuInit::get_current_frame`.
+pub struct Frame {
+    pub texture: wgpu::Texture,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+    /// Set when an offscreen frame is copied to `surface_texture` on present.
+    copy: Option<(wgpu::Device, wgpu::Queue)>,
+}
+
+impl Frame {
+    /// Shows the frame in the window; offscreen frames also stay in `IWgpuInit::offscreen`.
+    pub fn present(self) {
+        let Some(surface_texture) = self.surface_texture else {
+            return;
+        };
+        if let Some((device, queue)) = self.copy {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Present Offscreen") });
+            encoder.copy_texture_to_texture(
+                self.texture.as_image_copy(),
+                surface_texture.texture.as_image_copy(),
+                wgpu::Extent3d {
+                    width: self.texture.width().min(surface_texture.texture.width()),
+                    height: self.texture.height().min(surface_texture.texture.height()),
+                    depth_or_array_layers: 1,
+                },
+            );
+            queue.submit(std::iter::once(encoder.finish()));
         }
+        surface_texture.present();
+    }
+}
+
+/// Sample counts every format in `formats` supports on `adapter`.
+pub fn supported_sample_counts(adapter: &wgpu::Adapter, formats: &[wgpu::TextureFormat]) -> Vec<u32> {
+    SAMPLE_COUNTS
+        .into_iter()
+        .filter(|&count| formats.iter().all(|&format| adapter.get_texture_format_features(format).flags.sample_count_supported(count)))
+        .collect()
+}
+
+/// The largest sample count up to `requested` that all `formats` support, with a warning when
+/// it is lower.
+pub fn negotiate_sample_count(adapter: &wgpu::Adapter, formats: &[wgpu::TextureFormat], requested: u32) -> u32 {
+    let count = supported_sample_counts(adapter, formats).into_iter().filter(|&count| count <= requested.max(1)).max().unwrap_or(1);
+    if count != requested {
+        log::warn!("{}x MSAA is not supported for {:?}, using {}x", requested, formats, count);
     }
+    count
+}
+
+async fn request_device(adapter: &wgpu::Adapter, limits: Option<wgpu::Limits>) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
+        label: None,
+        required_features: DEVICE_FEATURES | (adapter.features() & OPTIONAL_DEVICE_FEATURES),
+        required_limits: limits.unwrap_or_default(),
+        memory_hints: wgpu::MemoryHints::Performance,
+    }, None).await?;
+    Ok((device, queue))
+}
+
+fn create_offscreen_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Frame"),
+        size: wgpu::Extent3d { width: config.width, height: config.height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
 }
 
+/// Prints the report of the adapter picked for `window`; `wgpu_info` covers every adapter
+/// without a window.
 pub async fn get_wgpu_info(window: &Window) {
-    let init = IWgpuInit::new(&window, 1, None).await;
-    println!("{:?}", init.adapter.get_info());
-    println!("{:?}", init.adapter.limits());
-    println!("{:?}", init.device.limits());
+    let init = IWgpuInit::new(window, 1, None).await;
+    println!("{}", AdapterReport::new(&init.adapter));
 }
 
 pub struct IRenderPipeline<'a> {
@@ -88,6 +323,20 @@ pub struct IRenderPipeline<'a> {
     pub strip_index_format: Option<wgpu::IndexFormat>,
     pub cull_mode: Option<wgpu::Face>,
     pub is_depth_stencil: bool,
+    /// Overrides `IWgpuInit::depth`'s format, e.g. for offscreen targets.
+    pub depth_format: Option<wgpu::TextureFormat>,
+    /// Overrides the depth test of `IWgpuInit::depth`.
+    pub depth_compare: Option<wgpu::CompareFunction>,
+    pub depth_write_enabled: bool,
+    /// Needs a depth format with a stencil aspect, see `depth::stencil_state`.
+    pub stencil: wgpu::StencilState,
+    /// Color target formats; empty means a single target in the surface format.
+    pub color_formats: &'a [wgpu::TextureFormat],
+    /// Blending of each color target, in order; targets without an entry replace. Translucent
+    /// geometry usually also turns off `depth_write_enabled`.
+    pub blend: &'a [BlendMode],
+    /// Overrides `IWgpuInit::sample_count`, e.g. for offscreen targets.
+    pub sample_count: Option<u32>,
     pub vs_entry: String,
     pub fs_entry: String,
 }
@@ -102,8 +351,15 @@ impl Default for IRenderPipeline<'_> {
             pipeline_layout: None,
             topology: wgpu::PrimitiveTopology::TriangleList,
             strip_index_format: None,
-            cull_mode: None,
+            cull_mode: Some(wgpu::Face::Back),
             is_depth_stencil: true,
+            depth_format: None,
+            depth_compare: None,
+            depth_write_enabled: true,
+            stencil: wgpu::StencilState::default(),
+            color_formats: &[],
+            blend: &[],
+            sample_count: None,
             vs_entry: String::from("vs_main"),
             fs_entry: String::from("fs_main"),
         }
@@ -111,6 +367,7 @@ impl Default for IRenderPipeline<'_> {
 }
 
 impl IRenderPipeline<'_> {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(&mut self, init: &IWgpuInit) -> wgpu::RenderPipeline {
         if self.shader.is_some() {
             self.vs_shader = self.shader;
@@ -120,41 +377,48 @@ impl IRenderPipeline<'_> {
         let mut depth_stencil: Option<wgpu::DepthStencilState> = None;
         if self.is_depth_stencil {
             depth_stencil = Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth24Plus,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::LessEqual,
-                stencil: wgpu::StencilState::default(),
+                format: self.depth_format.unwrap_or(init.depth.format),
+                depth_write_enabled: self.depth_write_enabled,
+                depth_compare: self.depth_compare.unwrap_or(init.depth.compare()),
+                stencil: self.stencil.clone(),
                 bias: wgpu::DepthBiasState::default(),
             });
         }
 
+        let surface_format = [init.config.format];
+        let color_formats = if self.color_formats.is_empty() { &surface_format[..] } else { self.color_formats };
+        let targets: Vec<Option<wgpu::ColorTargetState>> = color_formats
+            .iter()
+            .enumerate()
+            .map(|(i, &format)| {
+                Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(self.blend.get(i).unwrap_or(&BlendMode::Replace).state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })
+            })
+            .collect();
+
         init.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipeline"),
-            layout: Some(&self.pipeline_layout.unwrap()),
+            layout: Some(self.pipeline_layout.unwrap()),
             vertex: wgpu::VertexState {
-                module: &self.vs_shader.as_ref().unwrap(),
+                module: self.vs_shader.as_ref().unwrap(),
                 entry_point: Some(&self.vs_entry),
-                buffers: &self.vertex_buffer_layout,
+                buffers: self.vertex_buffer_layout,
                 compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
-                module: &self.fs_shader.as_ref().unwrap(),
+                module: self.fs_shader.as_ref().unwrap(),
                 entry_point: Some(&self.fs_entry),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: init.config.format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent::REPLACE,
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                targets: &targets,
                 compilation_options: Default::default(),
             }),
             primitive: wgpu::PrimitiveState {
                 topology: self.topology,
                 strip_index_format: self.strip_index_format,
                 front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
+                cull_mode: self.cull_mode,
                 // Setting this to anything other than Fill requires Features::POLYGON_MODE_LINE
                 // or Features::POLYGON_MODE_POINT
                 polygon_mode: wgpu::PolygonMode::Fill,
@@ -165,7 +429,7 @@ impl IRenderPipeline<'_> {
             },
             depth_stencil,
             multisample: wgpu::MultisampleState {
-                count: init.sample_count,
+                count: self.sample_count.unwrap_or(init.sample_count),
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -189,6 +453,13 @@ pub fn create_color_attachment<'a>(
 }
 
 pub fn create_image_texture_view(init: &IWgpuInit, diffuse_image: &DynamicImage) -> wgpu::TextureView {
+    create_image_texture_view_with_format(init, diffuse_image, wgpu::TextureFormat::Rgba8UnormSrgb)
+}
+
+/// Same as `create_image_texture_view`, but lets data textures (normal maps, metallic-roughness, ...)
+/// be uploaded as `Rgba8Unorm` instead of sRGB.
+pub fn create_image_texture_view_with_format(init: &IWgpuInit, diffuse_image: &DynamicImage,
+format: wgpu::TextureFormat) -> wgpu::TextureView {
     let diffuse_rgba = diffuse_image.to_rgba8();
     use image::GenericImageView;
     let dimensions = diffuse_image.dimensions();
@@ -204,7 +475,7 @@ pub fn create_image_texture_view(init: &IWgpuInit, diffuse_image: &DynamicImage)
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
             label: None,
             view_formats: &[],
@@ -253,6 +524,11 @@ pub fn create_msaa_texture_view(init: &IWgpuInit) -> wgpu::TextureView {
 
 }
 
+/// The multisampled color view for `init.sample_count`, `None` without MSAA.
+pub fn create_msaa_view(init: &IWgpuInit) -> Option<wgpu::TextureView> {
+    (init.sample_count > 1).then(|| create_msaa_texture_view(init))
+}
+
 pub fn create_msaa_color_attachement<'a>(texture_view: &'a wgpu::TextureView,
 mssa_view: &'a wgpu::TextureView) -> wgpu::RenderPassColorAttachment<'a> {
     wgpu::RenderPassColorAttachment {
@@ -265,8 +541,28 @@ mssa_view: &'a wgpu::TextureView) -> wgpu::RenderPassColorAttachment<'a> {
     }
 }
 
+/// Renders to `msaa_view` and resolves into `texture_view` when there is one, see `create_msaa_view`.
+pub fn create_frame_color_attachment<'a>(texture_view: &'a wgpu::TextureView,
+msaa_view: Option<&'a wgpu::TextureView>) -> wgpu::RenderPassColorAttachment<'a> {
+    match msaa_view {
+        Some(msaa_view) => create_msaa_color_attachement(texture_view, msaa_view),
+        None => create_color_attachment(texture_view),
+    }
+}
+
 pub fn create_depth_view(init: &IWgpuInit) -> wgpu::TextureView {
-    let depth_texture = init.device.create_texture(&wgpu::TextureDescriptor {
+    create_depth_texture(init, false).create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// A frame-sized depth texture in `IWgpuInit::depth`'s format. With `sampleable`, later passes
+/// can read it through `create_depth_sample_view`.
+pub fn create_depth_texture(init: &IWgpuInit, sampleable: bool) -> wgpu::Texture {
+    let usage = if sampleable {
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+    } else {
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+    };
+    init.device.create_texture(&wgpu::TextureDescriptor {
         size: wgpu::Extent3d {
             width: init.config.width,
             height: init.config.height,
@@ -275,25 +571,26 @@ pub fn create_depth_view(init: &IWgpuInit) -> wgpu::TextureView {
         mip_level_count: 1,
         sample_count:init.sample_count,
         dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Depth24Plus,
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        label: None,
+        format: init.depth.format,
+        usage,
+        label: Some("Depth Texture"),
         view_formats: &[],
-    });
-    depth_texture.create_view(&wgpu::TextureViewDescriptor::default())
+    })
 }
 
-pub fn create_depth_stencil_attachment<'a>(depth_view: &'a  wgpu::TextureView) -> wgpu::RenderPassDepthStencilAttachment<'a> {
-    wgpu::RenderPassDepthStencilAttachment {
-        view: depth_view,
-        depth_ops: Some(
-            wgpu::Operations {
-                load: wgpu::LoadOp::Clear(1.0),
-                store: wgpu::StoreOp::Store,
-            }
-        ),
-        stencil_ops: None,
-    }
+/// The depth aspect of a depth texture, bindable as `texture_depth_2d` (or
+/// `texture_depth_multisampled_2d`) in later passes.
+pub fn create_depth_sample_view(texture: &wgpu::Texture) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("Depth Sample View"),
+        aspect: wgpu::TextureAspect::DepthOnly,
+        ..Default::default()
+    })
+}
+
+/// Clears depth and stencil as `IWgpuInit::depth` requires, see `DepthConfig::attachment`.
+pub fn create_depth_stencil_attachment<'a>(init: &IWgpuInit, depth_view: &'a wgpu::TextureView) -> wgpu::RenderPassDepthStencilAttachment<'a> {
+    init.depth.attachment(depth_view)
 }
 
 pub fn create_default_sampler(init: &IWgpuInit) -> wgpu::Sampler {
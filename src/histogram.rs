@@ -0,0 +1,145 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::helpers::IWgpuInit;
+
+/// Bins per channel; matches `BINS` and the `find_max` workgroup size in `histogram.wgsl`.
+pub const HISTOGRAM_BINS: u32 = 256;
+
+/// Size of `Histogram::buffer`: `4 * HISTOGRAM_BINS` counts followed by the largest count of each
+/// channel, all u32.
+pub const HISTOGRAM_BUFFER_SIZE: u64 = (4 * HISTOGRAM_BINS as u64 + 4) * 4;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct HistogramParams {
+    range: [f32; 2],
+    channels: u32,
+    _padding: u32,
+}
+
+/// Per-channel histograms of `R32Float`, `Rg32Float` or `Rgba32Float` textures, counted with
+/// atomics in a compute pass. The result stays on the GPU in `buffer`, which a fragment shader
+/// can bind read-only to draw it.
+pub struct Histogram {
+    pub buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    count_pipeline: wgpu::ComputePipeline,
+    max_pipeline: wgpu::ComputePipeline,
+}
+
+impl Histogram {
+    pub fn new(init: &IWgpuInit) -> Self {
+        let buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Histogram Buffer"),
+            size: HISTOGRAM_BUFFER_SIZE,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let params_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Histogram Params"),
+            contents: bytemuck::bytes_of(&HistogramParams::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Histogram Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let shader = init.device.create_shader_module(wgpu::include_wgsl!("shaders/histogram.wgsl"));
+        let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Histogram Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point: &str| {
+            init.device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        Self {
+            count_pipeline: create_pipeline("count"),
+            max_pipeline: create_pipeline("find_max"),
+            buffer,
+            params_buffer,
+            layout,
+        }
+    }
+
+    /// Records passes that recount `source` into `buffer`. `channels` of each texel are counted,
+    /// with `range` spread over the bins and values outside it added to the first or last bin.
+    pub fn compute(&self, init: &IWgpuInit, encoder: &mut wgpu::CommandEncoder, source: &wgpu::TextureView,
+    size: (u32, u32), channels: u32, range: (f32, f32)) {
+        let params = HistogramParams { range: [range.0, range.1], channels: channels.min(4), _padding: 0 };
+        init.queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+        let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Histogram Bind Group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(source) },
+                wgpu::BindGroupEntry { binding: 1, resource: self.params_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: self.buffer.as_entire_binding() },
+            ],
+        });
+        encoder.clear_buffer(&self.buffer, 0, None);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Histogram Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_pipeline(&self.count_pipeline);
+        pass.dispatch_workgroups(size.0.div_ceil(16), size.1.div_ceil(16), 1);
+        pass.set_pipeline(&self.max_pipeline);
+        pass.dispatch_workgroups(1, 1, 1);
+    }
+}
+
+/// Layout entry for reading `Histogram::buffer` in a fragment shader as `array<u32>`.
+pub fn histogram_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(HISTOGRAM_BUFFER_SIZE),
+        },
+        count: None,
+    }
+}
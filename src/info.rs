@@ -0,0 +1,256 @@
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use crate::helpers::{DEVICE_FEATURES, OPTIONAL_DEVICE_FEATURES};
+
+/// Formats in the support matrix: every uncompressed and depth format, plus the first block
+/// size of each compressed family.
+pub const REPORT_FORMATS: &[wgpu::TextureFormat] = {
+    use wgpu::TextureFormat::*;
+    &[
+        R8Unorm, R8Snorm, R8Uint, R8Sint, R16Uint, R16Sint, R16Unorm, R16Snorm, R16Float, Rg8Unorm, Rg8Snorm,
+        Rg8Uint, Rg8Sint, R32Uint, R32Sint, R32Float, Rg16Uint, Rg16Sint, Rg16Unorm, Rg16Snorm, Rg16Float,
+        Rgba8Unorm, Rgba8UnormSrgb, Rgba8Snorm, Rgba8Uint, Rgba8Sint, Bgra8Unorm, Bgra8UnormSrgb, Rgb9e5Ufloat,
+        Rgb10a2Uint, Rgb10a2Unorm, Rg11b10Ufloat, R64Uint, Rg32Uint, Rg32Sint, Rg32Float, Rgba16Uint, Rgba16Sint,
+        Rgba16Unorm, Rgba16Snorm, Rgba16Float, Rgba32Uint, Rgba32Sint, Rgba32Float, Stencil8, Depth16Unorm,
+        Depth24Plus, Depth24PlusStencil8, Depth32Float, Depth32FloatStencil8, NV12, Bc1RgbaUnorm, Bc4RUnorm,
+        Bc5RgUnorm, Bc6hRgbUfloat, Bc7RgbaUnorm, Etc2Rgb8Unorm, EacR11Unorm,
+        Astc { block: wgpu::AstcBlock::B4x4, channel: wgpu::AstcChannel::Unorm },
+    ]
+};
+
+/// Name and value of every limit; for names starting with `min_` smaller values are better.
+pub fn limit_values(limits: &wgpu::Limits) -> Vec<(&'static str, u64)> {
+    macro_rules! values {
+        ($($name:ident),* $(,)?) => {
+            vec![$((stringify!($name), limits.$name as u64)),*]
+        };
+    }
+    values!(
+        max_texture_dimension_1d,
+        max_texture_dimension_2d,
+        max_texture_dimension_3d,
+        max_texture_array_layers,
+        max_bind_groups,
+        max_bindings_per_bind_group,
+        max_dynamic_uniform_buffers_per_pipeline_layout,
+        max_dynamic_storage_buffers_per_pipeline_layout,
+        max_sampled_textures_per_shader_stage,
+        max_samplers_per_shader_stage,
+        max_storage_buffers_per_shader_stage,
+        max_storage_textures_per_shader_stage,
+        max_uniform_buffers_per_shader_stage,
+        max_uniform_buffer_binding_size,
+        max_storage_buffer_binding_size,
+        max_vertex_buffers,
+        max_buffer_size,
+        max_vertex_attributes,
+        max_vertex_buffer_array_stride,
+        min_uniform_buffer_offset_alignment,
+        min_storage_buffer_offset_alignment,
+        max_inter_stage_shader_components,
+        max_color_attachments,
+        max_color_attachment_bytes_per_sample,
+        max_compute_workgroup_storage_size,
+        max_compute_invocations_per_workgroup,
+        max_compute_workgroup_size_x,
+        max_compute_workgroup_size_y,
+        max_compute_workgroup_size_z,
+        max_compute_workgroups_per_dimension,
+        min_subgroup_size,
+        max_subgroup_size,
+        max_push_constant_size,
+        max_non_sampler_bindings,
+    )
+}
+
+/// Whether `value` of the limit `name` is at least as good as `baseline`.
+fn meets(name: &str, value: u64, baseline: u64) -> bool {
+    if name.starts_with("min_") { value <= baseline } else { value >= baseline }
+}
+
+/// What a texture format supports on an adapter.
+#[derive(Clone, Debug)]
+pub struct FormatSupport {
+    pub format: wgpu::TextureFormat,
+    pub filterable: bool,
+    pub blendable: bool,
+    pub render_attachment: bool,
+    pub storage: bool,
+    pub sample_counts: Vec<u32>,
+}
+
+impl FormatSupport {
+    pub fn new(adapter: &wgpu::Adapter, format: wgpu::TextureFormat) -> Self {
+        let features = adapter.get_texture_format_features(format);
+        let flags = features.flags;
+        Self {
+            format,
+            filterable: flags.contains(wgpu::TextureFormatFeatureFlags::FILTERABLE),
+            blendable: flags.contains(wgpu::TextureFormatFeatureFlags::BLENDABLE),
+            render_attachment: features.allowed_usages.contains(wgpu::TextureUsages::RENDER_ATTACHMENT),
+            storage: features.allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING),
+            sample_counts: flags.supported_sample_counts(),
+        }
+    }
+
+    /// False for formats the adapter cannot use at all, e.g. compressed formats without their
+    /// feature.
+    pub fn is_usable(&self) -> bool {
+        self.filterable || self.render_attachment || self.storage
+    }
+}
+
+/// Everything `wgpu_info` reports about one adapter, without creating a device.
+pub struct AdapterReport {
+    pub info: wgpu::AdapterInfo,
+    pub features: wgpu::Features,
+    pub limits: wgpu::Limits,
+    pub downlevel: wgpu::DownlevelCapabilities,
+    pub formats: Vec<FormatSupport>,
+}
+
+impl AdapterReport {
+    pub fn new(adapter: &wgpu::Adapter) -> Self {
+        Self {
+            info: adapter.get_info(),
+            features: adapter.features(),
+            limits: adapter.limits(),
+            downlevel: adapter.get_downlevel_capabilities(),
+            formats: REPORT_FORMATS.iter().map(|&format| FormatSupport::new(adapter, format)).collect(),
+        }
+    }
+
+    /// Reports of every adapter on `backends`.
+    pub fn enumerate(backends: wgpu::Backends) -> Vec<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor { backends, ..Default::default() });
+        instance.enumerate_adapters(backends).iter().map(Self::new).collect()
+    }
+
+    /// The adapter's features that `IWgpuInit` enables, see `DEVICE_FEATURES` and
+    /// `OPTIONAL_DEVICE_FEATURES`.
+    pub fn enabled_features(&self) -> wgpu::Features {
+        self.features & (DEVICE_FEATURES | OPTIONAL_DEVICE_FEATURES)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let info = &self.info;
+        let features: Vec<Value> = self
+            .features
+            .iter_names()
+            .map(|(name, feature)| json!({ "name": name, "enabled": self.enabled_features().contains(feature) }))
+            .collect();
+        let (default, downlevel) = (wgpu::Limits::default(), wgpu::Limits::downlevel_defaults());
+        let limits: Vec<Value> = limit_values(&self.limits)
+            .into_iter()
+            .zip(limit_values(&default).into_iter().zip(limit_values(&downlevel)))
+            .map(|((name, value), ((_, default), (_, downlevel)))| {
+                json!({
+                    "name": name,
+                    "value": value,
+                    "default": default,
+                    "downlevel_default": downlevel,
+                    "meets_default": meets(name, value, default),
+                    "meets_downlevel_default": meets(name, value, downlevel),
+                })
+            })
+            .collect();
+        let flags = self.downlevel.flags;
+        let formats: Vec<Value> = self
+            .formats
+            .iter()
+            .map(|f| {
+                json!({
+                    "format": format_name(f.format),
+                    "filterable": f.filterable,
+                    "blendable": f.blendable,
+                    "render_attachment": f.render_attachment,
+                    "storage": f.storage,
+                    "sample_counts": f.sample_counts,
+                })
+            })
+            .collect();
+        json!({
+            "name": info.name,
+            "vendor": info.vendor,
+            "device": info.device,
+            "device_type": format!("{:?}", info.device_type),
+            "driver": info.driver,
+            "driver_info": info.driver_info,
+            "backend": format!("{:?}", info.backend),
+            "features": features,
+            "limits": limits,
+            "downlevel": {
+                "webgpu_compliant": self.downlevel.is_webgpu_compliant(),
+                "shader_model": format!("{:?}", self.downlevel.shader_model),
+                "flags": flags.iter_names().map(|(name, _)| name).collect::<Vec<_>>(),
+                "missing_flags": (!flags).iter_names().map(|(name, _)| name).collect::<Vec<_>>(),
+            },
+            "formats": formats,
+        })
+    }
+}
+
+/// The `Debug` name, with ASTC formats shortened to e.g. `Astc4x4Unorm`.
+pub fn format_name(format: wgpu::TextureFormat) -> String {
+    match format {
+        wgpu::TextureFormat::Astc { block, channel } => {
+            format!("Astc{}{:?}", format!("{:?}", block).trim_start_matches('B'), channel)
+        }
+        format => format!("{:?}", format),
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "-" }
+}
+
+/// Human-readable tables.
+impl fmt::Display for AdapterReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let info = &self.info;
+        writeln!(f, "{} ({:?}, {:?})", info.name, info.backend, info.device_type)?;
+        writeln!(f, "  vendor:  {:#06x}   device: {:#06x}", info.vendor, info.device)?;
+        writeln!(f, "  driver:  {}", [info.driver.trim(), info.driver_info.trim()].join(" ").trim())?;
+
+        writeln!(f, "\nFeatures (* enabled by IWgpuInit):")?;
+        for (name, feature) in self.features.iter_names() {
+            let enabled = if self.enabled_features().contains(feature) { "*" } else { " " };
+            writeln!(f, "  {} {}", enabled, name)?;
+        }
+
+        writeln!(f, "\nLimits (< below the baseline):")?;
+        writeln!(f, "  {:<48} {:>12} {:>12} {:>12}", "limit", "value", "default", "downlevel")?;
+        let (default, downlevel) = (wgpu::Limits::default(), wgpu::Limits::downlevel_defaults());
+        let rows = limit_values(&self.limits).into_iter().zip(limit_values(&default).into_iter().zip(limit_values(&downlevel)));
+        for ((name, value), ((_, default), (_, downlevel))) in rows {
+            let mark = |baseline| if meets(name, value, baseline) { " " } else { "<" };
+            writeln!(f, "  {:<48} {:>12} {:>11}{} {:>11}{}", name, value, default, mark(default), downlevel, mark(downlevel))?;
+        }
+
+        let flags = self.downlevel.flags;
+        writeln!(f, "\nDownlevel:")?;
+        writeln!(f, "  WebGPU compliant: {}", yes_no(self.downlevel.is_webgpu_compliant()))?;
+        writeln!(f, "  shader model:     {:?}", self.downlevel.shader_model)?;
+        let missing: Vec<&str> = (!flags).iter_names().map(|(name, _)| name).collect();
+        writeln!(f, "  missing flags:    {}", if missing.is_empty() { "none".to_string() } else { missing.join(", ") })?;
+
+        writeln!(f, "\nFormats (unusable ones left out):")?;
+        writeln!(f, "  {:<24} {:>6} {:>6} {:>7} {:>8}  samples", "format", "filter", "blend", "render", "storage")?;
+        for s in self.formats.iter().filter(|s| s.is_usable()) {
+            let counts: Vec<String> = s.sample_counts.iter().map(u32::to_string).collect();
+            writeln!(
+                f,
+                "  {:<24} {:>6} {:>6} {:>7} {:>8}  {}",
+                format_name(s.format),
+                yes_no(s.filterable),
+                yes_no(s.blendable),
+                yes_no(s.render_attachment),
+                yes_no(s.storage),
+                counts.join(",")
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+use bytemuck::{Pod, Zeroable};
+use cgmath::Matrix4;
+
+use crate::helpers::IWgpuInit;
+use crate::mesh::Mesh;
+
+/// Per-instance data that can be fed to a vertex shader through a buffer stepped per instance.
+///
+/// Attribute locations must not overlap the ones used by the mesh vertex layout; the built-in
+/// `InstanceRaw` starts at location 4 so it combines with `geometry::Vertex` (locations 0 to 3).
+pub trait InstanceData: Pod {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute];
+
+    fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// General purpose instance: a model matrix, a color and four floats of user data.
+///
+/// ```wgsl
+/// struct InstanceInput {
+///     @location(4) model_0: vec4f,
+///     @location(5) model_1: vec4f,
+///     @location(6) model_2: vec4f,
+///     @location(7) model_3: vec4f,
+///     @location(8) color: vec4f,
+///     @location(9) data: vec4f,
+/// };
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+    pub color: [f32; 4],
+    pub data: [f32; 4],
+}
+
+impl InstanceRaw {
+    pub fn new(model: Matrix4<f32>, color: [f32; 4]) -> Self {
+        Self { model: model.into(), color, data: [0.0; 4] }
+    }
+}
+
+impl InstanceData for InstanceRaw {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+        4 => Float32x4, 5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32x4
+    ];
+}
+
+/// A typed, growable vertex buffer of instances.
+pub struct InstanceBuffer<T: InstanceData> {
+    pub buffer: wgpu::Buffer,
+    len: u32,
+    capacity: u32,
+    _marker: PhantomData<T>,
+}
+
+impl<T: InstanceData> InstanceBuffer<T> {
+    pub fn new(init: &IWgpuInit, instances: &[T]) -> Self {
+        let capacity = (instances.len() as u32).max(1);
+        let mut buffer = Self {
+            buffer: create_instance_buffer::<T>(init, capacity),
+            len: 0,
+            capacity,
+            _marker: PhantomData,
+        };
+        buffer.set(init, instances);
+        buffer
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Replaces every instance. The buffer is reallocated, to the next power of two, only when
+    /// `instances` does not fit; returns true in that case.
+    pub fn set(&mut self, init: &IWgpuInit, instances: &[T]) -> bool {
+        let len = instances.len() as u32;
+        let grown = grown_capacity(self.capacity, len);
+        if let Some(capacity) = grown {
+            self.capacity = capacity;
+            self.buffer = create_instance_buffer::<T>(init, capacity);
+        }
+        self.len = len;
+        if !instances.is_empty() {
+            init.queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(instances));
+        }
+        grown.is_some()
+    }
+
+    /// Overwrites `instances.len()` instances starting at `first`, leaving the others untouched.
+    pub fn write(&self, init: &IWgpuInit, first: u32, instances: &[T]) -> Result<()> {
+        let offset = write_offset::<T>(first, instances.len(), self.len)?;
+        if !instances.is_empty() {
+            init.queue.write_buffer(&self.buffer, offset, bytemuck::cast_slice(instances));
+        }
+        Ok(())
+    }
+
+    pub fn range(&self) -> Range<u32> {
+        0..self.len
+    }
+
+    /// Binds the instances to vertex buffer `slot`.
+    pub fn bind(&self, render_pass: &mut wgpu::RenderPass, slot: u32) {
+        let size = self.len.max(1) as u64 * std::mem::size_of::<T>() as u64;
+        render_pass.set_vertex_buffer(slot, self.buffer.slice(..size));
+    }
+
+    /// Draws every instance of `mesh`, with the mesh in vertex buffer slot 0 and the instances in slot 1.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, mesh: &Mesh) {
+        if self.is_empty() {
+            return;
+        }
+        self.bind(render_pass, 1);
+        mesh.draw_instanced(render_pass, self.range());
+    }
+}
+
+/// The capacity to reallocate to for `len` instances, or `None` if they fit in `capacity`.
+fn grown_capacity(capacity: u32, len: u32) -> Option<u32> {
+    (len > capacity).then(|| len.checked_next_power_of_two().unwrap_or(len))
+}
+
+/// Byte offset of instance `first`, if `count` instances from there fit in the `len` in use.
+fn write_offset<T>(first: u32, count: usize, len: u32) -> Result<u64> {
+    let end = (first as u64).saturating_add(count as u64);
+    if end > len as u64 {
+        bail!("instances {}..{} are out of range, the buffer holds {}", first, end, len);
+    }
+    Ok(first as u64 * std::mem::size_of::<T>() as u64)
+}
+
+fn create_instance_buffer<T: InstanceData>(init: &IWgpuInit, capacity: u32) -> wgpu::Buffer {
+    init.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Instance Buffer"),
+        size: capacity as u64 * std::mem::size_of::<T>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_to_the_next_power_of_two() {
+        assert_eq!(grown_capacity(1, 0), None);
+        assert_eq!(grown_capacity(1, 1), None);
+        assert_eq!(grown_capacity(1, 2), Some(2));
+        assert_eq!(grown_capacity(4, 5), Some(8));
+        assert_eq!(grown_capacity(8, 1000), Some(1024));
+        assert_eq!(grown_capacity(1024, 1024), None);
+        assert_eq!(grown_capacity(1, u32::MAX), Some(u32::MAX));
+    }
+
+    #[test]
+    fn grows_from_empty() {
+        // `new` with no instances still allocates one.
+        assert_eq!(grown_capacity(0, 0), None);
+        assert_eq!(grown_capacity(0, 1), Some(1));
+        assert_eq!(grown_capacity(0, 3), Some(4));
+    }
+
+    #[test]
+    fn shrinking_keeps_the_buffer() {
+        assert_eq!(grown_capacity(16, 3), None);
+        assert_eq!(grown_capacity(16, 0), None);
+    }
+
+    #[test]
+    fn write_offsets() {
+        let size = std::mem::size_of::<InstanceRaw>() as u64;
+        assert_eq!(write_offset::<InstanceRaw>(0, 4, 4).unwrap(), 0);
+        assert_eq!(write_offset::<InstanceRaw>(3, 1, 4).unwrap(), 3 * size);
+        assert_eq!(write_offset::<InstanceRaw>(4, 0, 4).unwrap(), 4 * size);
+    }
+
+    #[test]
+    fn write_past_len_fails() {
+        let e = write_offset::<InstanceRaw>(3, 2, 4).unwrap_err();
+        assert_eq!(e.to_string(), "instances 3..5 are out of range, the buffer holds 4");
+        assert!(write_offset::<InstanceRaw>(5, 0, 4).is_err());
+        assert!(write_offset::<InstanceRaw>(0, 1, 0).is_err());
+        assert!(write_offset::<InstanceRaw>(u32::MAX, usize::MAX, u32::MAX).is_err());
+    }
+}
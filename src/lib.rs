@@ -1 +1,26 @@
-pub mod helpers;
\ No newline at end of file
+pub mod app;
+pub mod array;
+pub mod blend;
+pub mod camera;
+pub mod capture;
+pub mod colormap;
+pub mod compare;
+pub mod controller;
+pub mod depth;
+pub mod filter;
+pub mod fullscreen;
+pub mod geometry;
+pub mod helpers;
+pub mod histogram;
+pub mod info;
+pub mod instance;
+pub mod light;
+pub mod mesh;
+pub mod model;
+pub mod oit;
+pub mod postprocess;
+pub mod record;
+pub mod render_target;
+pub mod shadow;
+pub mod transform;
+pub mod transparency;
@@ -0,0 +1,530 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{InnerSpace, Point3, Rad, Vector3};
+use image::{DynamicImage, Rgba, RgbaImage};
+use wgpu::util::DeviceExt;
+
+use crate::camera::Camera;
+use crate::geometry::Vertex;
+use crate::helpers::{self as ws, IWgpuInit};
+use crate::model::{Model, Texture};
+use crate::render_target::RenderTarget;
+use crate::shadow::{ShadowAssignment, ShadowMaps, ShadowSettings};
+use crate::transform::create_transform_bind_group_layout;
+
+/// WGSL shared by the lit shaders: camera at group 0, lights and shadow maps at group 1, transform at group 3
+/// and a vertex stage taking `geometry::Vertex`. Material shaders append their group 2 bindings
+/// and `fs_main`.
+pub const LIT_COMMON_WGSL: &str = include_str!("shaders/lit_common.wgsl");
+pub const BLINN_PHONG_WGSL: &str = include_str!("shaders/blinn_phong.wgsl");
+pub const PBR_WGSL: &str = include_str!("shaders/pbr.wgsl");
+
+#[derive(Clone, Copy, Debug)]
+pub enum Light {
+    Directional {
+        /// Direction the light travels in.
+        direction: Vector3<f32>,
+        color: [f32; 3],
+        intensity: f32,
+    },
+    Point {
+        position: Point3<f32>,
+        color: [f32; 3],
+        intensity: f32,
+        /// Distance at which the light fades out completely; 0 means unlimited.
+        range: f32,
+    },
+    Spot {
+        position: Point3<f32>,
+        direction: Vector3<f32>,
+        color: [f32; 3],
+        intensity: f32,
+        range: f32,
+        inner_angle: Rad<f32>,
+        outer_angle: Rad<f32>,
+    },
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct GpuLight {
+    pub position: [f32; 4],
+    pub direction: [f32; 4],
+    pub color: [f32; 4],
+    pub cone: [f32; 4],
+    /// x first shadow map layer, y layer count (0 = no shadow).
+    pub shadow: [f32; 4],
+    /// View-space far distance of each directional cascade.
+    pub splits: [f32; 4],
+}
+
+impl GpuLight {
+    fn with_shadow(light: &Light, shadow: &ShadowAssignment) -> Self {
+        Self {
+            shadow: [shadow.first_layer as f32, shadow.layer_count as f32, 0.0, 0.0],
+            splits: shadow.splits,
+            ..Self::from(light)
+        }
+    }
+}
+
+impl From<&Light> for GpuLight {
+    fn from(light: &Light) -> Self {
+        let scaled = |c: [f32; 3], i: f32| [c[0] * i, c[1] * i, c[2] * i, 0.0];
+        match *light {
+            Light::Directional { direction, color, intensity } => {
+                let d = direction.normalize();
+                Self {
+                    position: [0.0, 0.0, 0.0, 0.0],
+                    direction: [d.x, d.y, d.z, 0.0],
+                    color: scaled(color, intensity),
+                    cone: [0.0; 4],
+                    shadow: [0.0; 4],
+                    splits: [0.0; 4],
+                }
+            }
+            Light::Point { position, color, intensity, range } => Self {
+                position: [position.x, position.y, position.z, 1.0],
+                direction: [0.0, -1.0, 0.0, range],
+                color: scaled(color, intensity),
+                cone: [0.0; 4],
+                shadow: [0.0; 4],
+                splits: [0.0; 4],
+            },
+            Light::Spot { position, direction, color, intensity, range, inner_angle, outer_angle } => {
+                let d = direction.normalize();
+                Self {
+                    position: [position.x, position.y, position.z, 2.0],
+                    direction: [d.x, d.y, d.z, range],
+                    color: scaled(color, intensity),
+                    cone: [inner_angle.0.cos(), outer_angle.0.cos(), 0.0, 0.0],
+                    shadow: [0.0; 4],
+                    splits: [0.0; 4],
+                }
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+struct LightsHeader {
+    ambient: [f32; 4],
+    count: [u32; 4],
+}
+
+pub struct PhongMaterial {
+    pub diffuse: [f32; 4],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+}
+
+impl Default for PhongMaterial {
+    fn default() -> Self {
+        Self { diffuse: [0.8, 0.8, 0.8, 1.0], specular: [0.5; 3], shininess: 32.0 }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct PhongMaterialUniform {
+    diffuse: [f32; 4],
+    specular: [f32; 4],
+}
+
+pub struct PbrMaterial {
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: [0.0; 3],
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct PbrMaterialUniform {
+    base_color: [f32; 4],
+    emissive: [f32; 4],
+    params: [f32; 4],
+}
+
+/// Optional PBR texture maps; missing ones fall back to neutral 1x1 textures.
+#[derive(Default)]
+pub struct PbrMaps<'a> {
+    pub base_color: Option<&'a Texture>,
+    pub normal: Option<&'a Texture>,
+    pub metallic_roughness: Option<&'a Texture>,
+    pub occlusion: Option<&'a Texture>,
+    pub emissive: Option<&'a Texture>,
+}
+
+pub struct MaterialBinding {
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// Bind group layouts, fallback textures and pipelines shared by every lit object.
+pub struct Lighting {
+    pub lights_layout: wgpu::BindGroupLayout,
+    pub phong_layout: wgpu::BindGroupLayout,
+    pub pbr_layout: wgpu::BindGroupLayout,
+    pub transform_layout: wgpu::BindGroupLayout,
+    white: Texture,
+    flat_normal: Texture,
+}
+
+impl Lighting {
+    pub fn new(init: &IWgpuInit) -> Self {
+        let lights_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Lights Bind Group Layout"),
+            entries: &[
+                storage_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                storage_entry(3),
+            ],
+        });
+
+        let phong_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Phong Material Bind Group Layout"),
+            entries: &[uniform_entry(0), texture_entry(1), sampler_entry(2)],
+        });
+
+        let mut pbr_entries = vec![uniform_entry(0)];
+        pbr_entries.extend((1..=5).map(texture_entry));
+        pbr_entries.push(sampler_entry(6));
+        let pbr_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("PBR Material Bind Group Layout"),
+            entries: &pbr_entries,
+        });
+
+        Self {
+            lights_layout,
+            phong_layout,
+            pbr_layout,
+            transform_layout: create_transform_bind_group_layout(init),
+            white: solid_texture(init, [255, 255, 255, 255], wgpu::TextureFormat::Rgba8UnormSrgb),
+            flat_normal: solid_texture(init, [128, 128, 255, 255], wgpu::TextureFormat::Rgba8Unorm),
+        }
+    }
+
+    pub fn create_phong_material(&self, init: &IWgpuInit, material: &PhongMaterial, diffuse: Option<&Texture>) -> MaterialBinding {
+        let uniform = PhongMaterialUniform {
+            diffuse: material.diffuse,
+            specular: [material.specular[0], material.specular[1], material.specular[2], material.shininess],
+        };
+        let buffer = create_uniform_buffer(init, bytemuck::bytes_of(&uniform), "Phong Material Buffer");
+        let diffuse = diffuse.unwrap_or(&self.white);
+        let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Phong Material Bind Group"),
+            layout: &self.phong_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&diffuse.view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&diffuse.sampler) },
+            ],
+        });
+        MaterialBinding { buffer, bind_group }
+    }
+
+    pub fn create_pbr_material(&self, init: &IWgpuInit, material: &PbrMaterial, maps: &PbrMaps) -> MaterialBinding {
+        let uniform = PbrMaterialUniform {
+            base_color: material.base_color,
+            emissive: [material.emissive[0], material.emissive[1], material.emissive[2], 0.0],
+            params: [material.metallic, material.roughness, material.occlusion_strength, material.normal_scale],
+        };
+        let buffer = create_uniform_buffer(init, bytemuck::bytes_of(&uniform), "PBR Material Buffer");
+        let textures = [
+            maps.base_color.unwrap_or(&self.white),
+            maps.normal.unwrap_or(&self.flat_normal),
+            maps.metallic_roughness.unwrap_or(&self.white),
+            maps.occlusion.unwrap_or(&self.white),
+            maps.emissive.unwrap_or(&self.white),
+        ];
+        // A single sampler serves all maps; take the base color's so its wrap mode wins.
+        let sampler = &textures[0].sampler;
+        let mut entries = vec![wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }];
+        for (i, texture) in textures.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: i as u32 + 1,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+        }
+        entries.push(wgpu::BindGroupEntry { binding: 6, resource: wgpu::BindingResource::Sampler(sampler) });
+        let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("PBR Material Bind Group"),
+            layout: &self.pbr_layout,
+            entries: &entries,
+        });
+        MaterialBinding { buffer, bind_group }
+    }
+
+    /// PBR material for `model.materials[index]`, or a plain white one for `None`.
+    pub fn create_model_material(&self, init: &IWgpuInit, model: &Model, index: Option<usize>) -> MaterialBinding {
+        let Some(m) = index.and_then(|i| model.materials.get(i)) else {
+            return self.create_pbr_material(init, &PbrMaterial::default(), &PbrMaps::default());
+        };
+        let texture = |i: Option<usize>| i.and_then(|i| model.textures.get(i));
+        let material = PbrMaterial {
+            base_color: m.base_color_factor,
+            metallic: m.metallic_factor,
+            roughness: m.roughness_factor,
+            emissive: m.emissive_factor,
+            ..Default::default()
+        };
+        let maps = PbrMaps {
+            base_color: texture(m.base_color_texture),
+            normal: texture(m.normal_texture),
+            metallic_roughness: texture(m.metallic_roughness_texture),
+            occlusion: texture(m.occlusion_texture),
+            emissive: texture(m.emissive_texture),
+        };
+        self.create_pbr_material(init, &material, &maps)
+    }
+
+    pub fn create_phong_pipeline(&self, init: &IWgpuInit, camera_layout: &wgpu::BindGroupLayout) -> wgpu::RenderPipeline {
+        self.create_pipeline(init, camera_layout, Shading::Phong, Some(wgpu::Face::Back), None)
+    }
+
+    pub fn create_pbr_pipeline(&self, init: &IWgpuInit, camera_layout: &wgpu::BindGroupLayout) -> wgpu::RenderPipeline {
+        self.create_pipeline(init, camera_layout, Shading::Pbr, Some(wgpu::Face::Back), None)
+    }
+
+    /// Like `create_pbr_pipeline` without back-face culling, for model materials marked
+    /// `double_sided`. Back faces are shaded with the flipped normal.
+    pub fn create_double_sided_pbr_pipeline(&self, init: &IWgpuInit, camera_layout: &wgpu::BindGroupLayout) -> wgpu::RenderPipeline {
+        self.create_pipeline(init, camera_layout, Shading::Pbr, None, None)
+    }
+
+    /// Like `create_phong_pipeline`, for drawing into an offscreen `RenderTarget`.
+    pub fn create_phong_pipeline_for_target(&self, init: &IWgpuInit, camera_layout: &wgpu::BindGroupLayout,
+    target: &RenderTarget) -> wgpu::RenderPipeline {
+        self.create_pipeline(init, camera_layout, Shading::Phong, Some(wgpu::Face::Back), Some(target))
+    }
+
+    /// Like `create_pbr_pipeline`, for drawing into an offscreen `RenderTarget`.
+    pub fn create_pbr_pipeline_for_target(&self, init: &IWgpuInit, camera_layout: &wgpu::BindGroupLayout,
+    target: &RenderTarget) -> wgpu::RenderPipeline {
+        self.create_pipeline(init, camera_layout, Shading::Pbr, Some(wgpu::Face::Back), Some(target))
+    }
+
+    fn create_pipeline(&self, init: &IWgpuInit, camera_layout: &wgpu::BindGroupLayout, shading: Shading,
+    cull_mode: Option<wgpu::Face>, target: Option<&RenderTarget>) -> wgpu::RenderPipeline {
+        let (material_layout, fragment, label) = match shading {
+            Shading::Phong => (&self.phong_layout, BLINN_PHONG_WGSL, "Blinn-Phong"),
+            Shading::Pbr => (&self.pbr_layout, PBR_WGSL, "PBR"),
+        };
+        let shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!("{}\n{}", LIT_COMMON_WGSL, fragment))),
+        });
+        let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &[camera_layout, &self.lights_layout, material_layout, &self.transform_layout],
+            push_constant_ranges: &[],
+        });
+        let mut ppl = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &[Vertex::vertex_buffer_layout()],
+            cull_mode,
+            color_formats: target.map_or(&[], |t| t.color_formats()),
+            sample_count: target.map(|t| t.sample_count),
+            ..Default::default()
+        };
+        ppl.new(init)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Shading {
+    Phong,
+    Pbr,
+}
+
+/// The scene's lights, mirrored into a storage buffer bound at group 1 together with their
+/// shadow maps. Shadows start disabled; turn them on with `set_shadow_settings`.
+pub struct Lights {
+    pub lights: Vec<Light>,
+    pub ambient: [f32; 3],
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub shadows: ShadowMaps,
+    shadow_assignments: Vec<ShadowAssignment>,
+    capacity: usize,
+}
+
+impl Lights {
+    pub fn new(init: &IWgpuInit, lighting: &Lighting, lights: Vec<Light>, ambient: [f32; 3]) -> Self {
+        let capacity = lights.len().max(1);
+        let buffer = create_lights_buffer(init, capacity);
+        let shadows = ShadowMaps::new(init, lighting, ShadowSettings::disabled());
+        let bind_group = create_lights_bind_group(init, lighting, &buffer, &shadows);
+        let lights = Self { lights, ambient, buffer, bind_group, shadows, shadow_assignments: Vec::new(), capacity };
+        lights.write(init);
+        lights
+    }
+
+    /// Recreates the shadow maps; call `update` afterwards to fit them to the camera.
+    pub fn set_shadow_settings(&mut self, init: &IWgpuInit, lighting: &Lighting, settings: ShadowSettings) {
+        self.shadows = ShadowMaps::new(init, lighting, settings);
+        self.bind_group = create_lights_bind_group(init, lighting, &self.buffer, &self.shadows);
+    }
+
+    /// Uploads `lights` and `ambient`, growing the buffer (and recreating the bind group) if needed.
+    /// Shadow views are refitted to `camera`, so call this whenever the camera or a light moves.
+    pub fn update(&mut self, init: &IWgpuInit, lighting: &Lighting, camera: &Camera) {
+        if self.lights.len() > self.capacity {
+            self.capacity = self.lights.len().next_power_of_two();
+            self.buffer = create_lights_buffer(init, self.capacity);
+            self.bind_group = create_lights_bind_group(init, lighting, &self.buffer, &self.shadows);
+        }
+        self.shadow_assignments = self.shadows.update(init, &self.lights, camera);
+        self.write(init);
+    }
+
+    /// Renders every shadow map; see `ShadowMaps::render`. Must be encoded before the lit pass.
+    pub fn render_shadows(&self, encoder: &mut wgpu::CommandEncoder, draw: &mut dyn FnMut(&mut wgpu::RenderPass)) {
+        self.shadows.render(encoder, draw);
+    }
+
+    fn write(&self, init: &IWgpuInit) {
+        let header = LightsHeader {
+            ambient: [self.ambient[0], self.ambient[1], self.ambient[2], 0.0],
+            count: [self.lights.len() as u32, 0, 0, 0],
+        };
+        let unshadowed = ShadowAssignment::default();
+        let items: Vec<GpuLight> = self
+            .lights
+            .iter()
+            .enumerate()
+            .map(|(i, light)| GpuLight::with_shadow(light, self.shadow_assignments.get(i).unwrap_or(&unshadowed)))
+            .collect();
+        init.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&header));
+        if !items.is_empty() {
+            init.queue.write_buffer(&self.buffer, std::mem::size_of::<LightsHeader>() as u64, bytemuck::cast_slice(&items));
+        }
+    }
+}
+
+fn create_lights_buffer(init: &IWgpuInit, capacity: usize) -> wgpu::Buffer {
+    let size = std::mem::size_of::<LightsHeader>() + capacity * std::mem::size_of::<GpuLight>();
+    init.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Lights Storage Buffer"),
+        size: size as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_lights_bind_group(init: &IWgpuInit, lighting: &Lighting, buffer: &wgpu::Buffer, shadows: &ShadowMaps) -> wgpu::BindGroup {
+    init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Lights Bind Group"),
+        layout: &lighting.lights_layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&shadows.array_view) },
+            wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&shadows.sampler) },
+            wgpu::BindGroupEntry { binding: 3, resource: shadows.casters_buffer.as_entire_binding() },
+        ],
+    })
+}
+
+fn create_uniform_buffer(init: &IWgpuInit, contents: &[u8], label: &str) -> wgpu::Buffer {
+    init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn solid_texture(init: &IWgpuInit, rgba: [u8; 4], format: wgpu::TextureFormat) -> Texture {
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba(rgba)));
+    Texture {
+        view: ws::create_image_texture_view_with_format(init, &image, format),
+        sampler: init.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        }),
+    }
+}
+
+fn storage_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
@@ -0,0 +1,119 @@
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+use bytemuck::{cast_slice, Pod};
+use wgpu::util::DeviceExt;
+
+use crate::helpers::{IRenderPipeline, IWgpuInit};
+
+pub struct Mesh {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_vertices: u32,
+    pub num_indices: u32,
+    pub topology: wgpu::PrimitiveTopology,
+    pub index_format: wgpu::IndexFormat,
+}
+
+impl Mesh {
+    /// Panics when `indices` are out of range, see `try_new`; for geometry built in code.
+    pub fn new<V: Pod>(init: &IWgpuInit, vertices: &[V], indices: &[u32], topology: wgpu::PrimitiveTopology) -> Self {
+        Self::try_new(init, vertices, indices, topology).expect("invalid mesh")
+    }
+
+    /// Fails unless every index addresses one of `vertices`; strip topologies may also use
+    /// `u32::MAX` to restart the strip.
+    pub fn try_new<V: Pod>(init: &IWgpuInit, vertices: &[V], indices: &[u32], topology: wgpu::PrimitiveTopology)
+    -> Result<Self> {
+        check_indices(indices, vertices.len(), topology)?;
+        let num_vertices = vertices.len() as u32;
+        let index_format = index_format_for(num_vertices);
+
+        let vertex_buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Vertex Buffer"),
+            contents: cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // create_buffer_init pads the contents to COPY_BUFFER_ALIGNMENT, so an odd number
+        // of u16 indices is fine here.
+        let index_buffer = match index_format {
+            wgpu::IndexFormat::Uint16 => {
+                // Keeps the restart value a restart value.
+                let indices_u16: Vec<u16> = indices.iter().map(|&i| if i == u32::MAX { u16::MAX } else { i as u16 }).collect();
+                create_index_buffer(init, cast_slice(&indices_u16))
+            }
+            wgpu::IndexFormat::Uint32 => create_index_buffer(init, cast_slice(indices)),
+        };
+
+        Ok(Self {
+            vertex_buffer,
+            index_buffer,
+            num_vertices,
+            num_indices: indices.len() as u32,
+            topology,
+            index_format,
+        })
+    }
+
+    /// The value `IRenderPipeline::strip_index_format` must have to draw this mesh.
+    pub fn strip_index_format(&self) -> Option<wgpu::IndexFormat> {
+        if self.topology.is_strip() {
+            Some(self.index_format)
+        } else {
+            None
+        }
+    }
+
+    pub fn validate(&self, ppl: &IRenderPipeline) -> Result<()> {
+        if ppl.topology != self.topology {
+            bail!("mesh topology {:?} does not match pipeline topology {:?}", self.topology, ppl.topology);
+        }
+        if ppl.strip_index_format != self.strip_index_format() {
+            bail!(
+                "pipeline strip_index_format {:?} does not match mesh index format, expected {:?}",
+                ppl.strip_index_format,
+                self.strip_index_format()
+            );
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        self.draw_instanced(render_pass, 0..1);
+    }
+
+    /// Draws `instances` copies of the mesh; per-instance buffers must already be bound to the
+    /// other vertex buffer slots, see `instance::InstanceBuffer::draw`.
+    pub fn draw_instanced(&self, render_pass: &mut wgpu::RenderPass, instances: Range<u32>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+        render_pass.draw_indexed(0..self.num_indices, 0, instances);
+    }
+}
+
+/// Uint16 is used when every vertex can be addressed without touching 0xFFFF, which is
+/// reserved as the primitive restart value for strip topologies.
+pub fn index_format_for(num_vertices: u32) -> wgpu::IndexFormat {
+    if num_vertices <= u16::MAX as u32 {
+        wgpu::IndexFormat::Uint16
+    } else {
+        wgpu::IndexFormat::Uint32
+    }
+}
+
+fn check_indices(indices: &[u32], num_vertices: usize, topology: wgpu::PrimitiveTopology) -> Result<()> {
+    let restart = |i: u32| topology.is_strip() && i == u32::MAX;
+    if let Some((position, &i)) = indices.iter().enumerate().find(|&(_, &i)| i as usize >= num_vertices && !restart(i)) {
+        bail!("index {} at position {} is out of range for {} vertices", i, position, num_vertices);
+    }
+    Ok(())
+}
+
+fn create_index_buffer(init: &IWgpuInit, contents: &[u8]) -> wgpu::Buffer {
+    init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Mesh Index Buffer"),
+        contents,
+        usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+    })
+}
@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use image::DynamicImage;
+
+use crate::geometry::{Geometry, Vertex};
+use crate::helpers::{self as ws, IWgpuInit};
+use crate::mesh::Mesh;
+
+pub struct Texture {
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+/// Metallic-roughness material. OBJ materials are mapped onto it (Kd -> base color,
+/// Ns -> roughness) and also keep their Blinn-Phong specular terms.
+pub struct Material {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub emissive_factor: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    pub double_sided: bool,
+    /// Indices into `Model::textures`.
+    pub base_color_texture: Option<usize>,
+    pub normal_texture: Option<usize>,
+    pub metallic_roughness_texture: Option<usize>,
+    pub occlusion_texture: Option<usize>,
+    pub emissive_texture: Option<usize>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            base_color_factor: [1.0; 4],
+            metallic_factor: 0.0,
+            roughness_factor: 1.0,
+            emissive_factor: [0.0; 3],
+            specular: [0.5; 3],
+            shininess: 32.0,
+            double_sided: false,
+            base_color_texture: None,
+            normal_texture: None,
+            metallic_roughness_texture: None,
+            occlusion_texture: None,
+            emissive_texture: None,
+        }
+    }
+}
+
+pub struct ModelMesh {
+    pub name: String,
+    pub mesh: Mesh,
+    /// Index into `Model::materials`.
+    pub material: Option<usize>,
+}
+
+pub struct Node {
+    pub name: String,
+    /// Transform relative to the parent node.
+    pub transform: Matrix4<f32>,
+    pub children: Vec<usize>,
+    /// Indices into `Model::meshes`; a glTF mesh with several primitives yields several entries.
+    pub meshes: Vec<usize>,
+    pub camera: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Projection {
+    Perspective { yfov: f32, aspect_ratio: Option<f32>, znear: f32, zfar: Option<f32> },
+    Orthographic { xmag: f32, ymag: f32, znear: f32, zfar: f32 },
+}
+
+pub struct ModelCamera {
+    pub name: String,
+    pub projection: Projection,
+}
+
+#[derive(Default)]
+pub struct Model {
+    pub meshes: Vec<ModelMesh>,
+    pub materials: Vec<Material>,
+    pub textures: Vec<Texture>,
+    pub nodes: Vec<Node>,
+    pub roots: Vec<usize>,
+    pub cameras: Vec<ModelCamera>,
+}
+
+impl Model {
+    /// Loads `.obj`, `.gltf` or `.glb` depending on the file extension.
+    pub fn load(init: &IWgpuInit, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("obj") => Self::load_obj(init, path),
+            Some("gltf") | Some("glb") => Self::load_gltf(init, path),
+            _ => bail!("{}: unsupported model format", path.display()),
+        }
+    }
+
+    pub fn load_obj(init: &IWgpuInit, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        load_obj(init, path).with_context(|| format!("failed to load OBJ {}", path.display()))
+    }
+
+    pub fn load_gltf(init: &IWgpuInit, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        load_gltf(init, path).with_context(|| format!("failed to load glTF {}", path.display()))
+    }
+
+    /// World-space transform of every node, indexed like `nodes`. Nodes are visited once, so a
+    /// hierarchy that is not a forest cannot loop.
+    pub fn world_transforms(&self) -> Vec<Matrix4<f32>> {
+        let mut world = vec![Matrix4::identity(); self.nodes.len()];
+        let mut visited = vec![false; self.nodes.len()];
+        let mut stack: Vec<(usize, Matrix4<f32>)> = self.roots.iter().map(|&r| (r, Matrix4::identity())).collect();
+        while let Some((index, parent)) = stack.pop() {
+            if std::mem::replace(&mut visited[index], true) {
+                continue;
+            }
+            let node = &self.nodes[index];
+            world[index] = parent * node.transform;
+            stack.extend(node.children.iter().map(|&c| (c, world[index])));
+        }
+        world
+    }
+}
+
+fn load_obj(init: &IWgpuInit, path: &Path) -> Result<Model> {
+    let (models, materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let mut model = Model::default();
+
+    // A missing or broken .mtl only costs us the materials, not the geometry.
+    let materials = materials.unwrap_or_else(|e| {
+        log::warn!("{}: could not load materials: {}", path.display(), e);
+        Vec::new()
+    });
+    let mut texture_cache: HashMap<(PathBuf, bool), Option<usize>> = HashMap::new();
+    for m in &materials {
+        let mut texture = |name: &Option<String>, srgb: bool| -> Option<usize> {
+            let file = base_dir.join(name.as_ref()?);
+            *texture_cache.entry((file.clone(), srgb)).or_insert_with(|| {
+                match image::open(&file) {
+                    Ok(img) => {
+                        model.textures.push(create_texture(init, &img, srgb, repeat_sampler(init)));
+                        Some(model.textures.len() - 1)
+                    }
+                    Err(e) => {
+                        log::warn!("{}: could not load texture {}: {}", path.display(), file.display(), e);
+                        None
+                    }
+                }
+            })
+        };
+        let diffuse = m.diffuse.unwrap_or([0.8; 3]);
+        let shininess = m.shininess.unwrap_or(32.0);
+        let base_color_texture = texture(&m.diffuse_texture, true);
+        let normal_texture = texture(&m.normal_texture, false);
+        model.materials.push(Material {
+            name: m.name.clone(),
+            base_color_factor: [diffuse[0], diffuse[1], diffuse[2], m.dissolve.unwrap_or(1.0)],
+            roughness_factor: (2.0 / (shininess + 2.0)).sqrt(),
+            emissive_factor: m.emissive.unwrap_or([0.0; 3]),
+            specular: m.specular.unwrap_or([0.5; 3]),
+            shininess,
+            base_color_texture,
+            normal_texture,
+            ..Default::default()
+        });
+    }
+
+    for m in models {
+        let mesh = m.mesh;
+        let positions: Vec<[f32; 3]> = mesh.positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]).collect();
+        let normals = (!mesh.normals.is_empty())
+            .then(|| mesh.normals.chunks_exact(3).map(|n| [n[0], n[1], n[2]]).collect());
+        // OBJ puts the texture origin at the bottom left, wgpu at the top left.
+        let uvs = (!mesh.texcoords.is_empty())
+            .then(|| mesh.texcoords.chunks_exact(2).map(|t| [t[0], 1.0 - t[1]]).collect());
+        let geometry = build_geometry(positions, normals, uvs, None, mesh.indices)
+            .with_context(|| format!("model {}", m.name))?;
+
+        model.nodes.push(Node {
+            name: m.name.clone(),
+            transform: Matrix4::identity(),
+            children: Vec::new(),
+            meshes: vec![model.meshes.len()],
+            camera: None,
+        });
+        model.roots.push(model.nodes.len() - 1);
+        model.meshes.push(ModelMesh {
+            name: m.name,
+            mesh: geometry.to_mesh(init),
+            material: mesh.material_id.filter(|&i| i < model.materials.len()),
+        });
+    }
+    Ok(model)
+}
+
+fn load_gltf(init: &IWgpuInit, path: &Path) -> Result<Model> {
+    let bytes = std::fs::read(path)?;
+    let gltf = gltf::Gltf::from_slice(&bytes)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let document = &gltf.document;
+
+    let buffers = document
+        .buffers()
+        .map(|buffer| {
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => gltf.blob.clone().ok_or_else(|| anyhow!("missing GLB binary chunk"))?,
+                gltf::buffer::Source::Uri(uri) => read_uri(base_dir, uri)?,
+            };
+            if data.len() < buffer.length() {
+                bail!("buffer {} is {} bytes, expected {}", buffer.index(), data.len(), buffer.length());
+            }
+            Ok(data)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for view in document.views() {
+        let end = view.offset().checked_add(view.length());
+        if end.is_none_or(|end| end > buffers[view.buffer().index()].len()) {
+            bail!("buffer view {} is out of range of buffer {}", view.index(), view.buffer().index());
+        }
+    }
+
+    let mut model = Model::default();
+    let mut images: HashMap<usize, Option<DynamicImage>> = HashMap::new();
+    let mut texture_cache: HashMap<(usize, bool), Option<usize>> = HashMap::new();
+    let mut texture = |model: &mut Model, texture: gltf::Texture, srgb: bool| -> Option<usize> {
+        *texture_cache.entry((texture.index(), srgb)).or_insert_with(|| {
+            let source = texture.source();
+            let image = images.entry(source.index()).or_insert_with(|| {
+                let decoded = match source.source() {
+                    gltf::image::Source::View { view, .. } => buffers
+                        .get(view.buffer().index())
+                        .and_then(|data| data.get(view.offset()..view.offset().checked_add(view.length())?))
+                        .ok_or_else(|| anyhow!("buffer view {} is out of range", view.index()))
+                        .and_then(|data| image::load_from_memory(data).map_err(anyhow::Error::from)),
+                    gltf::image::Source::Uri { uri, .. } => read_uri(base_dir, uri)
+                        .and_then(|data| image::load_from_memory(&data).map_err(anyhow::Error::from)),
+                };
+                decoded
+                    .map_err(|e| log::warn!("{}: could not load image {}: {}", path.display(), source.index(), e))
+                    .ok()
+            });
+            let sampler = create_gltf_sampler(init, &texture.sampler());
+            image.as_ref().map(|img| {
+                model.textures.push(create_texture(init, img, srgb, sampler));
+                model.textures.len() - 1
+            })
+        })
+    };
+
+    for m in document.materials() {
+        let pbr = m.pbr_metallic_roughness();
+        let material = Material {
+            name: m.name().unwrap_or_default().to_string(),
+            base_color_factor: pbr.base_color_factor(),
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            emissive_factor: m.emissive_factor(),
+            double_sided: m.double_sided(),
+            base_color_texture: pbr.base_color_texture().and_then(|t| texture(&mut model, t.texture(), true)),
+            metallic_roughness_texture: pbr
+                .metallic_roughness_texture()
+                .and_then(|t| texture(&mut model, t.texture(), false)),
+            normal_texture: m.normal_texture().and_then(|t| texture(&mut model, t.texture(), false)),
+            occlusion_texture: m.occlusion_texture().and_then(|t| texture(&mut model, t.texture(), false)),
+            emissive_texture: m.emissive_texture().and_then(|t| texture(&mut model, t.texture(), true)),
+            ..Default::default()
+        };
+        model.materials.push(material);
+    }
+
+    let mut mesh_primitives: Vec<Vec<usize>> = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|b| buffers.get(b.index()).map(|d| d.as_slice()));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| anyhow!("mesh {} has a primitive without positions", mesh.index()))?
+                .collect();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            let indices = match primitive.mode() {
+                gltf::mesh::Mode::Triangles => indices,
+                gltf::mesh::Mode::TriangleStrip => strip_to_list(&indices),
+                gltf::mesh::Mode::TriangleFan => fan_to_list(&indices),
+                mode => {
+                    log::warn!("{}: skipping {:?} primitive in mesh {}", path.display(), mode, mesh.index());
+                    continue;
+                }
+            };
+            let normals = reader.read_normals().map(|n| n.collect());
+            let uvs = reader.read_tex_coords(0).map(|t| t.into_f32().collect());
+            let tangents = reader.read_tangents().map(|t| t.collect());
+            let geometry = build_geometry(positions, normals, uvs, tangents, indices)
+                .with_context(|| format!("mesh {}", mesh.index()))?;
+            primitives.push(model.meshes.len());
+            model.meshes.push(ModelMesh {
+                name: mesh.name().unwrap_or_default().to_string(),
+                mesh: geometry.to_mesh(init),
+                material: primitive.material().index(),
+            });
+        }
+        mesh_primitives.push(primitives);
+    }
+
+    for camera in document.cameras() {
+        let projection = match camera.projection() {
+            gltf::camera::Projection::Perspective(p) => Projection::Perspective {
+                yfov: p.yfov(),
+                aspect_ratio: p.aspect_ratio(),
+                znear: p.znear(),
+                zfar: p.zfar(),
+            },
+            gltf::camera::Projection::Orthographic(o) => Projection::Orthographic {
+                xmag: o.xmag(),
+                ymag: o.ymag(),
+                znear: o.znear(),
+                zfar: o.zfar(),
+            },
+        };
+        model.cameras.push(ModelCamera { name: camera.name().unwrap_or_default().to_string(), projection });
+    }
+
+    for node in document.nodes() {
+        model.nodes.push(Node {
+            name: node.name().unwrap_or_default().to_string(),
+            transform: Matrix4::from(node.transform().matrix()),
+            children: node.children().map(|c| c.index()).collect(),
+            meshes: node.mesh().map(|m| mesh_primitives[m.index()].clone()).unwrap_or_default(),
+            camera: node.camera().map(|c| c.index()),
+        });
+    }
+
+    model.roots = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene.nodes().map(|n| n.index()).collect(),
+        None => {
+            let mut is_child = vec![false; model.nodes.len()];
+            model.nodes.iter().flat_map(|n| &n.children).for_each(|&c| is_child[c] = true);
+            (0..model.nodes.len()).filter(|&i| !is_child[i]).collect()
+        }
+    };
+    check_hierarchy(&model.nodes)?;
+    Ok(model)
+}
+
+/// Fails unless the nodes form a forest: no node has two parents and none is its own ancestor.
+fn check_hierarchy(nodes: &[Node]) -> Result<()> {
+    let mut parent: Vec<Option<usize>> = vec![None; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        for &child in &node.children {
+            if let Some(other) = parent[child].replace(index) {
+                bail!("node {} is a child of both node {} and node {}", child, other, index);
+            }
+        }
+    }
+    // Every node reachable from a parentless one is in a tree; the others hang off a cycle.
+    let mut reached = vec![false; nodes.len()];
+    let mut stack: Vec<usize> = (0..nodes.len()).filter(|&i| parent[i].is_none()).collect();
+    while let Some(index) = stack.pop() {
+        reached[index] = true;
+        stack.extend(&nodes[index].children);
+    }
+    match reached.iter().position(|&r| !r) {
+        Some(index) => bail!("node {} is part of a cycle in the node hierarchy", index),
+        None => Ok(()),
+    }
+}
+
+/// Resolves a glTF/OBJ URI: base64 `data:` URIs are decoded, anything else is read
+/// relative to the model's directory.
+fn read_uri(base_dir: &Path, uri: &str) -> Result<Vec<u8>> {
+    if let Some(rest) = uri.strip_prefix("data:") {
+        let (_, data) = rest.split_once(";base64,").ok_or_else(|| anyhow!("unsupported data URI"))?;
+        return Ok(base64::engine::general_purpose::STANDARD.decode(data)?);
+    }
+    let file = base_dir.join(percent_decode(uri));
+    std::fs::read(&file).with_context(|| format!("could not read {}", file.display()))
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn build_geometry(
+    positions: Vec<[f32; 3]>,
+    normals: Option<Vec<[f32; 3]>>,
+    uvs: Option<Vec<[f32; 2]>>,
+    tangents: Option<Vec<[f32; 4]>>,
+    indices: Vec<u32>,
+) -> Result<Geometry> {
+    if let Some(&i) = indices.iter().find(|&&i| i as usize >= positions.len()) {
+        bail!("index {} is out of range for {} vertices", i, positions.len());
+    }
+    let normals = normals.unwrap_or_else(|| smooth_normals(&positions, &indices));
+    let mut geometry = Geometry {
+        vertices: positions
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let uv = uvs.as_ref().and_then(|u| u.get(i).copied()).unwrap_or_default();
+                Vertex::new(p, normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]), uv)
+            })
+            .collect(),
+        indices,
+    };
+    match tangents {
+        Some(tangents) if tangents.len() == geometry.vertices.len() => {
+            geometry.vertices.iter_mut().zip(tangents).for_each(|(v, t)| v.tangent = t);
+        }
+        _ => geometry.compute_tangents(),
+    }
+    Ok(geometry)
+}
+
+/// Area-weighted vertex normals for meshes that do not provide any.
+fn smooth_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = vec![Vector3::new(0.0f32, 0.0, 0.0); positions.len()];
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| Vector3::from(positions[i as usize]));
+        let n = (b - a).cross(c - a);
+        for &i in tri {
+            normals[i as usize] += n;
+        }
+    }
+    normals
+        .into_iter()
+        .map(|n| if n.magnitude2() > 0.0 { n.normalize().into() } else { [0.0, 1.0, 0.0] })
+        .collect()
+}
+
+fn strip_to_list(indices: &[u32]) -> Vec<u32> {
+    indices
+        .windows(3)
+        .enumerate()
+        .flat_map(|(i, w)| if i % 2 == 0 { [w[0], w[1], w[2]] } else { [w[1], w[0], w[2]] })
+        .collect()
+}
+
+fn fan_to_list(indices: &[u32]) -> Vec<u32> {
+    indices.windows(2).skip(1).flat_map(|w| [indices[0], w[0], w[1]]).collect()
+}
+
+fn create_texture(init: &IWgpuInit, image: &DynamicImage, srgb: bool, sampler: wgpu::Sampler) -> Texture {
+    let format = if srgb { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm };
+    Texture { view: ws::create_image_texture_view_with_format(init, image, format), sampler }
+}
+
+fn repeat_sampler(init: &IWgpuInit) -> wgpu::Sampler {
+    init.device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+fn create_gltf_sampler(init: &IWgpuInit, sampler: &gltf::texture::Sampler) -> wgpu::Sampler {
+    use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+    let address_mode = |mode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+    let (min_filter, mipmap_filter) = match sampler.min_filter() {
+        Some(MinFilter::Nearest) | Some(MinFilter::NearestMipmapNearest) => (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest),
+        Some(MinFilter::NearestMipmapLinear) => (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear),
+        Some(MinFilter::LinearMipmapNearest) => (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest),
+        _ => (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear),
+    };
+    init.device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,155 @@
+use anyhow::{ensure, Result};
+
+use crate::{
+    blend::BlendMode,
+    depth::DepthConfig,
+    fullscreen::{FullscreenPass, FullscreenPassDescriptor},
+    helpers::{self, IWgpuInit},
+    render_target::{RenderTarget, RenderTargetDescriptor},
+};
+
+/// `OitOutput` and `oit_output(color, view_depth)` for the fragment stage of translucent
+/// pipelines drawing into `WeightedBlendedOit`; prepend it to their shader.
+pub const OIT_WGSL: &str = include_str!("shaders/oit.wgsl");
+const COMPOSITE_WGSL: &str = include_str!("shaders/oit_composite.wgsl");
+
+/// Sum of weighted premultiplied colors and weighted alphas.
+pub const ACCUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Product of `1 - alpha` over all fragments, how much of the opaque scene shows through.
+pub const REVEALAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+/// Blending of the accumulation and revealage targets, in that order, for
+/// `IRenderPipeline::blend`: accumulation adds up, revealage multiplies by `1 - alpha`.
+pub const OIT_BLEND: [BlendMode; 2] = {
+    use wgpu::BlendFactor::{One, OneMinusSrc, Zero};
+    [
+        BlendMode::Custom(wgpu::BlendState { color: add(One, One), alpha: add(One, One) }),
+        BlendMode::Custom(wgpu::BlendState { color: add(Zero, OneMinusSrc), alpha: add(Zero, OneMinusSrc) }),
+    ]
+};
+
+const fn add(src_factor: wgpu::BlendFactor, dst_factor: wgpu::BlendFactor) -> wgpu::BlendComponent {
+    wgpu::BlendComponent { src_factor, dst_factor, operation: wgpu::BlendOperation::Add }
+}
+
+/// Weighted blended order-independent transparency: translucent geometry is drawn in any order
+/// into an accumulation and a revealage target, testing against the opaque depth buffer, and
+/// `composite` blends their weighted average over the opaque frame. Unlike sorting it handles
+/// intersecting geometry, at the cost of approximating the order by a depth weight.
+///
+/// Translucent pipelines use `color_formats()`, `sample_count()`, `OIT_BLEND`, no depth writes
+/// and a fragment stage returning `oit_output` from `OIT_WGSL`.
+pub struct WeightedBlendedOit {
+    pub target: RenderTarget,
+    composite: FullscreenPass,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+}
+
+impl WeightedBlendedOit {
+    /// Targets follow the surface size and `IWgpuInit::sample_count`, so passes can share the
+    /// frame's depth buffer. Fails when the target formats do not support that sample count, see
+    /// `supported_sample_counts`.
+    pub fn new(init: &IWgpuInit) -> Result<Self> {
+        let supported = Self::supported_sample_counts(init);
+        ensure!(
+            supported.contains(&init.sample_count),
+            "OIT targets do not support {}x MSAA, only {:?}", init.sample_count, supported
+        );
+        let target = RenderTarget::new(init, &RenderTargetDescriptor {
+            label: "OIT Accumulation",
+            color_formats: &[ACCUM_FORMAT, REVEALAGE_FORMAT],
+            depth: false,
+            sample_count: init.sample_count,
+            ..Default::default()
+        });
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("OIT Composite Bind Group Layout"),
+            entries: &[texture_entry(0), texture_entry(1)],
+        });
+        let composite = FullscreenPass::new(init, COMPOSITE_WGSL, &FullscreenPassDescriptor {
+            label: "OIT Composite",
+            bind_group_layouts: &[&layout],
+            sample_count: init.sample_count,
+            blend: &[BlendMode::Alpha],
+            ..Default::default()
+        });
+        let bind_group = create_bind_group(init, &layout, &target);
+        Ok(Self { target, composite, layout, bind_group })
+    }
+
+    /// Frame sample counts `new` accepts.
+    pub fn supported_sample_counts(init: &IWgpuInit) -> Vec<u32> {
+        helpers::supported_sample_counts(&init.adapter, &[ACCUM_FORMAT, REVEALAGE_FORMAT])
+    }
+
+    pub fn color_formats(&self) -> &[wgpu::TextureFormat] {
+        self.target.color_formats()
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.target.sample_count
+    }
+
+    /// Follows the surface size; call it after `IWgpuInit::resize`.
+    pub fn resize(&mut self, init: &IWgpuInit) {
+        if self.target.resize(init) {
+            self.bind_group = create_bind_group(init, &self.layout, &self.target);
+        }
+    }
+
+    /// Starts the pass for translucent geometry: clears accumulation to 0 and revealage to 1 and
+    /// keeps the opaque depth in `depth_view`.
+    pub fn begin_accumulate_pass<'e>(&'e self, encoder: &'e mut wgpu::CommandEncoder, depth_view: &'e wgpu::TextureView,
+    depth: &DepthConfig) -> wgpu::RenderPass<'e> {
+        let mut color_attachments = self.target.color_attachments(Some(wgpu::Color::TRANSPARENT));
+        if let Some(revealage) = &mut color_attachments[1] {
+            revealage.ops.load = wgpu::LoadOp::Clear(wgpu::Color::WHITE);
+        }
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("OIT Accumulate Pass"),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: Some(depth.attachment_with_ops(depth_view, wgpu::LoadOp::Load, wgpu::LoadOp::Load)),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+
+    /// Blends the accumulated transparency over the frame in `view`, rendering to `msaa_view` and
+    /// resolving when there is one, as `create_frame_color_attachment` does.
+    pub fn composite(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, msaa_view: Option<&wgpu::TextureView>) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("OIT Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: msaa_view.unwrap_or(view),
+                resolve_target: msaa_view.map(|_| view),
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        self.composite.draw(&mut render_pass, &[&self.bind_group]);
+    }
+}
+
+fn create_bind_group(init: &IWgpuInit, layout: &wgpu::BindGroupLayout, target: &RenderTarget) -> wgpu::BindGroup {
+    init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("OIT Composite Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(target.view(0)) },
+            wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(target.view(1)) },
+        ],
+    })
+}
@@ -0,0 +1,585 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use bytemuck::{Pod, Zeroable};
+
+use crate::fullscreen::{FullscreenPass, FullscreenPassDescriptor, FULLSCREEN_WGSL};
+use crate::helpers::IWgpuInit;
+use crate::render_target::{create_texture_bind_group_layout, RenderTarget, RenderTargetDescriptor, TargetSize};
+
+/// Bindings every effect sees, appended to `fullscreen::FULLSCREEN_WGSL`: the previous result as
+/// `input_texture`/`input_sampler` at group 0 and `params: PostParams` at group 1. Custom effects
+/// are appended to both and must define `fs_main(in: VertexOutput) -> @location(0) vec4f`.
+pub const POST_COMMON_WGSL: &str = include_str!("shaders/post/common.wgsl");
+
+/// Format of the scene target and of every intermediate image in the chain.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Effect {
+    /// Scales the HDR input by `exposure` and maps it into 0..1.
+    ToneMap { exposure: f32, operator: ToneMapOperator },
+    /// Adds a blurred copy of everything brighter than `threshold`; belongs before `ToneMap`.
+    Bloom { threshold: f32, knee: f32, intensity: f32 },
+    Fxaa { contrast_threshold: f32, relative_threshold: f32, subpixel: f32 },
+    Vignette { strength: f32, radius: f32, softness: f32 },
+    /// Looks colors up in a 3D LUT, see `PostProcessor::set_lut`. Expects 0..1 input.
+    ColorGrade { strength: f32 },
+    /// Encodes for non-sRGB outputs; sRGB surfaces already encode in hardware.
+    Gamma { gamma: f32 },
+    /// A user fragment shader appended to `POST_COMMON_WGSL`, receiving `values` and `extra`
+    /// as `params.values` and `params.extra`.
+    Custom { source: String, values: [f32; 4], extra: [f32; 4] },
+}
+
+impl Effect {
+    pub fn tone_map() -> Self {
+        Effect::ToneMap { exposure: 1.0, operator: ToneMapOperator::Aces }
+    }
+
+    pub fn bloom() -> Self {
+        Effect::Bloom { threshold: 1.0, knee: 0.5, intensity: 0.6 }
+    }
+
+    pub fn fxaa() -> Self {
+        Effect::Fxaa { contrast_threshold: 0.0312, relative_threshold: 0.063, subpixel: 0.75 }
+    }
+
+    pub fn vignette() -> Self {
+        Effect::Vignette { strength: 0.5, radius: 0.6, softness: 0.8 }
+    }
+
+    pub fn color_grade() -> Self {
+        Effect::ColorGrade { strength: 1.0 }
+    }
+
+    pub fn gamma() -> Self {
+        Effect::Gamma { gamma: 2.2 }
+    }
+
+    fn params(&self) -> PostParams {
+        let values = match self {
+            Effect::ToneMap { exposure, operator } => [*exposure, *operator as u32 as f32, 0.0, 0.0],
+            Effect::Bloom { threshold, knee, intensity } => [*threshold, *knee, *intensity, 0.0],
+            Effect::Fxaa { contrast_threshold, relative_threshold, subpixel } => {
+                [*contrast_threshold, *relative_threshold, *subpixel, 0.0]
+            }
+            Effect::Vignette { strength, radius, softness } => [*strength, *radius, *softness, 0.0],
+            Effect::ColorGrade { strength } => [*strength, 0.0, 0.0, 0.0],
+            Effect::Gamma { gamma } => [*gamma, 0.0, 0.0, 0.0],
+            Effect::Custom { values, extra, .. } => return PostParams { values: *values, extra: *extra },
+        };
+        PostParams { values, extra: [0.0; 4] }
+    }
+
+    fn source(&self) -> Cow<'_, str> {
+        Cow::Borrowed(match self {
+            Effect::ToneMap { .. } => include_str!("shaders/post/tonemap.wgsl"),
+            Effect::Bloom { .. } => include_str!("shaders/post/bloom.wgsl"),
+            Effect::Fxaa { .. } => include_str!("shaders/post/fxaa.wgsl"),
+            Effect::Vignette { .. } => include_str!("shaders/post/vignette.wgsl"),
+            Effect::ColorGrade { .. } => include_str!("shaders/post/color_grade.wgsl"),
+            Effect::Gamma { .. } => include_str!("shaders/post/gamma.wgsl"),
+            Effect::Custom { source, .. } => source,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct PostParams {
+    values: [f32; 4],
+    extra: [f32; 4],
+}
+
+/// A 3D color lookup table sampled by `Effect::ColorGrade`.
+pub struct Lut {
+    pub size: u32,
+    pub view: wgpu::TextureView,
+}
+
+impl Lut {
+    pub fn identity(init: &IWgpuInit, size: u32) -> Self {
+        Self::from_fn(init, size, |rgb| rgb)
+    }
+
+    pub fn from_fn(init: &IWgpuInit, size: u32, f: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        let size = size.max(2);
+        let scale = 1.0 / (size - 1) as f32;
+        let mut table = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push(f([r as f32 * scale, g as f32 * scale, b as f32 * scale]));
+                }
+            }
+        }
+        Self::from_table(init, size, &table)
+    }
+
+    /// Parses an Adobe/Resolve `.cube` file with a `LUT_3D_SIZE` table.
+    pub fn from_cube(init: &IWgpuInit, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let (size, table) = parse_cube(&text).with_context(|| format!("invalid LUT {}", path.display()))?;
+        Ok(Self::from_table(init, size, &table))
+    }
+
+    /// `table` is indexed red fastest, then green, then blue.
+    fn from_table(init: &IWgpuInit, size: u32, table: &[[f32; 3]]) -> Self {
+        let to_unorm = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let texels: Vec<u8> = table.iter().flat_map(|c| [to_unorm(c[0]), to_unorm(c[1]), to_unorm(c[2]), 255]).collect();
+        let extent = wgpu::Extent3d { width: size, height: size, depth_or_array_layers: size };
+        let texture = init.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color Grading LUT"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        init.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &texels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size),
+                rows_per_image: Some(size),
+            },
+            extent,
+        );
+        Self { size, view: texture.create_view(&wgpu::TextureViewDescriptor::default()) }
+    }
+}
+
+fn parse_cube(text: &str) -> Result<(u32, Vec<[f32; 3]>)> {
+    let mut size = None;
+    let mut table = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("LUT_3D_SIZE") {
+            size = Some(value.trim().parse::<u32>().context("bad LUT_3D_SIZE")?);
+            continue;
+        }
+        if line.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            // TITLE, DOMAIN_MIN/MAX, LUT_1D_SIZE, ...
+            if line.starts_with("LUT_1D_SIZE") {
+                bail!("1D LUTs are not supported");
+            }
+            continue;
+        }
+        let values: Vec<f32> = line.split_whitespace().map(str::parse).collect::<Result<_, _>>()?;
+        if values.len() != 3 {
+            bail!("expected 3 values per entry, found {:?}", line);
+        }
+        table.push([values[0], values[1], values[2]]);
+    }
+    let size = size.context("missing LUT_3D_SIZE")?;
+    if table.len() != (size * size * size) as usize {
+        bail!("expected {} entries, found {}", size * size * size, table.len());
+    }
+    Ok((size, table))
+}
+
+struct Pass {
+    name: String,
+    effect: Effect,
+    enabled: bool,
+    /// Renders into an intermediate `HDR_FORMAT` target.
+    pipeline: FullscreenPass,
+    /// Same shader rendering into the output format, used when the pass is the last one.
+    output_pipeline: FullscreenPass,
+    /// Bloom's extract and blur passes.
+    bloom_pipelines: Option<[FullscreenPass; 3]>,
+    params_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    lut_bind_group: Option<wgpu::BindGroup>,
+}
+
+/// A reorderable chain of fullscreen effects between the scene and the swapchain.
+///
+/// Render the scene into `scene` (HDR, with depth) using pipelines created for its formats, then
+/// call `run` with the surface view. Enabled effects ping-pong between two `HDR_FORMAT` targets;
+/// the last one writes straight to the output.
+pub struct PostProcessor {
+    pub scene: RenderTarget,
+    output_format: wgpu::TextureFormat,
+    ping_pong: [RenderTarget; 2],
+    bloom: [RenderTarget; 2],
+    texture_layout: wgpu::BindGroupLayout,
+    params_layout: wgpu::BindGroupLayout,
+    lut_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    lut_sampler: wgpu::Sampler,
+    identity_lut: Lut,
+    copy_pipeline: FullscreenPass,
+    copy_params: wgpu::BindGroup,
+    /// Bind groups for scene, ping_pong[0], ping_pong[1], bloom[0], bloom[1], in that order.
+    sources: Vec<wgpu::BindGroup>,
+    passes: Vec<Pass>,
+}
+
+const SCENE: usize = 0;
+const PING: usize = 1;
+const BLOOM: usize = 3;
+
+impl PostProcessor {
+    /// `sample_count` applies to the scene target only; it is resolved before the first effect.
+    pub fn new(init: &IWgpuInit, sample_count: u32) -> Self {
+        let scene = RenderTarget::new(init, &RenderTargetDescriptor {
+            label: "Post Scene",
+            color_formats: &[HDR_FORMAT],
+            depth: true,
+            sample_count,
+            ..Default::default()
+        });
+        let intermediate = |label, scale| {
+            RenderTarget::new(init, &RenderTargetDescriptor {
+                label,
+                size: TargetSize::Surface { scale },
+                color_formats: &[HDR_FORMAT],
+                depth: false,
+                sample_count: 1,
+            })
+        };
+        let ping_pong = [intermediate("Post Ping", 1.0), intermediate("Post Pong", 1.0)];
+        let bloom = [intermediate("Bloom Ping", 0.5), intermediate("Bloom Pong", 0.5)];
+
+        let texture_layout = create_texture_bind_group_layout(init);
+        let params_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Params Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let lut_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("LUT Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let linear_clamp = |label| {
+            init.device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some(label),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                ..Default::default()
+            })
+        };
+        let sampler = linear_clamp("Post Sampler");
+        let lut_sampler = linear_clamp("LUT Sampler");
+
+        let copy_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post Copy"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(post_source(include_str!("shaders/post/copy.wgsl")))),
+        });
+        let copy_pipeline = create_post_pipeline(init, &[&texture_layout, &params_layout], &copy_shader, "fs_main", init.config.format);
+
+        let mut post = Self {
+            scene,
+            output_format: init.config.format,
+            ping_pong,
+            bloom,
+            copy_pipeline,
+            copy_params: create_params(init, &params_layout, &PostParams::zeroed()).1,
+            texture_layout,
+            params_layout,
+            lut_layout,
+            sampler,
+            lut_sampler,
+            identity_lut: Lut::identity(init, 2),
+            sources: Vec::new(),
+            passes: Vec::new(),
+        };
+        post.create_sources(init);
+        post
+    }
+
+    /// A typical chain: bloom, ACES tone mapping, color grading (identity LUT), vignette and FXAA,
+    /// plus gamma when the output is not an sRGB format. Vignette starts disabled.
+    pub fn with_default_chain(init: &IWgpuInit, sample_count: u32) -> Self {
+        let mut post = Self::new(init, sample_count);
+        post.push(init, "bloom", Effect::bloom()).unwrap();
+        post.push(init, "tone map", Effect::tone_map()).unwrap();
+        post.push(init, "color grade", Effect::color_grade()).unwrap();
+        post.push(init, "vignette", Effect::vignette()).unwrap();
+        post.set_enabled("vignette", false);
+        post.push(init, "fxaa", Effect::fxaa()).unwrap();
+        if !init.config.format.is_srgb() {
+            post.push(init, "gamma", Effect::gamma()).unwrap();
+        }
+        post
+    }
+
+    /// Appends an effect; fails only if a `Custom` shader does not compile or does not fit the
+    /// pass, e.g. lacks an entry point or declares other bindings.
+    pub fn push(&mut self, init: &IWgpuInit, name: &str, effect: Effect) -> Result<()> {
+        let index = self.passes.len();
+        self.insert(init, index, name, effect)
+    }
+
+    pub fn insert(&mut self, init: &IWgpuInit, index: usize, name: &str, effect: Effect) -> Result<()> {
+        let pass = self.create_pass(init, name, effect)?;
+        self.passes.insert(index.min(self.passes.len()), pass);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Effect> {
+        let index = self.index_of(name)?;
+        Some(self.passes.remove(index).effect)
+    }
+
+    /// Moves the named effect to position `to` in the chain.
+    pub fn move_to(&mut self, name: &str, to: usize) -> bool {
+        let Some(from) = self.index_of(name) else {
+            return false;
+        };
+        let pass = self.passes.remove(from);
+        self.passes.insert(to.min(self.passes.len()), pass);
+        true
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.index_of(name) {
+            Some(index) => {
+                self.passes[index].enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Flips the named effect on or off and returns its new state.
+    pub fn toggle(&mut self, name: &str) -> Option<bool> {
+        let index = self.index_of(name)?;
+        let pass = &mut self.passes[index];
+        pass.enabled = !pass.enabled;
+        Some(pass.enabled)
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.index_of(name).is_some_and(|i| self.passes[i].enabled)
+    }
+
+    /// Effect settings, editable at runtime; changes are picked up by the next `run`. Switching
+    /// an effect to a different variant is not supported, `remove` and `insert` it instead.
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut Effect> {
+        let index = self.index_of(name)?;
+        Some(&mut self.passes[index].effect)
+    }
+
+    /// Effect names in chain order, with their enabled state.
+    pub fn effects(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.passes.iter().map(|p| (p.name.as_str(), p.enabled))
+    }
+
+    /// Replaces the LUT of a `ColorGrade` effect.
+    pub fn set_lut(&mut self, init: &IWgpuInit, name: &str, lut: &Lut) -> bool {
+        let Some(index) = self.index_of(name) else {
+            return false;
+        };
+        if !matches!(self.passes[index].effect, Effect::ColorGrade { .. }) {
+            return false;
+        }
+        self.passes[index].lut_bind_group = Some(self.create_lut_bind_group(init, lut));
+        true
+    }
+
+    /// Keeps every target in sync with the surface; call it after `IWgpuInit::resize`.
+    pub fn resize(&mut self, init: &IWgpuInit) {
+        let mut changed = self.scene.resize(init);
+        for target in self.ping_pong.iter_mut().chain(self.bloom.iter_mut()) {
+            changed |= target.resize(init);
+        }
+        if changed {
+            self.create_sources(init);
+        }
+    }
+
+    /// Records the chain, reading `scene` and writing the final image to `output`.
+    pub fn run(&self, init: &IWgpuInit, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let enabled: Vec<&Pass> = self.passes.iter().filter(|p| p.enabled).collect();
+        if enabled.is_empty() {
+            self.fullscreen_pass(encoder, output, &self.copy_pipeline, SCENE, &self.copy_params, None);
+            return;
+        }
+        let mut source = SCENE;
+        for (i, pass) in enabled.iter().enumerate() {
+            init.queue.write_buffer(&pass.params_buffer, 0, bytemuck::bytes_of(&pass.effect.params()));
+            let last = i + 1 == enabled.len();
+            let destination = if source == PING { PING + 1 } else { PING };
+            let (view, pipeline) = if last {
+                (output, &pass.output_pipeline)
+            } else {
+                (self.ping_pong[destination - PING].view(0), &pass.pipeline)
+            };
+            let extra = if let Some([extract, horizontal, vertical]) = &pass.bloom_pipelines {
+                let params = &pass.params_bind_group;
+                self.fullscreen_pass(encoder, self.bloom[0].view(0), extract, source, params, None);
+                self.fullscreen_pass(encoder, self.bloom[1].view(0), horizontal, BLOOM, params, None);
+                self.fullscreen_pass(encoder, self.bloom[0].view(0), vertical, BLOOM + 1, params, None);
+                Some(&self.sources[BLOOM])
+            } else {
+                pass.lut_bind_group.as_ref()
+            };
+            self.fullscreen_pass(encoder, view, pipeline, source, &pass.params_bind_group, extra);
+            source = destination;
+        }
+    }
+
+    fn fullscreen_pass(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, pipeline: &FullscreenPass,
+    source: usize, params: &wgpu::BindGroup, extra: Option<&wgpu::BindGroup>) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Post Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        let mut bind_groups = vec![&self.sources[source], params];
+        bind_groups.extend(extra);
+        pipeline.draw(&mut render_pass, &bind_groups);
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|p| p.name == name)
+    }
+
+    fn create_sources(&mut self, init: &IWgpuInit) {
+        let targets = [&self.scene, &self.ping_pong[0], &self.ping_pong[1], &self.bloom[0], &self.bloom[1]];
+        self.sources = targets
+            .iter()
+            .map(|target| target.create_bind_group(init, &self.texture_layout, 0, &self.sampler))
+            .collect();
+    }
+
+    fn create_lut_bind_group(&self, init: &IWgpuInit, lut: &Lut) -> wgpu::BindGroup {
+        init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("LUT Bind Group"),
+            layout: &self.lut_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&lut.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&self.lut_sampler) },
+            ],
+        })
+    }
+
+    fn create_pass(&self, init: &IWgpuInit, name: &str, effect: Effect) -> Result<Pass> {
+        let source = post_source(&effect.source());
+        // Catch errors in custom effects, in the WGSL or in creating the pipelines from it, here
+        // rather than as an uncaptured device error.
+        init.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+        let mut layouts = vec![&self.texture_layout, &self.params_layout];
+        match effect {
+            Effect::Bloom { .. } => layouts.push(&self.texture_layout),
+            Effect::ColorGrade { .. } => layouts.push(&self.lut_layout),
+            _ => {}
+        }
+        let entry = if let Effect::Bloom { .. } = effect { "fs_composite" } else { "fs_main" };
+        let pipeline = create_post_pipeline(init, &layouts, &shader, entry, HDR_FORMAT);
+        let output_pipeline = create_post_pipeline(init, &layouts, &shader, entry, self.output_format);
+        let bloom_pipelines = matches!(effect, Effect::Bloom { .. }).then(|| {
+            let layouts = &layouts[..2];
+            ["fs_extract", "fs_blur_horizontal", "fs_blur_vertical"]
+                .map(|entry| create_post_pipeline(init, layouts, &shader, entry, HDR_FORMAT))
+        });
+        if let Some(error) = pollster::block_on(init.device.pop_error_scope()) {
+            bail!("post effect {:?} is invalid: {}", name, error);
+        }
+        let lut_bind_group = matches!(effect, Effect::ColorGrade { .. })
+            .then(|| self.create_lut_bind_group(init, &self.identity_lut));
+        let (params_buffer, params_bind_group) = create_params(init, &self.params_layout, &effect.params());
+        Ok(Pass {
+            name: name.to_string(),
+            effect,
+            enabled: true,
+            pipeline,
+            output_pipeline,
+            bloom_pipelines,
+            params_buffer,
+            params_bind_group,
+            lut_bind_group,
+        })
+    }
+}
+
+fn create_params(init: &IWgpuInit, layout: &wgpu::BindGroupLayout, params: &PostParams) -> (wgpu::Buffer, wgpu::BindGroup) {
+    use wgpu::util::DeviceExt;
+    let buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Post Params Buffer"),
+        contents: bytemuck::bytes_of(params),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Post Params Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+    });
+    (buffer, bind_group)
+}
+
+fn post_source(fragment: &str) -> String {
+    format!("{}\n{}\n{}", FULLSCREEN_WGSL, POST_COMMON_WGSL, fragment)
+}
+
+fn create_post_pipeline(init: &IWgpuInit, layouts: &[&wgpu::BindGroupLayout], shader: &wgpu::ShaderModule, fs_entry: &str,
+format: wgpu::TextureFormat) -> FullscreenPass {
+    FullscreenPass::with_module(init, shader, &FullscreenPassDescriptor {
+        label: fs_entry,
+        fs_entry,
+        bind_group_layouts: layouts,
+        color_formats: &[format],
+        sample_count: 1,
+        blend: &[],
+    })
+}
@@ -0,0 +1,301 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread::JoinHandle,
+};
+
+use anyhow::{bail, Context, Result};
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, RgbaImage,
+};
+
+use crate::{app::FIXED_FRAME_RATE, capture::ReadbackLayout, helpers::IWgpuInit};
+
+/// Readbacks that can be in flight before `Recorder::capture` waits for the oldest one.
+const RING_SIZE: usize = 4;
+
+/// Where recorded frames go, picked from the output path by `RecordFormat::from_path`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// `frame_00000.png`, `frame_00001.png`, ... in a directory.
+    PngSequence,
+    /// A looping animated GIF.
+    Gif,
+    /// An uncompressed YUV4MPEG2 stream (4:4:4, BT.601 limited range) for encoders like ffmpeg.
+    Y4m,
+}
+
+impl RecordFormat {
+    /// `.gif` and `.y4m` files by extension; anything else is a directory for PNGs.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("gif") => RecordFormat::Gif,
+            Some("y4m") => RecordFormat::Y4m,
+            _ => RecordFormat::PngSequence,
+        }
+    }
+}
+
+enum Sink {
+    Png { dir: PathBuf },
+    Gif(GifEncoder<BufWriter<File>>),
+    Y4m(BufWriter<File>),
+}
+
+impl Sink {
+    /// Writes frame number `index`, recorded every `every`th capture.
+    fn write(&mut self, image: &RgbaImage, index: u32, every: u32) -> Result<()> {
+        match self {
+            Sink::Png { dir } => {
+                let path = dir.join(format!("frame_{:05}.png", index));
+                image.save(&path).with_context(|| format!("failed to save {}", path.display()))?;
+            }
+            Sink::Gif(encoder) => {
+                // GIF delays are in hundredths of a second and get rounded.
+                let delay = Delay::from_numer_denom_ms(1000 * every, FIXED_FRAME_RATE);
+                encoder.encode_frame(image::Frame::from_parts(image.clone(), 0, 0, delay))?;
+            }
+            Sink::Y4m(file) => {
+                if index == 0 {
+                    let (numerator, denominator) = frame_rate(every);
+                    writeln!(file, "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444", image.width(), image.height(), numerator, denominator)?;
+                }
+                file.write_all(b"FRAME\n")?;
+                let pixels: Vec<[f32; 3]> = image.pixels().map(|p| [0, 1, 2].map(|c| p.0[c] as f32 / 255.0)).collect();
+                for plane in 0..3 {
+                    let bytes: Vec<u8> = pixels.iter().map(|&rgb| rgb_to_ycbcr(rgb)[plane]).collect();
+                    file.write_all(&bytes)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes every frame received, then closes the output. Returns the number of frames.
+    fn run(mut self, frames: Receiver<RgbaImage>, every: u32) -> Result<u32> {
+        let mut written = 0;
+        for image in frames {
+            self.write(&image, written, every)?;
+            written += 1;
+        }
+        match self {
+            Sink::Png { .. } => {}
+            // The encoder writes the trailer when dropped.
+            Sink::Gif(encoder) => drop(encoder),
+            Sink::Y4m(mut file) => file.flush()?,
+        }
+        Ok(written)
+    }
+}
+
+struct Pending {
+    buffer: wgpu::Buffer,
+    submission: wgpu::SubmissionIndex,
+    mapped: Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+/// Records offscreen frames, see `IWgpuInit::set_offscreen`. Each capture copies the frame into
+/// one of a ring of readback buffers and only waits for the GPU when all of them are in flight,
+/// and then only for the oldest, so frames are read back a few captures later. Encoding runs on
+/// a writer thread, since a GIF frame alone takes tens of milliseconds to quantize at window
+/// sizes; when the writer falls behind by `RING_SIZE` frames, `capture` blocks until it catches
+/// up. Frames are made opaque, as shown in the window.
+pub struct Recorder {
+    path: PathBuf,
+    /// Captures per recorded frame.
+    every: u32,
+    captures: u64,
+    layout: Option<ReadbackLayout>,
+    free: Vec<wgpu::Buffer>,
+    pending: VecDeque<Pending>,
+    sender: Option<SyncSender<RgbaImage>>,
+    writer: Option<JoinHandle<Result<u32>>>,
+    frames: u32,
+    skipped: u32,
+}
+
+impl Recorder {
+    /// Records every `every`th capture to `path`, in the format given by
+    /// `RecordFormat::from_path`. Captures are assumed `app::FIXED_TIMESTEP` apart, which sets
+    /// the frame rate of GIF and Y4M output.
+    pub fn new(path: &Path, every: u32) -> Result<Self> {
+        if every == 0 {
+            bail!("frames must be recorded at least every capture");
+        }
+        let create = || File::create(path).map(BufWriter::new).with_context(|| format!("failed to create {}", path.display()));
+        let sink = match RecordFormat::from_path(path) {
+            RecordFormat::PngSequence => {
+                std::fs::create_dir_all(path).with_context(|| format!("failed to create {}", path.display()))?;
+                Sink::Png { dir: path.to_path_buf() }
+            }
+            RecordFormat::Gif => {
+                // Speed 10 of 30 quantizes a frame in well under a second at window sizes.
+                let mut encoder = GifEncoder::new_with_speed(create()?, 10);
+                encoder.set_repeat(Repeat::Infinite)?;
+                Sink::Gif(encoder)
+            }
+            RecordFormat::Y4m => Sink::Y4m(create()?),
+        };
+        let (sender, frames) = mpsc::sync_channel(RING_SIZE);
+        let writer = std::thread::Builder::new()
+            .name("recorder".to_string())
+            .spawn(move || sink.run(frames, every))
+            .context("failed to start the recorder thread")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            every,
+            captures: 0,
+            layout: None,
+            free: Vec::new(),
+            pending: VecDeque::new(),
+            sender: Some(sender),
+            writer: Some(writer),
+            frames: 0,
+            skipped: 0,
+        })
+    }
+
+    /// Frames read back so far; the last few may still be being written.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Queues the readback of the offscreen frame just rendered and hands finished ones to the
+    /// writer. The first recorded frame sets the size; later frames of another size are skipped,
+    /// since GIF and Y4M cannot change it.
+    pub fn capture(&mut self, init: &IWgpuInit) -> Result<()> {
+        let texture = init.offscreen.as_ref().context("only offscreen frames can be recorded")?;
+        let record = self.captures.is_multiple_of(self.every as u64);
+        self.captures += 1;
+        if !record {
+            return Ok(());
+        }
+        let layout = ReadbackLayout::new(texture)?;
+        if *self.layout.get_or_insert(layout) != layout {
+            if self.skipped == 0 {
+                log::warn!("frame size changed while recording, skipping frames of {}x{}", layout.width, layout.height);
+            }
+            self.skipped += 1;
+            return Ok(());
+        }
+
+        let _ = init.device.poll(wgpu::Maintain::Poll);
+        while let Some(mapped) = self.pending.front().and_then(|oldest| oldest.mapped.try_recv().ok()) {
+            self.read_oldest(Some(mapped))?;
+        }
+        if self.free.is_empty() && self.pending.len() >= RING_SIZE {
+            let oldest = self.pending.front().expect("the ring is full").submission.clone();
+            let _ = init.device.poll(wgpu::Maintain::WaitForSubmissionIndex(oldest));
+            self.read_oldest(None)?;
+        }
+        let buffer = self.free.pop().unwrap_or_else(|| layout.create_buffer(&init.device));
+        let mut encoder = init.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Record Frame") });
+        layout.copy(&mut encoder, texture, &buffer);
+        let submission = init.queue.submit(std::iter::once(encoder.finish()));
+        let (sender, mapped) = mpsc::channel();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.pending.push_back(Pending { buffer, submission, mapped });
+        Ok(())
+    }
+
+    /// Waits for the frames in flight and the writer, and closes the output.
+    pub fn finish(mut self, init: &IWgpuInit) -> Result<u32> {
+        let _ = init.device.poll(wgpu::Maintain::Wait);
+        while !self.pending.is_empty() {
+            self.read_oldest(None)?;
+        }
+        self.sender = None;
+        let frames = self.join_writer()?;
+        if self.skipped > 0 {
+            log::warn!("skipped {} frames of another size", self.skipped);
+        }
+        log::info!("recorded {} frames to {}", frames, self.path.display());
+        Ok(frames)
+    }
+
+    /// Hands the oldest frame in flight to the writer. Its mapping result is received here
+    /// unless already given as `mapped`, so the device must have been polled until it is done.
+    fn read_oldest(&mut self, mapped: Option<Result<(), wgpu::BufferAsyncError>>) -> Result<()> {
+        let oldest = self.pending.pop_front().expect("a frame is in flight");
+        let mapped = match mapped {
+            Some(mapped) => mapped,
+            None => oldest.mapped.recv().context("readback was dropped")?,
+        };
+        mapped.context("failed to map readback buffer")?;
+        let buffer = oldest.buffer;
+        let mut image = self.layout.expect("layout is set before the first readback").read(&buffer);
+        buffer.unmap();
+        self.free.push(buffer);
+        image.pixels_mut().for_each(|p| p.0[3] = 255);
+        let sender = self.sender.as_ref().expect("the writer runs until `finish`");
+        if sender.send(image).is_err() {
+            // The writer only hangs up when it fails.
+            return Err(self.join_writer().err().unwrap_or_else(|| anyhow::anyhow!("the recorder thread stopped")));
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn join_writer(&mut self) -> Result<u32> {
+        let writer = self.writer.take().context("the recorder thread already stopped")?;
+        writer.join().map_err(|_| anyhow::anyhow!("the recorder thread panicked"))?
+            .with_context(|| format!("failed to record to {}", self.path.display()))
+    }
+}
+
+/// Frames per second as a reduced fraction when recording every `every`th frame at
+/// `FIXED_FRAME_RATE`.
+fn frame_rate(every: u32) -> (u32, u32) {
+    let gcd = (1..=every.min(FIXED_FRAME_RATE)).rev().find(|&d| FIXED_FRAME_RATE.is_multiple_of(d) && every.is_multiple_of(d)).unwrap_or(1);
+    (FIXED_FRAME_RATE / gcd, every / gcd)
+}
+
+/// BT.601 limited range Y'CbCr of a gamma-encoded color.
+fn rgb_to_ycbcr([r, g, b]: [f32; 3]) -> [u8; 3] {
+    let y = 16.0 + 65.481 * r + 128.553 * g + 24.966 * b;
+    let cb = 128.0 - 37.797 * r - 74.203 * g + 112.0 * b;
+    let cr = 128.0 + 112.0 * r - 93.786 * g - 18.214 * b;
+    [y, cb, cr].map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_from_paths() {
+        assert_eq!(RecordFormat::from_path(Path::new("out.gif")), RecordFormat::Gif);
+        assert_eq!(RecordFormat::from_path(Path::new("dir/OUT.GIF")), RecordFormat::Gif);
+        assert_eq!(RecordFormat::from_path(Path::new("out.y4m")), RecordFormat::Y4m);
+        assert_eq!(RecordFormat::from_path(Path::new("frames")), RecordFormat::PngSequence);
+        assert_eq!(RecordFormat::from_path(Path::new("frames.png")), RecordFormat::PngSequence);
+        assert_eq!(RecordFormat::from_path(Path::new("gif/frames")), RecordFormat::PngSequence);
+    }
+
+    #[test]
+    fn frame_rates_are_reduced() {
+        assert_eq!(FIXED_FRAME_RATE, 60);
+        assert_eq!(frame_rate(1), (60, 1));
+        assert_eq!(frame_rate(2), (30, 1));
+        assert_eq!(frame_rate(7), (60, 7));
+        assert_eq!(frame_rate(45), (4, 3));
+        assert_eq!(frame_rate(120), (1, 2));
+    }
+
+    #[test]
+    fn bt601_limited_range() {
+        assert_eq!(rgb_to_ycbcr([0.0, 0.0, 0.0]), [16, 128, 128]);
+        assert_eq!(rgb_to_ycbcr([1.0, 1.0, 1.0]), [235, 128, 128]);
+        assert_eq!(rgb_to_ycbcr([1.0, 0.0, 0.0]), [81, 90, 240]);
+        assert_eq!(rgb_to_ycbcr([0.0, 1.0, 0.0]), [145, 54, 34]);
+        assert_eq!(rgb_to_ycbcr([0.0, 0.0, 1.0]), [41, 240, 110]);
+        // Out of gamut input stays within the byte range.
+        assert_eq!(rgb_to_ycbcr([2.0, 2.0, 2.0])[0], 255);
+    }
+}
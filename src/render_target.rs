@@ -0,0 +1,254 @@
+use crate::{
+    depth::DepthConfig,
+    helpers::{self, IWgpuInit},
+};
+
+/// How a render target is sized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TargetSize {
+    /// Follows the surface, multiplied by `scale` (0.5 renders at half resolution).
+    Surface { scale: f32 },
+    Fixed { width: u32, height: u32 },
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderTargetDescriptor<'a> {
+    pub label: &'a str,
+    pub size: TargetSize,
+    /// One color attachment per format.
+    pub color_formats: &'a [wgpu::TextureFormat],
+    /// Adds a depth attachment in `IWgpuInit::depth`'s format, matching `IRenderPipeline`'s depth
+    /// state.
+    pub depth: bool,
+    /// With more than one sample, rendering goes to multisampled textures that are resolved into
+    /// the sampleable ones at the end of the pass. Lowered to what the formats support.
+    pub sample_count: u32,
+}
+
+impl Default for RenderTargetDescriptor<'_> {
+    fn default() -> Self {
+        Self {
+            label: "Render Target",
+            size: TargetSize::Surface { scale: 1.0 },
+            color_formats: &[wgpu::TextureFormat::Rgba8UnormSrgb],
+            depth: true,
+            sample_count: 1,
+        }
+    }
+}
+
+pub struct ColorBuffer {
+    pub texture: wgpu::Texture,
+    /// Single-sampled view, bindable as a texture in later passes.
+    pub view: wgpu::TextureView,
+    pub msaa_view: Option<wgpu::TextureView>,
+}
+
+/// Offscreen color (and optional depth) attachments that can be rendered to and then sampled.
+///
+/// Pipelines drawing into a target must be created with matching `IRenderPipeline::color_formats`
+/// and `sample_count`, see `color_formats()` and `sample_count`.
+pub struct RenderTarget {
+    pub label: String,
+    pub size: TargetSize,
+    pub width: u32,
+    pub height: u32,
+    pub sample_count: u32,
+    pub colors: Vec<ColorBuffer>,
+    pub depth_view: Option<wgpu::TextureView>,
+    /// The depth aspect of `depth_view`, bindable as a depth texture in later passes; single-sampled
+    /// targets only.
+    pub depth_sample_view: Option<wgpu::TextureView>,
+    /// Format and direction of `depth_view`, taken from `IWgpuInit::depth` when created.
+    pub depth: DepthConfig,
+    formats: Vec<wgpu::TextureFormat>,
+}
+
+impl RenderTarget {
+    pub fn new(init: &IWgpuInit, descriptor: &RenderTargetDescriptor) -> Self {
+        let (width, height) = resolve_size(init, descriptor.size);
+        let mut formats = descriptor.color_formats.to_vec();
+        if descriptor.depth {
+            formats.push(init.depth.format);
+        }
+        let sample_count = helpers::negotiate_sample_count(&init.adapter, &formats, descriptor.sample_count.max(1));
+        let mut target = Self {
+            label: descriptor.label.to_string(),
+            size: descriptor.size,
+            width,
+            height,
+            sample_count,
+            colors: Vec::new(),
+            depth_view: None,
+            depth_sample_view: None,
+            depth: init.depth,
+            formats: descriptor.color_formats.to_vec(),
+        };
+        target.create_textures(init, descriptor.depth);
+        target
+    }
+
+    pub fn color_formats(&self) -> &[wgpu::TextureFormat] {
+        &self.formats
+    }
+
+    /// The sampleable view of color attachment `index`.
+    pub fn view(&self, index: usize) -> &wgpu::TextureView {
+        &self.colors[index].view
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// Follows the surface size for `TargetSize::Surface` targets; call it after
+    /// `IWgpuInit::resize`. Returns true when the textures were recreated, in which case bind
+    /// groups referencing the old views have to be rebuilt.
+    pub fn resize(&mut self, init: &IWgpuInit) -> bool {
+        let (width, height) = resolve_size(init, self.size);
+        if (width, height) == (self.width, self.height) {
+            return false;
+        }
+        self.width = width;
+        self.height = height;
+        let depth = self.depth_view.is_some();
+        self.create_textures(init, depth);
+        true
+    }
+
+    /// Switches to a new size policy, e.g. a different fixed resolution. Same return value as `resize`.
+    pub fn set_size(&mut self, init: &IWgpuInit, size: TargetSize) -> bool {
+        self.size = size;
+        self.resize(init)
+    }
+
+    /// Color attachments for a pass into this target; `clear` of `None` keeps the previous contents.
+    pub fn color_attachments(&self, clear: Option<wgpu::Color>) -> Vec<Option<wgpu::RenderPassColorAttachment<'_>>> {
+        let load = clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear);
+        self.colors
+            .iter()
+            .map(|color| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view: color.msaa_view.as_ref().unwrap_or(&color.view),
+                    resolve_target: color.msaa_view.as_ref().map(|_| &color.view),
+                    ops: wgpu::Operations {
+                        load,
+                        // The multisampled contents are not needed once resolved.
+                        store: if color.msaa_view.is_some() { wgpu::StoreOp::Discard } else { wgpu::StoreOp::Store },
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Clears depth to `clear` and stencil, if any, to 0.
+    pub fn depth_stencil_attachment(&self, clear: f32) -> Option<wgpu::RenderPassDepthStencilAttachment<'_>> {
+        self.depth_view.as_ref().map(|view| self.depth.attachment_with_ops(view, wgpu::LoadOp::Clear(clear), wgpu::LoadOp::Clear(0)))
+    }
+
+    /// Starts a pass clearing color to `clear` (or loading it for `None`), depth to the far value
+    /// of `depth` and stencil to 0.
+    pub fn begin_render_pass<'e>(&'e self, encoder: &'e mut wgpu::CommandEncoder, clear: Option<wgpu::Color>) -> wgpu::RenderPass<'e> {
+        let color_attachments = self.color_attachments(clear);
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&self.label),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: self.depth_stencil_attachment(self.depth.clear_value()),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+
+    /// Bind group with color attachment `index` at binding 0 and `sampler` at binding 1, laid out
+    /// as in `create_texture_bind_group_layout`.
+    pub fn create_bind_group(&self, init: &IWgpuInit, layout: &wgpu::BindGroupLayout, index: usize, sampler: &wgpu::Sampler) -> wgpu::BindGroup {
+        init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&self.label),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(self.view(index)) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(sampler) },
+            ],
+        })
+    }
+
+    fn create_textures(&mut self, init: &IWgpuInit, depth: bool) {
+        let size = wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 };
+        let create = |label: &str, format: wgpu::TextureFormat, sample_count: u32, usage: wgpu::TextureUsages| {
+            init.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+                view_formats: &[],
+            })
+        };
+        let sampled = wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC;
+        self.colors = self
+            .formats
+            .iter()
+            .map(|&format| {
+                let texture = create(&self.label, format, 1, sampled);
+                let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                let msaa_view = (self.sample_count > 1).then(|| {
+                    create(&self.label, format, self.sample_count, wgpu::TextureUsages::RENDER_ATTACHMENT)
+                        .create_view(&wgpu::TextureViewDescriptor::default())
+                });
+                ColorBuffer { texture, view, msaa_view }
+            })
+            .collect();
+        // Multisampled depth is not resolved, so only single-sampled targets can be read later.
+        let sampleable = self.sample_count == 1;
+        let depth_texture = depth.then(|| {
+            let usage = if sampleable {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+            } else {
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            };
+            create(&self.label, self.depth.format, self.sample_count, usage)
+        });
+        self.depth_view = depth_texture.as_ref().map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        self.depth_sample_view = depth_texture.as_ref().filter(|_| sampleable).map(helpers::create_depth_sample_view);
+    }
+}
+
+/// Texture at binding 0 and filtering sampler at binding 1, visible to the fragment stage; the
+/// layout `RenderTarget::create_bind_group` and `imshow.wgsl` use.
+pub fn create_texture_bind_group_layout(init: &IWgpuInit) -> wgpu::BindGroupLayout {
+    init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Texture Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+fn resolve_size(init: &IWgpuInit, size: TargetSize) -> (u32, u32) {
+    match size {
+        TargetSize::Surface { scale } => (
+            ((init.config.width as f32 * scale).round() as u32).max(1),
+            ((init.config.height as f32 * scale).round() as u32).max(1),
+        ),
+        TargetSize::Fixed { width, height } => (width.max(1), height.max(1)),
+    }
+}
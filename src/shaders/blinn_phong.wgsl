@@ -0,0 +1,32 @@
+struct PhongMaterial {
+    diffuse: vec4f,
+    // rgb specular color, w shininess
+    specular: vec4f,
+};
+
+@group(2) @binding(0) var<uniform> material: PhongMaterial;
+@group(2) @binding(1) var diffuse_texture: texture_2d<f32>;
+@group(2) @binding(2) var material_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let diffuse = material.diffuse * textureSample(diffuse_texture, material_sampler, in.uv);
+    let n = normalize(in.normal);
+    let v = normalize(camera.position.xyz - in.world_position);
+
+    var color = lights.ambient.rgb * diffuse.rgb;
+    for (var i = 0u; i < lights.count.x; i++) {
+        let light = lights.items[i];
+        let incidence = light_incidence(light, in.world_position);
+        let l = incidence.xyz;
+        let n_dot_l = max(dot(n, l), 0.0);
+        let h = normalize(l + v);
+        var specular = 0.0;
+        if n_dot_l > 0.0 {
+            specular = pow(max(dot(n, h), 0.0), material.specular.w);
+        }
+        let radiance = light.color.rgb * incidence.w * shadow_factor(light, in.world_position, n);
+        color += radiance * (diffuse.rgb * n_dot_l + material.specular.rgb * specular);
+    }
+    return vec4f(color, diffuse.a);
+}
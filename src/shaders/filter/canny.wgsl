@@ -0,0 +1,61 @@
+// Canny edge stages after a Gaussian blur and `sobel.wgsl`'s `gradient`. `suppress` keeps local
+// maxima along the gradient and classifies them with the thresholds in values.xy (1 strong,
+// 0.5 weak, 0 none), `hysteresis` promotes weak pixels next to strong ones and is run several
+// times, and `finish` drops the remaining weak pixels.
+
+@compute @workgroup_size(8, 8)
+fn suppress(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let p = vec2i(id.xy);
+    let g = load(p);
+    // Quantize the gradient direction to one of four neighbor axes.
+    let angle = atan2(g.y, g.x);
+    let sector = i32(round(angle / 0.78539816)) & 3;
+    var step = vec2i(1, 0);
+    if sector == 1 {
+        step = vec2i(1, 1);
+    } else if sector == 2 {
+        step = vec2i(0, 1);
+    } else if sector == 3 {
+        step = vec2i(-1, 1);
+    }
+    var edge = 0.0;
+    if g.z >= load(p + step).z && g.z >= load(p - step).z {
+        if g.z >= params.values.y {
+            edge = 1.0;
+        } else if g.z >= params.values.x {
+            edge = 0.5;
+        }
+    }
+    textureStore(output_texture, p, vec4f(vec3f(edge), 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn hysteresis(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let p = vec2i(id.xy);
+    var edge = load(p).r;
+    if edge == 0.5 {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                if load(p + vec2i(x, y)).r == 1.0 {
+                    edge = 1.0;
+                }
+            }
+        }
+    }
+    textureStore(output_texture, p, vec4f(vec3f(edge), 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn finish(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let edge = select(0.0, 1.0, load(vec2i(id.xy)).r == 1.0);
+    textureStore(output_texture, vec2i(id.xy), vec4f(vec3f(edge), 1.0));
+}
@@ -0,0 +1,94 @@
+// Color space conversions of sRGB-encoded input, selected by mode: 0 RGB to HSV, 1 HSV to RGB,
+// 2 RGB to YCbCr (BT.601 full range, chroma centered on 0.5), 3 YCbCr to RGB, 4 RGB to CIELAB
+// (D65, L in 0..100), 5 CIELAB to RGB. Alpha is passed through.
+
+fn rgb_to_hsv(c: vec3f) -> vec3f {
+    let high = max(c.r, max(c.g, c.b));
+    let low = min(c.r, min(c.g, c.b));
+    let d = high - low;
+    var h = 0.0;
+    if d > 0.0 {
+        if high == c.r {
+            h = (c.g - c.b) / d;
+        } else if high == c.g {
+            h = (c.b - c.r) / d + 2.0;
+        } else {
+            h = (c.r - c.g) / d + 4.0;
+        }
+        h = fract(h / 6.0 + 1.0);
+    }
+    return vec3f(h, select(0.0, d / high, high > 0.0), high);
+}
+
+fn hsv_to_rgb(c: vec3f) -> vec3f {
+    let k = vec3f(5.0, 3.0, 1.0);
+    let n = (k + c.x * 6.0) % 6.0;
+    return c.z - c.z * c.y * clamp(min(n, 4.0 - n), vec3f(0.0), vec3f(1.0));
+}
+
+fn srgb_to_linear(c: vec3f) -> vec3f {
+    return select(pow((c + 0.055) / 1.055, vec3f(2.4)), c / 12.92, c <= vec3f(0.04045));
+}
+
+fn linear_to_srgb(c: vec3f) -> vec3f {
+    return select(1.055 * pow(max(c, vec3f(0.0)), vec3f(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3f(0.0031308));
+}
+
+const WHITE: vec3f = vec3f(0.95047, 1.0, 1.08883);
+
+fn lab_f(t: f32) -> f32 {
+    return select(t / (3.0 * 0.04280618) + 4.0 / 29.0, pow(t, 1.0 / 3.0), t > 0.008856452);
+}
+
+fn lab_f_inverse(t: f32) -> f32 {
+    return select(3.0 * 0.04280618 * (t - 4.0 / 29.0), t * t * t, t > 6.0 / 29.0);
+}
+
+fn rgb_to_lab(c: vec3f) -> vec3f {
+    let l = srgb_to_linear(c);
+    let xyz = mat3x3f(
+        vec3f(0.4124, 0.2126, 0.0193),
+        vec3f(0.3576, 0.7152, 0.1192),
+        vec3f(0.1805, 0.0722, 0.9505),
+    ) * l / WHITE;
+    let f = vec3f(lab_f(xyz.x), lab_f(xyz.y), lab_f(xyz.z));
+    return vec3f(116.0 * f.y - 16.0, 500.0 * (f.x - f.y), 200.0 * (f.y - f.z));
+}
+
+fn lab_to_rgb(c: vec3f) -> vec3f {
+    let fy = (c.x + 16.0) / 116.0;
+    let f = vec3f(fy + c.y / 500.0, fy, fy - c.z / 200.0);
+    let xyz = vec3f(lab_f_inverse(f.x), lab_f_inverse(f.y), lab_f_inverse(f.z)) * WHITE;
+    let l = mat3x3f(
+        vec3f(3.2406, -0.9689, 0.0557),
+        vec3f(-1.5372, 1.8758, -0.2040),
+        vec3f(-0.4986, 0.0415, 1.0570),
+    ) * xyz;
+    return linear_to_srgb(l);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let p = vec2i(id.xy);
+    let v = load(p);
+    var c = v.rgb;
+    switch params.mode {
+        case 0u: { c = rgb_to_hsv(c); }
+        case 1u: { c = hsv_to_rgb(c); }
+        case 2u: {
+            let y = dot(c, vec3f(0.299, 0.587, 0.114));
+            c = vec3f(y, (c.b - y) * 0.564 + 0.5, (c.r - y) * 0.713 + 0.5);
+        }
+        case 3u: {
+            let cb = c.y - 0.5;
+            let cr = c.z - 0.5;
+            c = vec3f(c.x + 1.403 * cr, c.x - 0.344 * cb - 0.714 * cr, c.x + 1.773 * cb);
+        }
+        case 4u: { c = rgb_to_lab(c); }
+        default: { c = lab_to_rgb(c); }
+    }
+    textureStore(output_texture, p, vec4f(c, v.a));
+}
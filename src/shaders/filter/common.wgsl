@@ -0,0 +1,33 @@
+// Bindings shared by every filter stage: the previous image as `input_texture`, the stage's
+// result as `output_texture`, and `aux_texture` for stages combining two images (the input
+// itself when unused). Out-of-range reads clamp to the edge.
+
+struct FilterParams {
+    values: vec4f,
+    mode: u32,
+    radius: i32,
+}
+
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+@group(0) @binding(1) var output_texture: texture_storage_2d<rgba32float, write>;
+@group(0) @binding(2) var<uniform> params: FilterParams;
+@group(0) @binding(3) var aux_texture: texture_2d<f32>;
+
+fn load(p: vec2i) -> vec4f {
+    let size = vec2i(textureDimensions(input_texture));
+    return textureLoad(input_texture, clamp(p, vec2i(0), size - 1), 0);
+}
+
+fn load_aux(p: vec2i) -> vec4f {
+    let size = vec2i(textureDimensions(aux_texture));
+    return textureLoad(aux_texture, clamp(p, vec2i(0), size - 1), 0);
+}
+
+fn outside(id: vec3u) -> bool {
+    let size = textureDimensions(output_texture);
+    return id.x >= size.x || id.y >= size.y;
+}
+
+fn luminance(c: vec3f) -> f32 {
+    return dot(c, vec3f(0.2126, 0.7152, 0.0722));
+}
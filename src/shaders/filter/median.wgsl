@@ -0,0 +1,42 @@
+// Per-channel median over a square window with radius 1 or 2.
+
+const MAX_WINDOW: u32 = 25u;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let p = vec2i(id.xy);
+    let r = clamp(params.radius, 1, 2);
+    var window: array<vec4f, MAX_WINDOW>;
+    var n = 0u;
+    for (var y = -r; y <= r; y++) {
+        for (var x = -r; x <= r; x++) {
+            window[n] = load(p + vec2i(x, y));
+            n++;
+        }
+    }
+    var result: vec4f;
+    for (var c = 0u; c < 4u; c++) {
+        var values: array<f32, MAX_WINDOW>;
+        for (var i = 0u; i < n; i++) {
+            values[i] = window[i][c];
+        }
+        // Selection sort up to the middle element.
+        let middle = n / 2u;
+        for (var i = 0u; i <= middle; i++) {
+            var smallest = i;
+            for (var j = i + 1u; j < n; j++) {
+                if values[j] < values[smallest] {
+                    smallest = j;
+                }
+            }
+            let t = values[i];
+            values[i] = values[smallest];
+            values[smallest] = t;
+        }
+        result[c] = values[middle];
+    }
+    textureStore(output_texture, p, result);
+}
@@ -0,0 +1,53 @@
+// One axis of a Lanczos-3 resize; the output texture has the new size along that axis.
+// When shrinking, the kernel is stretched by the scale factor to avoid aliasing.
+
+const PI: f32 = 3.14159265;
+const LOBES: f32 = 3.0;
+
+fn sinc(x: f32) -> f32 {
+    if abs(x) < 1e-5 {
+        return 1.0;
+    }
+    return sin(PI * x) / (PI * x);
+}
+
+fn lanczos(x: f32) -> f32 {
+    if abs(x) >= LOBES {
+        return 0.0;
+    }
+    return sinc(x) * sinc(x / LOBES);
+}
+
+fn resize_axis(id: vec3u, axis: u32) {
+    if outside(id) {
+        return;
+    }
+    let in_size = f32(textureDimensions(input_texture)[axis]);
+    let out_size = f32(textureDimensions(output_texture)[axis]);
+    let scale = in_size / out_size;
+    let support = max(scale, 1.0);
+    // Output pixel center in input coordinates.
+    let center = (f32(id[axis]) + 0.5) * scale - 0.5;
+    let first = i32(floor(center - LOBES * support));
+    let last = i32(ceil(center + LOBES * support));
+    var sum = vec4f(0.0);
+    var weight_sum = 0.0;
+    for (var i = first; i <= last; i++) {
+        let w = lanczos((f32(i) - center) / support);
+        var p = vec2i(id.xy);
+        p[axis] = i;
+        sum += load(p) * w;
+        weight_sum += w;
+    }
+    textureStore(output_texture, vec2i(id.xy), sum / weight_sum);
+}
+
+@compute @workgroup_size(8, 8)
+fn horizontal(@builtin(global_invocation_id) id: vec3u) {
+    resize_axis(id, 0u);
+}
+
+@compute @workgroup_size(8, 8)
+fn vertical(@builtin(global_invocation_id) id: vec3u) {
+    resize_axis(id, 1u);
+}
@@ -0,0 +1,43 @@
+// One axis of a separable kernel over a (2 * radius + 1) window. mode 0 averages (box blur),
+// 1 weights by a Gaussian with sigma = values.x, 2 takes the minimum (erode) and 3 the
+// maximum (dilate).
+
+fn filter_axis(id: vec3u, axis: vec2i) {
+    if outside(id) {
+        return;
+    }
+    let center = vec2i(id.xy);
+    var sum = vec4f(0.0);
+    var weight_sum = 0.0;
+    var low = vec4f(3.4e38);
+    var high = vec4f(-3.4e38);
+    let sigma = max(params.values.x, 1e-3);
+    for (var i = -params.radius; i <= params.radius; i++) {
+        let v = load(center + axis * i);
+        var w = 1.0;
+        if params.mode == 1u {
+            w = exp(-f32(i * i) / (2.0 * sigma * sigma));
+        }
+        sum += v * w;
+        weight_sum += w;
+        low = min(low, v);
+        high = max(high, v);
+    }
+    var result = sum / weight_sum;
+    if params.mode == 2u {
+        result = low;
+    } else if params.mode == 3u {
+        result = high;
+    }
+    textureStore(output_texture, center, result);
+}
+
+@compute @workgroup_size(8, 8)
+fn horizontal(@builtin(global_invocation_id) id: vec3u) {
+    filter_axis(id, vec2i(1, 0));
+}
+
+@compute @workgroup_size(8, 8)
+fn vertical(@builtin(global_invocation_id) id: vec3u) {
+    filter_axis(id, vec2i(0, 1));
+}
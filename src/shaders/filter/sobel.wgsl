@@ -0,0 +1,32 @@
+// Sobel gradients of the luminance. `gradient` writes (gx, gy, magnitude, 1) for later stages,
+// `magnitude` the magnitude as a gray image.
+
+fn sobel(p: vec2i) -> vec2f {
+    var l: array<f32, 9>;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            l[(y + 1) * 3 + x + 1] = luminance(load(p + vec2i(x, y)).rgb);
+        }
+    }
+    let gx = (l[2] + 2.0 * l[5] + l[8]) - (l[0] + 2.0 * l[3] + l[6]);
+    let gy = (l[6] + 2.0 * l[7] + l[8]) - (l[0] + 2.0 * l[1] + l[2]);
+    return vec2f(gx, gy);
+}
+
+@compute @workgroup_size(8, 8)
+fn gradient(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let g = sobel(vec2i(id.xy));
+    textureStore(output_texture, vec2i(id.xy), vec4f(g, length(g), 1.0));
+}
+
+@compute @workgroup_size(8, 8)
+fn magnitude(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let m = length(sobel(vec2i(id.xy)));
+    textureStore(output_texture, vec2i(id.xy), vec4f(vec3f(m), 1.0));
+}
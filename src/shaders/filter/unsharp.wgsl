@@ -0,0 +1,13 @@
+// Sharpens the input by adding values.x times its difference from the blurred aux_texture.
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3u) {
+    if outside(id) {
+        return;
+    }
+    let p = vec2i(id.xy);
+    let original = load(p);
+    let blurred = load_aux(p);
+    let sharpened = original.rgb + params.values.x * (original.rgb - blurred.rgb);
+    textureStore(output_texture, p, vec4f(sharpened, original.a));
+}
@@ -0,0 +1,33 @@
+struct VertexOutput {
+    @builtin(position) position: vec4f,
+    // (0, 0) is the top-left corner of the image, matching texture coordinates.
+    @location(0) uv: vec2f,
+};
+
+// One counter-clockwise triangle covering the whole target; draw it with `draw(0..3, 0..1)`.
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2f(f32(vertex_index & 2u), f32((vertex_index << 1u) & 2u));
+    var output: VertexOutput;
+    output.position = vec4f(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+struct QuadInput {
+    // Destination rectangle as (left, top, right, bottom) in 0..1 target coordinates, y down.
+    @location(0) rect: vec4f,
+    // Source rectangle in texture coordinates, same layout.
+    @location(1) uv_rect: vec4f,
+};
+
+// A 4-vertex triangle strip per instance, placed by `QuadInput`.
+@vertex
+fn vs_quad(@builtin(vertex_index) vertex_index: u32, quad: QuadInput) -> VertexOutput {
+    let corner = vec2f(f32((vertex_index >> 1u) & 1u), f32(vertex_index & 1u));
+    let p = mix(quad.rect.xy, quad.rect.zw, corner);
+    var output: VertexOutput;
+    output.position = vec4f(p.x * 2.0 - 1.0, 1.0 - p.y * 2.0, 0.0, 1.0);
+    output.uv = mix(quad.uv_rect.xy, quad.uv_rect.zw, corner);
+    return output;
+}
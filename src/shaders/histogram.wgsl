@@ -0,0 +1,41 @@
+// Per-channel histogram of a float texture. `histogram` holds 4 * BINS counts, channel after
+// channel, followed by the largest count of each channel.
+
+const BINS: u32 = 256u;
+
+struct Params {
+    // Values mapped to the first and last bin; values outside land in the edge bins.
+    range: vec2f,
+    channels: u32,
+}
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var<uniform> params: Params;
+@group(0) @binding(2) var<storage, read_write> histogram: array<atomic<u32>>;
+
+@compute @workgroup_size(16, 16)
+fn count(@builtin(global_invocation_id) id: vec3u) {
+    let size = textureDimensions(source);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+    let texel = textureLoad(source, vec2i(id.xy), 0);
+    let scale = f32(BINS) / max(params.range.y - params.range.x, 1e-20);
+    for (var c = 0u; c < params.channels; c++) {
+        let v = texel[c];
+        // Skip NaN and infinities.
+        if v != v || abs(v) > 3.4e38 {
+            continue;
+        }
+        let bin = u32(clamp((v - params.range.x) * scale, 0.0, f32(BINS - 1u)));
+        atomicAdd(&histogram[c * BINS + bin], 1u);
+    }
+}
+
+// Dispatched as a single workgroup once `count` is done.
+@compute @workgroup_size(256)
+fn find_max(@builtin(local_invocation_index) bin: u32) {
+    for (var c = 0u; c < 4u; c++) {
+        atomicMax(&histogram[4u * BINS + c], atomicLoad(&histogram[c * BINS + bin]));
+    }
+}
@@ -0,0 +1,144 @@
+struct Camera {
+    view: mat4x4f,
+    projection: mat4x4f,
+    view_projection: mat4x4f,
+    inverse_view_projection: mat4x4f,
+    position: vec4f,
+};
+
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+
+struct Light {
+    // xyz position, w light kind
+    position: vec4f,
+    // xyz direction the light travels in, w range (0 = unlimited)
+    direction: vec4f,
+    // rgb color premultiplied by intensity
+    color: vec4f,
+    // x cos(inner cone angle), y cos(outer cone angle)
+    cone: vec4f,
+    // x first shadow map layer, y layer count (0 = no shadow)
+    shadow: vec4f,
+    // view-space far distance of each directional cascade
+    splits: vec4f,
+};
+
+struct Lights {
+    ambient: vec4f,
+    count: vec4u,
+    items: array<Light>,
+};
+
+struct ShadowCaster {
+    view_proj: mat4x4f,
+    // x world-space size of one shadow map texel
+    texel: vec4f,
+};
+
+struct Shadows {
+    // x pcf radius, y normal offset in texels, z 1 / resolution
+    params: vec4f,
+    casters: array<ShadowCaster>,
+};
+
+struct Transform {
+    model: mat4x4f,
+    normal: mat4x4f,
+};
+
+@group(0) @binding(0) var<uniform> camera: Camera;
+@group(1) @binding(0) var<storage, read> lights: Lights;
+@group(1) @binding(1) var shadow_maps: texture_depth_2d_array;
+@group(1) @binding(2) var shadow_sampler: sampler_comparison;
+@group(1) @binding(3) var<storage, read> shadows: Shadows;
+@group(3) @binding(0) var<uniform> transform: Transform;
+
+struct VertexInput {
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+    @location(3) tangent: vec4f,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4f,
+    @location(0) world_position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) uv: vec2f,
+    @location(3) tangent: vec4f,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    let world = transform.model * vec4f(in.position, 1.0);
+    output.clip_position = camera.view_projection * world;
+    output.world_position = world.xyz;
+    output.normal = (transform.normal * vec4f(in.normal, 0.0)).xyz;
+    output.uv = in.uv;
+    output.tangent = vec4f((transform.model * vec4f(in.tangent.xyz, 0.0)).xyz, in.tangent.w);
+    return output;
+}
+
+// Direction towards the light in xyz and distance/cone attenuation in w.
+fn light_incidence(light: Light, world_position: vec3f) -> vec4f {
+    let kind = u32(light.position.w);
+    if kind == LIGHT_DIRECTIONAL {
+        return vec4f(-normalize(light.direction.xyz), 1.0);
+    }
+    let to_light = light.position.xyz - world_position;
+    let distance2 = max(dot(to_light, to_light), 1e-4);
+    let l = to_light * inverseSqrt(distance2);
+    var attenuation = 1.0 / distance2;
+    let range = light.direction.w;
+    if range > 0.0 {
+        let ratio = distance2 / (range * range);
+        let window = clamp(1.0 - ratio * ratio, 0.0, 1.0);
+        attenuation *= window * window;
+    }
+    if kind == LIGHT_SPOT {
+        let cos_angle = dot(normalize(light.direction.xyz), -l);
+        attenuation *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+    }
+    return vec4f(l, attenuation);
+}
+
+// Fraction of the light reaching world_position: 1 when lit or unshadowed, 0 when fully occluded.
+fn shadow_factor(light: Light, world_position: vec3f, normal: vec3f) -> f32 {
+    let count = u32(light.shadow.y);
+    if count == 0u {
+        return 1.0;
+    }
+    var layer = u32(light.shadow.x);
+    if u32(light.position.w) == LIGHT_DIRECTIONAL {
+        let depth = -(camera.view * vec4f(world_position, 1.0)).z;
+        if depth > light.splits[count - 1u] {
+            return 1.0;
+        }
+        var cascade = 0u;
+        while cascade + 1u < count && depth > light.splits[cascade] {
+            cascade++;
+        }
+        layer += cascade;
+    }
+    let caster = shadows.casters[layer];
+    let offset = normal * shadows.params.y * caster.texel.x;
+    let clip = caster.view_proj * vec4f(world_position + offset, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2f(0.5, -0.5) + 0.5;
+    if any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) || ndc.z > 1.0 {
+        return 1.0;
+    }
+    let radius = i32(shadows.params.x);
+    var lit = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let st = uv + vec2f(f32(x), f32(y)) * shadows.params.z;
+            lit += textureSampleCompareLevel(shadow_maps, shadow_sampler, st, layer, ndc.z);
+        }
+    }
+    let taps = f32((2 * radius + 1) * (2 * radius + 1));
+    return lit / taps;
+}
@@ -0,0 +1,17 @@
+// Fragment output of weighted blended order-independent transparency (McGuire and Bavoil 2013)
+// into the targets of `WeightedBlendedOit`.
+struct OitOutput {
+    @location(0) accum: vec4f,
+    @location(1) revealage: f32,
+};
+
+// `color` is straight (not premultiplied); `view_depth` is the fragment's distance from the camera.
+fn oit_output(color: vec4f, view_depth: f32) -> OitOutput {
+    // Nearer and more opaque fragments weigh more, equation 9 of the paper.
+    let d = view_depth;
+    let w = color.a * clamp(10.0 / (1e-5 + pow(d / 5.0, 2.0) + pow(d / 200.0, 6.0)), 1e-2, 3e3);
+    var output: OitOutput;
+    output.accum = vec4f(color.rgb * color.a, color.a) * w;
+    output.revealage = color.a;
+    return output;
+}
@@ -0,0 +1,14 @@
+@group(0) @binding(0) var accum_texture: texture_2d<f32>;
+@group(0) @binding(1) var revealage_texture: texture_2d<f32>;
+
+// The weighted average color over the frame, covering it by 1 - revealage.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let p = vec2i(in.position.xy);
+    let revealage = textureLoad(revealage_texture, p, 0).r;
+    if revealage >= 1.0 {
+        discard;
+    }
+    let accum = textureLoad(accum_texture, p, 0);
+    return vec4f(accum.rgb / max(accum.a, 1e-5), 1.0 - revealage);
+}
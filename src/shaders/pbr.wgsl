@@ -0,0 +1,86 @@
+struct PbrMaterial {
+    base_color: vec4f,
+    // rgb emissive factor, w unused
+    emissive: vec4f,
+    // x metallic, y roughness, z occlusion strength, w normal scale
+    params: vec4f,
+};
+
+@group(2) @binding(0) var<uniform> material: PbrMaterial;
+@group(2) @binding(1) var base_color_texture: texture_2d<f32>;
+@group(2) @binding(2) var normal_texture: texture_2d<f32>;
+@group(2) @binding(3) var metallic_roughness_texture: texture_2d<f32>;
+@group(2) @binding(4) var occlusion_texture: texture_2d<f32>;
+@group(2) @binding(5) var emissive_texture: texture_2d<f32>;
+@group(2) @binding(6) var material_sampler: sampler;
+
+const PI: f32 = 3.14159265359;
+
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+fn visibility_smith_ggx(n_dot_v: f32, n_dot_l: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let gv = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - a2) + a2);
+    let gl = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - a2) + a2);
+    return 0.5 / max(gv + gl, 1e-5);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3f) -> vec3f {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4f {
+    let base_color = material.base_color * textureSample(base_color_texture, material_sampler, in.uv);
+    // glTF packs roughness in G and metalness in B.
+    let mr = textureSample(metallic_roughness_texture, material_sampler, in.uv);
+    let metallic = clamp(material.params.x * mr.b, 0.0, 1.0);
+    let roughness = clamp(material.params.y * mr.g, 0.04, 1.0);
+    let alpha = roughness * roughness;
+
+    // Sampled before branching so it stays in uniform control flow.
+    var tn = textureSample(normal_texture, material_sampler, in.uv).xyz * 2.0 - 1.0;
+    tn = vec3f(tn.xy * material.params.w, tn.z);
+    var n = normalize(in.normal);
+    // Only double-sided pipelines let back faces through.
+    if !front_facing {
+        n = -n;
+    }
+    let t = in.tangent.xyz - n * dot(n, in.tangent.xyz);
+    if dot(t, t) > 1e-8 {
+        let tangent = normalize(t);
+        let bitangent = cross(n, tangent) * in.tangent.w;
+        n = normalize(mat3x3f(tangent, bitangent, n) * tn);
+    }
+    let v = normalize(camera.position.xyz - in.world_position);
+    let n_dot_v = max(dot(n, v), 1e-4);
+
+    let f0 = mix(vec3f(0.04), base_color.rgb, metallic);
+    let diffuse_color = base_color.rgb * (1.0 - metallic);
+
+    var color = vec3f(0.0);
+    for (var i = 0u; i < lights.count.x; i++) {
+        let light = lights.items[i];
+        let incidence = light_incidence(light, in.world_position);
+        let l = incidence.xyz;
+        let n_dot_l = max(dot(n, l), 0.0);
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+        let h = normalize(l + v);
+        let f = fresnel_schlick(max(dot(v, h), 0.0), f0);
+        let specular = f * distribution_ggx(max(dot(n, h), 0.0), alpha) * visibility_smith_ggx(n_dot_v, n_dot_l, alpha);
+        let diffuse = (1.0 - f) * diffuse_color / PI;
+        let shadow = shadow_factor(light, in.world_position, normalize(in.normal));
+        color += (diffuse + specular) * light.color.rgb * incidence.w * shadow * n_dot_l;
+    }
+
+    let occlusion = mix(1.0, textureSample(occlusion_texture, material_sampler, in.uv).r, material.params.z);
+    color += lights.ambient.rgb * base_color.rgb * occlusion;
+    color += material.emissive.rgb * textureSample(emissive_texture, material_sampler, in.uv).rgb;
+    return vec4f(color, base_color.a);
+}
@@ -0,0 +1,47 @@
+// values: x threshold, y soft knee (0..1), z intensity
+@group(2) @binding(0) var bloom_texture: texture_2d<f32>;
+@group(2) @binding(1) var bloom_sampler: sampler;
+
+// Keeps the part of the image above the threshold, with a quadratic knee, while downsampling
+// with a 4-tap box filter.
+@fragment
+fn fs_extract(in: VertexOutput) -> @location(0) vec4f {
+    let texel = 1.0 / vec2f(textureDimensions(input_texture));
+    let color = 0.25 * (input_at(in.uv + texel * vec2f(-0.5, -0.5)) + input_at(in.uv + texel * vec2f(0.5, -0.5))
+        + input_at(in.uv + texel * vec2f(-0.5, 0.5)) + input_at(in.uv + texel * vec2f(0.5, 0.5))).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    let threshold = params.values.x;
+    let knee = max(threshold * params.values.y, 1e-4);
+    let soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    let contribution = max(soft * soft / (4.0 * knee), brightness - threshold) / max(brightness, 1e-4);
+    return vec4f(color * contribution, 1.0);
+}
+
+const WEIGHTS = array<f32, 5>(0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+fn blur(uv: vec2f, direction: vec2f) -> vec4f {
+    let step = direction / vec2f(textureDimensions(input_texture));
+    var color = input_at(uv) * WEIGHTS[0];
+    for (var i = 1; i < 5; i++) {
+        let offset = step * f32(i);
+        color += (input_at(uv + offset) + input_at(uv - offset)) * WEIGHTS[i];
+    }
+    return color;
+}
+
+@fragment
+fn fs_blur_horizontal(in: VertexOutput) -> @location(0) vec4f {
+    return blur(in.uv, vec2f(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_vertical(in: VertexOutput) -> @location(0) vec4f {
+    return blur(in.uv, vec2f(0.0, 1.0));
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4f {
+    let bloom = textureSampleLevel(bloom_texture, bloom_sampler, in.uv, 0.0).rgb;
+    let input = input_at(in.uv);
+    return vec4f(input.rgb + bloom * params.values.z, input.a);
+}
@@ -0,0 +1,13 @@
+// values: x strength (0 = off, 1 = full LUT)
+@group(2) @binding(0) var lut_texture: texture_3d<f32>;
+@group(2) @binding(1) var lut_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let input = input_at(in.uv);
+    let size = f32(textureDimensions(lut_texture).x);
+    // Sample texel centers so 0 and 1 hit the first and last LUT entries exactly.
+    let coord = clamp(input.rgb, vec3f(0.0), vec3f(1.0)) * ((size - 1.0) / size) + 0.5 / size;
+    let graded = textureSampleLevel(lut_texture, lut_sampler, coord, 0.0).rgb;
+    return vec4f(mix(input.rgb, graded, params.values.x), input.a);
+}
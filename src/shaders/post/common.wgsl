@@ -0,0 +1,19 @@
+// Appended to fullscreen.wgsl, which provides VertexOutput and the vertex stage.
+
+// Per-effect settings; the meaning of each component is documented by the effect.
+struct PostParams {
+    values: vec4f,
+    extra: vec4f,
+};
+
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+@group(0) @binding(1) var input_sampler: sampler;
+@group(1) @binding(0) var<uniform> params: PostParams;
+
+fn input_at(uv: vec2f) -> vec4f {
+    return textureSampleLevel(input_texture, input_sampler, uv, 0.0);
+}
+
+fn luminance(color: vec3f) -> f32 {
+    return dot(color, vec3f(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1,100 @@
+// values: x contrast threshold, y relative threshold, z subpixel blending
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let texel = 1.0 / vec2f(textureDimensions(input_texture));
+    let center = input_at(in.uv);
+    let m = luminance(center.rgb);
+    let n = luminance(input_at(in.uv + vec2f(0.0, -texel.y)).rgb);
+    let s = luminance(input_at(in.uv + vec2f(0.0, texel.y)).rgb);
+    let e = luminance(input_at(in.uv + vec2f(texel.x, 0.0)).rgb);
+    let w = luminance(input_at(in.uv + vec2f(-texel.x, 0.0)).rgb);
+    let ne = luminance(input_at(in.uv + vec2f(texel.x, -texel.y)).rgb);
+    let nw = luminance(input_at(in.uv + vec2f(-texel.x, -texel.y)).rgb);
+    let se = luminance(input_at(in.uv + vec2f(texel.x, texel.y)).rgb);
+    let sw = luminance(input_at(in.uv + vec2f(-texel.x, texel.y)).rgb);
+
+    let highest = max(max(max(n, s), max(e, w)), m);
+    let lowest = min(min(min(n, s), min(e, w)), m);
+    let contrast = highest - lowest;
+    if contrast < max(params.values.x, params.values.y * highest) {
+        return center;
+    }
+
+    // Subpixel blend factor from the 3x3 neighbourhood average.
+    let average = (2.0 * (n + s + e + w) + ne + nw + se + sw) / 12.0;
+    let subpixel = smoothstep(0.0, 1.0, clamp(abs(average - m) / contrast, 0.0, 1.0));
+    let subpixel_blend = subpixel * subpixel * params.values.z;
+
+    // Edge orientation and the side of the edge the pixel is on.
+    let horizontal = 2.0 * abs(n + s - 2.0 * m) + abs(ne + se - 2.0 * e) + abs(nw + sw - 2.0 * w);
+    let vertical = 2.0 * abs(e + w - 2.0 * m) + abs(ne + nw - 2.0 * n) + abs(se + sw - 2.0 * s);
+    let is_horizontal = horizontal >= vertical;
+    var step = select(texel.x, texel.y, is_horizontal);
+    let positive = select(e, s, is_horizontal);
+    let negative = select(w, n, is_horizontal);
+    let gradient_positive = abs(positive - m);
+    let gradient_negative = abs(negative - m);
+    var opposite = positive;
+    var gradient = gradient_positive;
+    if gradient_positive < gradient_negative {
+        step = -step;
+        opposite = negative;
+        gradient = gradient_negative;
+    }
+
+    // Walk along the edge in both directions until its end.
+    var edge_uv = in.uv;
+    var edge_step = vec2f(texel.x, 0.0);
+    if is_horizontal {
+        edge_uv.y += step * 0.5;
+    } else {
+        edge_uv.x += step * 0.5;
+        edge_step = vec2f(0.0, texel.y);
+    }
+    let edge_luma = (m + opposite) * 0.5;
+    let gradient_threshold = gradient * 0.25;
+    var p_uv = edge_uv + edge_step;
+    var p_delta = luminance(input_at(p_uv).rgb) - edge_luma;
+    var p_end = abs(p_delta) >= gradient_threshold;
+    var n_uv = edge_uv - edge_step;
+    var n_delta = luminance(input_at(n_uv).rgb) - edge_luma;
+    var n_end = abs(n_delta) >= gradient_threshold;
+    for (var i = 0; i < 12 && !(p_end && n_end); i++) {
+        if !p_end {
+            p_uv += edge_step;
+            p_delta = luminance(input_at(p_uv).rgb) - edge_luma;
+            p_end = abs(p_delta) >= gradient_threshold;
+        }
+        if !n_end {
+            n_uv -= edge_step;
+            n_delta = luminance(input_at(n_uv).rgb) - edge_luma;
+            n_end = abs(n_delta) >= gradient_threshold;
+        }
+    }
+    var p_distance = p_uv.x - in.uv.x;
+    var n_distance = in.uv.x - n_uv.x;
+    if !is_horizontal {
+        p_distance = p_uv.y - in.uv.y;
+        n_distance = in.uv.y - n_uv.y;
+    }
+    var distance = p_distance;
+    var delta_sign = p_delta >= 0.0;
+    if n_distance < p_distance {
+        distance = n_distance;
+        delta_sign = n_delta >= 0.0;
+    }
+    var edge_blend = 0.0;
+    if delta_sign != (m - edge_luma >= 0.0) {
+        edge_blend = 0.5 - distance / (p_distance + n_distance);
+    }
+
+    let blend = max(edge_blend, subpixel_blend);
+    var uv = in.uv;
+    if is_horizontal {
+        uv.y += step * blend;
+    } else {
+        uv.x += step * blend;
+    }
+    return vec4f(input_at(uv).rgb, center.a);
+}
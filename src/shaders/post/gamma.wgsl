@@ -0,0 +1,7 @@
+// values: x gamma; only needed for non-sRGB output formats
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let input = input_at(in.uv);
+    return vec4f(pow(max(input.rgb, vec3f(0.0)), vec3f(1.0 / params.values.x)), input.a);
+}
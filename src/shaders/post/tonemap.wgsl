@@ -0,0 +1,24 @@
+// values: x exposure (linear multiplier), y operator (0 clamp, 1 Reinhard, 2 ACES fitted)
+
+fn aces_fitted(x: vec3f) -> vec3f {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3f(0.0), vec3f(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let input = input_at(in.uv);
+    let color = input.rgb * params.values.x;
+    var mapped = clamp(color, vec3f(0.0), vec3f(1.0));
+    let curve = u32(params.values.y);
+    if curve == 1u {
+        mapped = color / (1.0 + color);
+    } else if curve == 2u {
+        mapped = aces_fitted(color);
+    }
+    return vec4f(mapped, input.a);
+}
@@ -0,0 +1,11 @@
+// values: x strength, y radius where darkening starts, z softness
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4f {
+    let input = input_at(in.uv);
+    let size = vec2f(textureDimensions(input_texture));
+    // Distance from the center with the aspect ratio corrected, 1 at the middle of the short edges.
+    let offset = (in.uv - 0.5) * 2.0 * vec2f(size.x / min(size.x, size.y), size.y / min(size.x, size.y));
+    let falloff = smoothstep(params.values.y, params.values.y + max(params.values.z, 1e-4), length(offset));
+    return vec4f(input.rgb * (1.0 - params.values.x * falloff), input.a);
+}
@@ -0,0 +1,17 @@
+struct ShadowCaster {
+    view_proj: mat4x4f,
+    texel: vec4f,
+};
+
+struct Transform {
+    model: mat4x4f,
+    normal: mat4x4f,
+};
+
+@group(0) @binding(0) var<uniform> caster: ShadowCaster;
+@group(1) @binding(0) var<uniform> transform: Transform;
+
+@vertex
+fn vs_main(@location(0) position: vec3f) -> @builtin(position) vec4f {
+    return caster.view_proj * transform.model * vec4f(position, 1.0);
+}
@@ -0,0 +1,398 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Rad, Vector3};
+
+use crate::camera::{Camera, Projection};
+use crate::depth::DepthConfig;
+use crate::geometry::Vertex;
+use crate::helpers::IWgpuInit;
+use crate::light::{Light, Lighting};
+
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Uniform slots in the caster buffer are addressed with dynamic offsets, which must be
+/// multiples of `min_uniform_buffer_offset_alignment` (256 by default).
+const CASTER_UNIFORM_STRIDE: u64 = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Width and height of every shadow map layer.
+    pub resolution: u32,
+    /// Layers available in the shadow texture array; directional lights take one per cascade,
+    /// spot lights one each. Lights that do not fit cast no shadow.
+    pub max_layers: u32,
+    /// Cascades per directional light, 1 to 4.
+    pub cascade_count: u32,
+    /// Blend between uniform (0) and logarithmic (1) cascade splits.
+    pub cascade_split_lambda: f32,
+    /// Directional shadows stop this far from the camera.
+    pub max_distance: f32,
+    /// Extra depth behind each cascade so casters outside the view still throw shadows.
+    pub caster_margin: f32,
+    /// Rasterizer depth bias applied while rendering the shadow maps.
+    pub depth_bias: i32,
+    pub slope_scale_bias: f32,
+    /// Offset along the surface normal, in shadow map texels, applied when sampling.
+    pub normal_offset: f32,
+    /// PCF kernel radius in texels; 0 is a single (bilinear) comparison tap.
+    pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: 2048,
+            max_layers: 8,
+            cascade_count: 4,
+            cascade_split_lambda: 0.75,
+            max_distance: 50.0,
+            caster_margin: 20.0,
+            depth_bias: 2,
+            slope_scale_bias: 2.0,
+            normal_offset: 1.5,
+            pcf_radius: 1,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub fn disabled() -> Self {
+        Self { enabled: false, resolution: 1, max_layers: 1, ..Default::default() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct ShadowCaster {
+    pub view_proj: [[f32; 4]; 4],
+    /// x is the world-space size of one shadow map texel.
+    pub texel: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+struct ShadowHeader {
+    /// pcf radius, normal offset, 1 / resolution, unused
+    params: [f32; 4],
+}
+
+/// Where a light's shadow lives in the array, see `GpuLight::shadow` and `GpuLight::splits`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShadowAssignment {
+    pub first_layer: u32,
+    pub layer_count: u32,
+    /// View-space far distance of each cascade.
+    pub splits: [f32; 4],
+}
+
+/// Depth texture array holding one layer per shadow view, plus the depth-only pipeline that
+/// fills it. Sampled through the lights bind group (group 1, bindings 1 to 3).
+pub struct ShadowMaps {
+    pub settings: ShadowSettings,
+    pub texture: wgpu::Texture,
+    pub array_view: wgpu::TextureView,
+    pub layer_views: Vec<wgpu::TextureView>,
+    pub sampler: wgpu::Sampler,
+    pub casters_buffer: wgpu::Buffer,
+    pub casters: Vec<ShadowCaster>,
+    caster_uniform: wgpu::Buffer,
+    caster_bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl ShadowMaps {
+    pub fn new(init: &IWgpuInit, lighting: &Lighting, settings: ShadowSettings) -> Self {
+        let settings = if settings.enabled { settings } else { ShadowSettings::disabled() };
+        let layers = settings.max_layers.max(1);
+        let texture = init.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Maps"),
+            size: wgpu::Extent3d {
+                width: settings.resolution.max(1),
+                height: settings.resolution.max(1),
+                depth_or_array_layers: layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..layers)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let sampler = init.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let casters_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Casters Buffer"),
+            size: (std::mem::size_of::<ShadowHeader>() + layers as usize * std::mem::size_of::<ShadowCaster>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let caster_uniform = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Pass Uniform Buffer"),
+            size: layers as u64 * CASTER_UNIFORM_STRIDE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let caster_layout = init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Pass Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ShadowCaster>() as u64),
+                },
+                count: None,
+            }],
+        });
+        let caster_bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Pass Bind Group"),
+            layout: &caster_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &caster_uniform,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<ShadowCaster>() as u64),
+                }),
+            }],
+        });
+
+        let shader = init.device.create_shader_module(wgpu::include_wgsl!("shaders/shadow.wgsl"));
+        let pipeline_layout = init.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&caster_layout, &lighting.transform_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = init.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[Vertex::vertex_buffer_layout()],
+                compilation_options: Default::default(),
+            },
+            // Depth only: no fragment stage, no color targets.
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                // Single-sided geometry such as planes has to cast shadows too.
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: settings.depth_bias,
+                    slope_scale: settings.slope_scale_bias,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            settings,
+            texture,
+            array_view,
+            layer_views,
+            sampler,
+            casters_buffer,
+            casters: Vec::new(),
+            caster_uniform,
+            caster_bind_group,
+            pipeline,
+        }
+    }
+
+    /// Fits a shadow view to every directional and spot light in order, uploads the caster
+    /// matrices and returns each light's layer assignment (`layer_count == 0` means unshadowed).
+    pub fn update(&mut self, init: &IWgpuInit, lights: &[Light], camera: &Camera) -> Vec<ShadowAssignment> {
+        self.casters.clear();
+        let mut assignments = vec![ShadowAssignment::default(); lights.len()];
+        if !self.settings.enabled {
+            return assignments;
+        }
+        let max_layers = self.layer_views.len();
+        for (light, assignment) in lights.iter().zip(assignments.iter_mut()) {
+            match *light {
+                Light::Directional { direction, .. } => {
+                    let cascades = self.settings.cascade_count.clamp(1, 4) as usize;
+                    if self.casters.len() + cascades > max_layers {
+                        continue;
+                    }
+                    let splits = cascade_splits(camera, &self.settings, cascades);
+                    assignment.first_layer = self.casters.len() as u32;
+                    assignment.layer_count = cascades as u32;
+                    let mut near = camera_near(camera);
+                    for (i, &far) in splits.iter().enumerate() {
+                        self.casters.push(self.directional_caster(camera, direction.normalize(), near, far));
+                        assignment.splits[i] = far;
+                        near = far;
+                    }
+                }
+                Light::Spot { position, direction, range, outer_angle, .. } => {
+                    if self.casters.len() + 1 > max_layers {
+                        continue;
+                    }
+                    let far = if range > 0.0 { range } else { self.settings.max_distance };
+                    let mut view = Camera::perspective(Rad((outer_angle.0 * 2.0).min(3.0)), 1.0, 0.05, far);
+                    view.position = position;
+                    view.look_at(position + direction.normalize(), stable_up(direction.normalize()));
+                    // Texel footprint varies with distance; a quarter of the range is a fair middle.
+                    let texel = 2.0 * outer_angle.0.tan() * far * 0.25 / self.settings.resolution as f32;
+                    assignment.first_layer = self.casters.len() as u32;
+                    assignment.layer_count = 1;
+                    self.casters.push(ShadowCaster {
+                        view_proj: view.view_projection_matrix(&DepthConfig::default()).into(),
+                        texel: [texel, 0.0, 0.0, 0.0],
+                    });
+                }
+                Light::Point { .. } => {}
+            }
+        }
+
+        let header = ShadowHeader {
+            params: [
+                self.settings.pcf_radius as f32,
+                self.settings.normal_offset,
+                1.0 / self.settings.resolution as f32,
+                0.0,
+            ],
+        };
+        init.queue.write_buffer(&self.casters_buffer, 0, bytemuck::bytes_of(&header));
+        if !self.casters.is_empty() {
+            let offset = std::mem::size_of::<ShadowHeader>() as u64;
+            init.queue.write_buffer(&self.casters_buffer, offset, bytemuck::cast_slice(&self.casters));
+        }
+        for (i, caster) in self.casters.iter().enumerate() {
+            init.queue.write_buffer(&self.caster_uniform, i as u64 * CASTER_UNIFORM_STRIDE, bytemuck::bytes_of(caster));
+        }
+        assignments
+    }
+
+    /// Records one depth pass per active shadow layer. `draw` is called inside each pass with
+    /// the shadow pipeline and group 0 already set; it should bind each object's transform at
+    /// group 1 and draw its mesh.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, draw: &mut dyn FnMut(&mut wgpu::RenderPass)) {
+        for (i, view) in self.layer_views.iter().enumerate().take(self.casters.len()) {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.caster_bind_group, &[(i as u64 * CASTER_UNIFORM_STRIDE) as u32]);
+            draw(&mut render_pass);
+        }
+    }
+
+    /// Orthographic view around the bounding sphere of the camera frustum slice `near..far`,
+    /// snapped to whole texels so the shadow does not shimmer while the camera moves.
+    fn directional_caster(&self, camera: &Camera, direction: Vector3<f32>, near: f32, far: f32) -> ShadowCaster {
+        let corners = frustum_corners(camera, near, far);
+        let center = corners.iter().fold(Vector3::new(0.0, 0.0, 0.0), |acc, c| acc + c.to_vec()) / 8.0;
+        let radius = corners.iter().map(|c| (c.to_vec() - center).magnitude()).fold(0.0f32, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+        let texel = 2.0 * radius / self.settings.resolution as f32;
+
+        let mut view = Camera::orthographic(2.0 * radius, 1.0, 0.0, 2.0 * radius + self.settings.caster_margin);
+        view.look_at(Point3::from_vec(direction), stable_up(direction));
+        let (right, up) = (view.right(), view.up());
+        let snap = |v: f32| (v / texel).floor() * texel;
+        let center = right * snap(center.dot(right)) + up * snap(center.dot(up)) + direction * center.dot(direction);
+        view.position = Point3::from_vec(center - direction * (radius + self.settings.caster_margin));
+
+        ShadowCaster { view_proj: view.view_projection_matrix(&DepthConfig::default()).into(), texel: [texel, 0.0, 0.0, 0.0] }
+    }
+}
+
+fn stable_up(direction: Vector3<f32>) -> Vector3<f32> {
+    if direction.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    }
+}
+
+fn camera_near(camera: &Camera) -> f32 {
+    match camera.projection {
+        Projection::Perspective { znear, .. } | Projection::Orthographic { znear, .. } => znear,
+    }
+}
+
+/// Far distance of each cascade, mixing logarithmic and uniform splits.
+fn cascade_splits(camera: &Camera, settings: &ShadowSettings, cascades: usize) -> Vec<f32> {
+    let near = camera_near(camera);
+    let far = match camera.projection {
+        Projection::Perspective { zfar, .. } | Projection::Orthographic { zfar, .. } => zfar.min(settings.max_distance),
+    };
+    let lambda = settings.cascade_split_lambda.clamp(0.0, 1.0);
+    (1..=cascades)
+        .map(|i| {
+            let t = i as f32 / cascades as f32;
+            let log = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+fn frustum_corners(camera: &Camera, near: f32, far: f32) -> [Point3<f32>; 8] {
+    let (forward, right, up) = (camera.forward(), camera.right(), camera.up());
+    let half_extent = |d: f32| match camera.projection {
+        Projection::Perspective { fovy, .. } => {
+            let h = d * (fovy.0 * 0.5).tan();
+            (h * camera.aspect, h)
+        }
+        Projection::Orthographic { height, .. } => (height * 0.5 * camera.aspect, height * 0.5),
+    };
+    let mut corners = [camera.position; 8];
+    for (i, d) in [near, far].into_iter().enumerate() {
+        let (w, h) = half_extent(d);
+        let center = camera.position + forward * d;
+        corners[i * 4] = center - right * w - up * h;
+        corners[i * 4 + 1] = center + right * w - up * h;
+        corners[i * 4 + 2] = center + right * w + up * h;
+        corners[i * 4 + 3] = center - right * w + up * h;
+    }
+    corners
+}
@@ -0,0 +1,66 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Matrix, Matrix4, SquareMatrix};
+use wgpu::util::DeviceExt;
+
+use crate::helpers::IWgpuInit;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct TransformUniform {
+    pub model: [[f32; 4]; 4],
+    /// Inverse transpose of `model`, kept as a mat4 to avoid mat3 padding rules.
+    pub normal: [[f32; 4]; 4],
+}
+
+impl From<Matrix4<f32>> for TransformUniform {
+    fn from(model: Matrix4<f32>) -> Self {
+        let normal = model.invert().unwrap_or(Matrix4::identity()).transpose();
+        Self { model: model.into(), normal: normal.into() }
+    }
+}
+
+/// Per-object model matrix at binding 0, visible to the vertex stage:
+///
+/// ```wgsl
+/// struct Transform { model: mat4x4f, normal: mat4x4f };
+/// ```
+pub fn create_transform_bind_group_layout(init: &IWgpuInit) -> wgpu::BindGroupLayout {
+    init.device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Transform Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    })
+}
+
+pub struct TransformBinding {
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl TransformBinding {
+    pub fn new(init: &IWgpuInit, layout: &wgpu::BindGroupLayout, model: Matrix4<f32>) -> Self {
+        let buffer = init.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transform Uniform Buffer"),
+            contents: bytemuck::bytes_of(&TransformUniform::from(model)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Transform Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        });
+        Self { buffer, bind_group }
+    }
+
+    pub fn update(&self, init: &IWgpuInit, model: Matrix4<f32>) {
+        init.queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&TransformUniform::from(model)));
+    }
+}
@@ -0,0 +1,52 @@
+use cgmath::{InnerSpace, Point3};
+
+use crate::camera::Camera;
+
+/// Distance of `point` in front of the camera along its view direction.
+pub fn view_depth(camera: &Camera, point: Point3<f32>) -> f32 {
+    (point - camera.position).dot(camera.forward())
+}
+
+/// Translucent draws collected for a frame. Blending is order dependent, so they are drawn after
+/// the opaque ones, farthest first, with a pipeline that tests depth but does not write it (see
+/// `IRenderPipeline::depth_write_enabled` and `IRenderPipeline::blend`). Sorting is per object,
+/// so intersecting or concave translucent objects can still show artifacts.
+pub struct TransparentQueue<T> {
+    draws: Vec<(f32, T)>,
+}
+
+impl<T> Default for TransparentQueue<T> {
+    fn default() -> Self {
+        Self { draws: Vec::new() }
+    }
+}
+
+impl<T> TransparentQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `draw` for an object centered at `center` in world space.
+    pub fn push(&mut self, camera: &Camera, center: Point3<f32>, draw: T) {
+        self.draws.push((view_depth(camera, center), draw));
+    }
+
+    pub fn len(&self) -> usize {
+        self.draws.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draws.is_empty()
+    }
+
+    /// Empties the queue, farthest draws first.
+    pub fn drain_back_to_front(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.draws.sort_by(|a, b| b.0.total_cmp(&a.0));
+        self.draws.drain(..).map(|(_, draw)| draw)
+    }
+
+    /// Empties the queue in the order draws were pushed, e.g. to compare against sorting.
+    pub fn drain_unsorted(&mut self) -> impl Iterator<Item = T> + '_ {
+        self.draws.drain(..).map(|(_, draw)| draw)
+    }
+}
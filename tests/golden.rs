@@ -0,0 +1,289 @@
+//! Renders the example scenes offscreen on a software adapter and compares the frames with the
+//! reference images in `tests/golden`. Failing scenes leave `actual`, `expected` and `diff`
+//! images in `target/tmp/golden`. Run with `WGPU_GP_BLESS=1` to write the references instead.
+//! Without a software adapter the tests fail unless `WGPU_GP_ALLOW_SKIP=1` lets them pass without
+//! checking anything.
+
+// Examples are separate crates, so those sharing a module each include their own copy.
+#![allow(clippy::duplicate_mod)]
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use image::RgbaImage;
+use wgpu_gp::{
+    app::{self, Example},
+    colormap::Colormap,
+    compare::{self, DifferenceMetric, ImageComparison},
+    depth::{self, DepthConfig},
+    helpers::IWgpuInit,
+};
+use winit::dpi::PhysicalSize;
+
+#[allow(dead_code)]
+#[path = "../examples/ch02/triangle.rs"]
+mod triangle;
+#[allow(dead_code)]
+#[path = "../examples/ch02/triangle_primitive.rs"]
+mod triangle_primitive;
+#[allow(dead_code)]
+#[path = "../examples/ch02/triangle_gpu_buffer.rs"]
+mod triangle_gpu_buffer;
+#[allow(dead_code)]
+#[path = "../examples/ch02/rectangle.rs"]
+mod rectangle;
+#[allow(dead_code)]
+#[path = "../examples/ch02/rectangle_index_buffer.rs"]
+mod rectangle_index_buffer;
+#[allow(dead_code)]
+#[path = "../examples/ch02/triangle_instances.rs"]
+mod triangle_instances;
+#[allow(dead_code)]
+#[path = "../examples/ch03/camera.rs"]
+mod camera;
+#[allow(dead_code)]
+#[path = "../examples/ch03/render_to_texture.rs"]
+mod render_to_texture;
+#[allow(dead_code)]
+#[path = "../examples/ch04/lighting.rs"]
+mod lighting;
+#[allow(dead_code)]
+#[path = "../examples/ch05/postprocess.rs"]
+mod postprocess;
+#[allow(dead_code)]
+#[path = "../examples/ch06/transparency.rs"]
+mod transparency;
+#[allow(dead_code)]
+#[path = "../examples/imshow/imshow.rs"]
+mod imshow;
+#[allow(dead_code)]
+#[path = "../examples/imshow/imdiff.rs"]
+mod imdiff;
+
+const SIZE: PhysicalSize<u32> = PhysicalSize { width: 320, height: 240 };
+/// Frames rendered before the read back, so animated scenes have moved.
+const FRAMES: u32 = 3;
+/// Largest channel difference, out of 255, that still counts as equal.
+const TOLERANCE: f32 = 3.0 / 255.0;
+/// Share of pixels allowed to differ by more than the tolerance, for rasterization differences
+/// between software renderer versions.
+const MAX_DIFFERING: f64 = 0.002;
+
+type Build = for<'a> fn(IWgpuInit<'a>, &[String]) -> anyhow::Result<Box<dyn Example + 'a>>;
+
+/// The software adapter is slow; one scene at a time keeps the tests from starving each other.
+static GPU: Mutex<()> = Mutex::new(());
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+fn asset(path: &str) -> String {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(path).display().to_string()
+}
+
+fn render(build: Build, args: &[&str], depth: DepthConfig, prepare: impl FnOnce(&mut dyn Example)) -> Option<RgbaImage> {
+    let _gpu = GPU.lock().unwrap_or_else(|e| e.into_inner());
+    let mut init = match pollster::block_on(IWgpuInit::new_headless(SIZE, 1, None, wgpu::Backends::all(), true)) {
+        Ok(init) => init,
+        Err(e) => return skip(&format!("no software adapter: {:#}", e)),
+    };
+    if let Err(e) = init.set_depth(depth) {
+        eprintln!("skipping, {:#}", e);
+        return None;
+    }
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    let mut example = build(init, &args).expect("failed to build the example");
+    prepare(example.as_mut());
+    Some(app::render_frames(example.as_mut(), FRAMES, app::FIXED_TIMESTEP).expect("failed to render"))
+}
+
+/// Scenes the machine cannot render fail, so a green run means they were checked, unless
+/// `WGPU_GP_ALLOW_SKIP` is set.
+fn skip(reason: &str) -> Option<RgbaImage> {
+    if std::env::var_os("WGPU_GP_ALLOW_SKIP").is_none() {
+        panic!("{}; set WGPU_GP_ALLOW_SKIP=1 to skip scenes this machine cannot render", reason);
+    }
+    eprintln!("skipping, {}", reason);
+    None
+}
+
+/// Renders a scene and checks it against `tests/golden/<name>.png`.
+fn check(name: &str, build: Build, args: &[&str]) {
+    check_prepared(name, build, args, DepthConfig::default(), |_| {});
+}
+
+/// Like `check`, with another depth setup and after `prepare` has changed the example.
+fn check_prepared(name: &str, build: Build, args: &[&str], depth: DepthConfig, prepare: impl FnOnce(&mut dyn Example)) {
+    let Some(actual) = render(build, args, depth, prepare) else {
+        return;
+    };
+    let reference = golden_dir().join(format!("{}.png", name));
+    if std::env::var_os("WGPU_GP_BLESS").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference).unwrap();
+        return;
+    }
+
+    let output = output_dir();
+    std::fs::create_dir_all(&output).unwrap();
+    let actual_path = output.join(format!("{}-actual.png", name));
+    let Ok(expected) = image::open(&reference) else {
+        actual.save(&actual_path).unwrap();
+        panic!(
+            "{}: no reference at {}; rendered {}, run with WGPU_GP_BLESS=1 to accept it",
+            name,
+            reference.display(),
+            actual_path.display()
+        );
+    };
+    let expected = expected.to_rgba8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{}: reference has a different size", name);
+
+    let (a, b) = (compare::rgba8_pixels(&actual), compare::rgba8_pixels(&expected));
+    let comparison = ImageComparison::new(&a, &b, SIZE.width, SIZE.height, TOLERANCE).unwrap();
+    if comparison.differing_pixels as f64 > MAX_DIFFERING * comparison.pixel_count() as f64 {
+        let diff = compare::difference_map(&a, &b, DifferenceMetric::Absolute, 0.1, Colormap::Magma);
+        let diff_path = output.join(format!("{}-diff.png", name));
+        actual.save(&actual_path).unwrap();
+        expected.save(output.join(format!("{}-expected.png", name))).unwrap();
+        RgbaImage::from_raw(SIZE.width, SIZE.height, diff).unwrap().save(&diff_path).unwrap();
+        panic!("{}: frame differs from the reference, see {}\n{}", name, diff_path.display(), comparison);
+    }
+}
+
+#[test]
+fn triangle() {
+    check("triangle", triangle::example, &[]);
+}
+
+#[test]
+fn triangle_primitive() {
+    for primitive in ["triangle-list", "triangle-strip", "line-list", "line-strip"] {
+        check(&format!("triangle_primitive-{}", primitive), triangle_primitive::example, &[primitive]);
+    }
+}
+
+#[test]
+fn triangle_gpu_buffer() {
+    check("triangle_gpu_buffer", triangle_gpu_buffer::example, &[]);
+}
+
+#[test]
+fn rectangle() {
+    check("rectangle", rectangle::example, &[]);
+}
+
+#[test]
+fn rectangle_index_buffer() {
+    check("rectangle_index_buffer", rectangle_index_buffer::example, &[]);
+}
+
+#[test]
+fn triangle_instances() {
+    check("triangle_instances", triangle_instances::example, &[]);
+}
+
+#[test]
+fn camera() {
+    for shape in ["cube", "uv-sphere", "torus"] {
+        check(&format!("camera-{}", shape), camera::example, &[shape]);
+    }
+}
+
+/// The outline is drawn only outside the stencil mark the shape leaves.
+#[test]
+fn stencil_outline() {
+    check("camera-cube-outline", camera::example, &["cube", "orbit", "outline"]);
+}
+
+/// Switching to MSAA and back rebuilds the pipeline and attachments for the count in effect.
+#[test]
+fn sample_count_change() {
+    let switch = |example: &mut dyn Example| {
+        assert_eq!(example.set_sample_count(4), Some(4));
+        app::render_frames(example, 1, app::FIXED_TIMESTEP).expect("failed to render with MSAA");
+        assert_eq!(example.set_sample_count(1), Some(1));
+    };
+    check_prepared("triangle_gpu_buffer", triangle_gpu_buffer::example, &[], DepthConfig::default(), switch);
+    check_prepared("camera-cube", camera::example, &["cube"], DepthConfig::default(), switch);
+    check_prepared("transparency-oit", transparency::example, &["oit"], DepthConfig::default(), switch);
+}
+
+/// Other depth formats and reversed-Z resolve visibility as the default setup does.
+#[test]
+fn depth_formats() {
+    for format in depth::DEPTH_FORMATS {
+        for reversed_z in [false, true] {
+            let depth = DepthConfig { format, reversed_z };
+            check_prepared("camera-torus", camera::example, &["torus"], depth, |_| {});
+            if depth.has_stencil() {
+                check_prepared("camera-cube-outline", camera::example, &["cube", "orbit", "outline"], depth, |_| {});
+            }
+        }
+    }
+    let depth = DepthConfig { format: wgpu::TextureFormat::Depth32Float, reversed_z: true };
+    check_prepared("lighting-pbr", lighting::example, &["pbr"], depth, |_| {});
+    check_prepared("render_to_texture", render_to_texture::example, &[], depth, |_| {});
+    check_prepared("transparency-oit", transparency::example, &["oit"], depth, |_| {});
+}
+
+#[test]
+fn render_to_texture() {
+    check("render_to_texture", render_to_texture::example, &[]);
+}
+
+#[test]
+fn lighting() {
+    check("lighting-pbr", lighting::example, &["pbr"]);
+    check("lighting-phong", lighting::example, &["phong"]);
+    check("lighting-noshadow", lighting::example, &["pbr", "noshadow"]);
+}
+
+#[test]
+fn postprocess() {
+    check("postprocess", postprocess::example, &[]);
+}
+
+#[test]
+fn transparency() {
+    check("transparency-alpha", transparency::example, &["alpha"]);
+    // Premultiplying the colors instead gives the same blend.
+    check("transparency-alpha", transparency::example, &["premultiplied"]);
+    check("transparency-unsorted", transparency::example, &["alpha", "unsorted"]);
+    for mode in ["additive", "multiply", "screen"] {
+        check(&format!("transparency-{}", mode), transparency::example, &[mode]);
+    }
+    // Weighted blended OIT does not depend on the draw order.
+    check("transparency-oit", transparency::example, &["oit"]);
+    check("transparency-oit", transparency::example, &["oit", "unsorted"]);
+}
+
+#[test]
+fn imshow() {
+    check("imshow", imshow::example, &[&asset("examples/imshow/happy-tree.png")]);
+    check("imshow-sobel", imshow::example, &["--filter", "sobel", &asset("examples/imshow/happy-tree.png")]);
+}
+
+#[test]
+fn imdiff() {
+    // The second image has an inverted square to show up in the comparison.
+    let tree = asset("examples/imshow/happy-tree.png");
+    let mut changed = image::open(&tree).unwrap().to_rgba8();
+    for y in 10..40 {
+        for x in 10..40 {
+            let p = changed.get_pixel_mut(x, y);
+            p.0 = [255 - p.0[0], 255 - p.0[1], 255 - p.0[2], p.0[3]];
+        }
+    }
+    std::fs::create_dir_all(output_dir()).unwrap();
+    let changed_path = output_dir().join("happy-tree-changed.png");
+    changed.save(&changed_path).unwrap();
+    check("imdiff", imdiff::example, &[&tree, &changed_path.display().to_string()]);
+}